@@ -0,0 +1,27 @@
+-- 004: 添加电量快速下降检测配置
+-- 为设备配置添加快速耗电阈值和滑动窗口长度，并为已有用户补建默认的快速耗电预警规则
+
+-- ============================================
+-- 1. 设备配置新增字段
+-- ============================================
+-- 放电速率阈值（%/小时），超过即触发 rapid_drain 预警
+ALTER TABLE device_configs
+    ADD COLUMN IF NOT EXISTS rapid_drain_threshold DOUBLE PRECISION NOT NULL DEFAULT 20.0
+        CHECK (rapid_drain_threshold > 0);
+
+-- 计算放电速率的滑动窗口（分钟）
+ALTER TABLE device_configs
+    ADD COLUMN IF NOT EXISTS rapid_drain_window_minutes INTEGER NOT NULL DEFAULT 30
+        CHECK (rapid_drain_window_minutes BETWEEN 5 AND 1440);
+
+-- ============================================
+-- 2. 已有用户的默认快速耗电预警规则
+-- ============================================
+-- 新用户注册时会创建同样的规则；已有该类型规则的用户跳过（迁移可重复执行）
+INSERT INTO alert_rules (id, user_id, name, alert_type, level, cooldown_minutes, enabled, created_at, updated_at)
+SELECT gen_random_uuid(), u.id, '电量快速下降', 'rapid_drain', 'warning', 60, TRUE, NOW(), NOW()
+FROM users u
+WHERE NOT EXISTS (
+    SELECT 1 FROM alert_rules r
+    WHERE r.user_id = u.id AND r.alert_type = 'rapid_drain'
+);
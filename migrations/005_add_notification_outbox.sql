@@ -0,0 +1,31 @@
+-- 005: 通知发件箱
+-- notification_history 中 pending / failed 记录作为发件箱，由后台任务投递并按指数退避重试，
+-- 超过最大尝试次数后转为 dead（死信），可由管理员重放
+
+-- ============================================
+-- 1. 发件箱字段
+-- ============================================
+-- 投递内容快照（预警事件及设备名称）
+ALTER TABLE notification_history ADD COLUMN IF NOT EXISTS payload JSONB;
+
+-- 已尝试次数
+ALTER TABLE notification_history ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 0;
+
+-- 下次投递时间
+ALTER TABLE notification_history ADD COLUMN IF NOT EXISTS next_attempt_at TIMESTAMPTZ;
+
+-- 最近一次投递时间
+ALTER TABLE notification_history ADD COLUMN IF NOT EXISTS last_attempt_at TIMESTAMPTZ;
+
+-- 投递租约（防止多实例重复投递）
+ALTER TABLE notification_history ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ;
+
+-- ============================================
+-- 2. 索引
+-- ============================================
+CREATE INDEX IF NOT EXISTS idx_notification_history_outbox
+    ON notification_history(next_attempt_at)
+    WHERE status IN ('pending', 'failed');
+
+COMMENT ON COLUMN notification_history.status IS '''pending'', ''sent'', ''failed'', ''skipped'', ''dead''';
+COMMENT ON COLUMN notification_history.payload IS '投递内容快照 (JSONB)';
@@ -0,0 +1,59 @@
+-- 006: 预警规则作用范围与自定义阈值
+-- 规则可作用于全部设备、指定设备列表或指定设备类型，并可携带自己的阈值和比较方式；
+-- 同一类型允许存在多条启用规则，触发时选用最具体的匹配规则
+
+-- ============================================
+-- 1. 枚举类型
+-- ============================================
+DO $$ BEGIN
+    CREATE TYPE alert_rule_scope AS ENUM ('all', 'devices', 'device_type');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE alert_comparison AS ENUM ('lt', 'lte', 'gt', 'gte');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+-- ============================================
+-- 2. 预警规则新增字段
+-- ============================================
+-- 作用范围
+ALTER TABLE alert_rules
+    ADD COLUMN IF NOT EXISTS scope alert_rule_scope NOT NULL DEFAULT 'all';
+
+-- scope = 'devices' 时的设备列表
+ALTER TABLE alert_rules
+    ADD COLUMN IF NOT EXISTS device_ids UUID[] NOT NULL DEFAULT '{}';
+
+-- scope = 'device_type' 时的设备类型
+ALTER TABLE alert_rules
+    ADD COLUMN IF NOT EXISTS device_type VARCHAR(50);
+
+-- 规则阈值与比较方式（为空时使用设备配置的阈值和预警类型的默认比较方式）
+ALTER TABLE alert_rules
+    ADD COLUMN IF NOT EXISTS threshold DOUBLE PRECISION;
+
+ALTER TABLE alert_rules
+    ADD COLUMN IF NOT EXISTS comparison alert_comparison;
+
+ALTER TABLE alert_rules DROP CONSTRAINT IF EXISTS alert_rules_scope_check;
+ALTER TABLE alert_rules ADD CONSTRAINT alert_rules_scope_check CHECK (
+    (scope = 'all' AND cardinality(device_ids) = 0 AND device_type IS NULL)
+    OR (scope = 'devices' AND cardinality(device_ids) > 0 AND device_type IS NULL)
+    OR (scope = 'device_type' AND cardinality(device_ids) = 0 AND device_type IS NOT NULL)
+);
+
+-- ============================================
+-- 3. 索引
+-- ============================================
+-- 不再限制每种类型只有一条启用规则
+DROP INDEX IF EXISTS idx_alert_rules_user_type_enabled;
+
+CREATE INDEX IF NOT EXISTS idx_alert_rules_device_ids ON alert_rules USING GIN (device_ids);
+
+COMMENT ON COLUMN alert_rules.scope IS '作用范围: all / devices / device_type';
+COMMENT ON COLUMN alert_rules.threshold IS '规则阈值，为空时使用设备配置';
+COMMENT ON COLUMN alert_rules.comparison IS '比较方式，为空时使用预警类型默认值';
@@ -0,0 +1,22 @@
+-- 007: 预警自动解决
+-- 上报数据恢复正常（超过阈值加回差）时自动解决未关闭的预警，并发送解决通知
+
+-- ============================================
+-- 1. 设备配置新增回差字段
+-- ============================================
+-- 电量回差（百分点）：电量回升到 阈值 + 回差 以上才解决低电量 / 临界电量预警
+ALTER TABLE device_configs
+    ADD COLUMN IF NOT EXISTS battery_hysteresis INTEGER NOT NULL DEFAULT 5
+        CHECK (battery_hysteresis BETWEEN 0 AND 50);
+
+-- 温度回差（摄氏度）：温度回落到 阈值 - 回差 以下才解决高温预警
+ALTER TABLE device_configs
+    ADD COLUMN IF NOT EXISTS temperature_hysteresis DOUBLE PRECISION NOT NULL DEFAULT 2.0
+        CHECK (temperature_hysteresis BETWEEN 0 AND 20);
+
+-- ============================================
+-- 2. 通知记录区分事件类型
+-- ============================================
+-- alert_triggered: 预警触发；alert_resolved: 预警解决
+ALTER TABLE notification_history
+    ADD COLUMN IF NOT EXISTS event_type VARCHAR(30) NOT NULL DEFAULT 'alert_triggered';
@@ -0,0 +1,43 @@
+-- 008: 电量上报幂等
+-- 设备网络不稳定时会重试上报，同一样本不应重复写入（也不应重复触发预警）
+-- 去重依据：
+--   1. 同一设备相同记录时间（唯一索引保证）
+--   2. 客户端提供的样本 ID（sample_id，在去重窗口内查找）
+
+-- ============================================
+-- 1. 电量数据新增客户端样本 ID
+-- ============================================
+ALTER TABLE battery_data
+    ADD COLUMN IF NOT EXISTS sample_id VARCHAR(64);
+
+COMMENT ON COLUMN battery_data.sample_id IS '客户端样本 ID（可选，用于重试去重）';
+
+CREATE INDEX IF NOT EXISTS idx_battery_data_device_sample
+    ON battery_data(device_id, sample_id, recorded_at DESC)
+    WHERE sample_id IS NOT NULL;
+
+-- ============================================
+-- 2. 同一设备同一记录时间唯一（只约束新数据）
+-- ============================================
+-- 迁移不删除任何历史数据：写入语句本身会跳过已存在相同记录时间的数据，
+-- 唯一索引只用于兜住并发写入。已有重复数据（或压缩后的 chunk 不支持建唯一索引）时
+-- 建索引失败，跳过并保留原有普通索引；清理历史重复数据是可选的手动步骤，
+-- 见 docs/IMPLEMENTATION_GUIDE.md「清理重复电量数据」。
+-- 部署时会重复执行迁移；013 起唯一性按组件区分，已执行过 013 时跳过
+DO $$ BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'battery_data' AND column_name = 'component'
+    ) THEN
+        BEGIN
+            -- hypertable 的唯一索引必须包含分区列 recorded_at
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_battery_data_device_recorded_unique
+                ON battery_data(device_id, recorded_at DESC);
+
+            -- 唯一索引已覆盖原有的 (device_id, recorded_at DESC) 普通索引
+            DROP INDEX IF EXISTS idx_battery_data_device_recorded;
+        EXCEPTION WHEN unique_violation OR feature_not_supported THEN
+            RAISE NOTICE '电量数据存在重复记录或已压缩的 chunk，跳过唯一索引: %', SQLERRM;
+        END;
+    END IF;
+END $$;
@@ -0,0 +1,90 @@
+-- 009: 充电会话
+-- 根据上报数据中 is_charging 的变化划分充电会话，用于充电时长和充电速率统计
+
+-- ============================================
+-- 1. 充电会话表
+-- ============================================
+CREATE TABLE IF NOT EXISTS charging_sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    device_id UUID NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+
+    -- 第一条充电样本的记录时间
+    started_at TIMESTAMPTZ NOT NULL,
+    -- 第一条非充电样本的记录时间，NULL 表示仍在充电
+    ended_at TIMESTAMPTZ,
+    -- 最后一条充电样本的记录时间
+    last_sample_at TIMESTAMPTZ NOT NULL,
+
+    start_level INTEGER NOT NULL CHECK (start_level BETWEEN 0 AND 100),
+    end_level INTEGER NOT NULL CHECK (end_level BETWEEN 0 AND 100),
+    peak_temperature DOUBLE PRECISION,
+    sample_count INTEGER NOT NULL DEFAULT 1,
+
+    -- 充电时长（分钟，未结束时计算到最后一条充电样本）
+    duration_minutes BIGINT NOT NULL DEFAULT 0,
+    -- 平均充电速率（%/小时），仅有一条样本时为 NULL
+    avg_charge_rate DOUBLE PRECISION,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_charging_sessions_device_started
+    ON charging_sessions(device_id, started_at DESC);
+
+-- 每台设备最多一个未结束的会话
+CREATE UNIQUE INDEX IF NOT EXISTS idx_charging_sessions_device_open
+    ON charging_sessions(device_id) WHERE ended_at IS NULL;
+
+COMMENT ON TABLE charging_sessions IS '充电会话，由上报数据的充电状态变化划分';
+
+-- ============================================
+-- 2. 从已有电量数据回填
+-- ============================================
+-- 按设备和记录时间排序，充电状态连续相同的样本归为一段，
+-- 充电段的结束时间取下一段（非充电）的第一条样本时间
+INSERT INTO charging_sessions (
+    device_id, started_at, ended_at, last_sample_at, start_level, end_level,
+    peak_temperature, sample_count, duration_minutes, avg_charge_rate
+)
+SELECT
+    device_id,
+    started_at,
+    ended_at,
+    last_sample_at,
+    start_level,
+    end_level,
+    peak_temperature,
+    sample_count,
+    (EXTRACT(EPOCH FROM COALESCE(ended_at, last_sample_at) - started_at) / 60)::BIGINT,
+    CASE WHEN last_sample_at > started_at
+        THEN (end_level - start_level) / (EXTRACT(EPOCH FROM last_sample_at - started_at) / 3600)
+    END
+FROM (
+    SELECT
+        device_id,
+        is_charging,
+        MIN(recorded_at) AS started_at,
+        MAX(recorded_at) AS last_sample_at,
+        (ARRAY_AGG(battery_level ORDER BY recorded_at))[1] AS start_level,
+        (ARRAY_AGG(battery_level ORDER BY recorded_at DESC))[1] AS end_level,
+        MAX(temperature) AS peak_temperature,
+        COUNT(*)::INTEGER AS sample_count,
+        LEAD(MIN(recorded_at)) OVER (PARTITION BY device_id ORDER BY MIN(recorded_at)) AS ended_at
+    FROM (
+        SELECT
+            device_id, recorded_at, battery_level, is_charging, temperature,
+            SUM(changed) OVER (PARTITION BY device_id ORDER BY recorded_at) AS run
+        FROM (
+            SELECT
+                device_id, recorded_at, battery_level, is_charging, temperature,
+                CASE WHEN is_charging IS DISTINCT FROM
+                    LAG(is_charging) OVER (PARTITION BY device_id ORDER BY recorded_at)
+                THEN 1 ELSE 0 END AS changed
+            FROM battery_data
+        ) transitions
+    ) runs
+    GROUP BY device_id, run, is_charging
+) segments
+WHERE is_charging
+  AND NOT EXISTS (SELECT 1 FROM charging_sessions);
@@ -0,0 +1,31 @@
+-- 010: 连续聚合实时查询
+-- 小时 / 天级别的聚合查询改为读取连续聚合视图：
+--   1. 开启实时聚合，尚未物化的最新数据由 TimescaleDB 从原始数据补齐
+--   2. 扩大刷新窗口，回填视图创建前的历史数据，并覆盖离线缓存 / 导入等补传数据
+
+-- ============================================
+-- 1. 开启实时聚合
+-- ============================================
+ALTER MATERIALIZED VIEW battery_hourly_stats SET (timescaledb.materialized_only = false);
+ALTER MATERIALIZED VIEW battery_daily_stats SET (timescaledb.materialized_only = false);
+
+-- ============================================
+-- 2. 刷新策略
+-- ============================================
+-- 刷新只重新计算有变更（失效）的区间，窗口扩大后的常规开销很小。
+-- 窗口起点略小于原始数据保留期（365 天），避免刷新原始数据已被删除的区间而清空聚合结果
+SELECT remove_continuous_aggregate_policy('battery_hourly_stats', if_exists => TRUE);
+SELECT add_continuous_aggregate_policy('battery_hourly_stats',
+    start_offset => INTERVAL '360 days',
+    end_offset => INTERVAL '1 hour',
+    schedule_interval => INTERVAL '1 hour',
+    initial_start => NOW()
+);
+
+SELECT remove_continuous_aggregate_policy('battery_daily_stats', if_exists => TRUE);
+SELECT add_continuous_aggregate_policy('battery_daily_stats',
+    start_offset => INTERVAL '360 days',
+    end_offset => INTERVAL '1 day',
+    schedule_interval => INTERVAL '1 hour',
+    initial_start => NOW()
+);
@@ -0,0 +1,79 @@
+-- 011: 连续聚合增加温度和电压
+-- 聚合查询返回平均温度和电压，并支持任意整小时 / 整天间隔从视图上卷，
+-- 上卷时平均值按样本数加权，因此同时记录非空样本数。
+-- 连续聚合视图不能修改查询定义，需要重建；重建后由刷新策略回填历史数据，
+-- 回填完成前查询由实时聚合从原始数据计算。
+-- 部署时会重复执行迁移，只在视图仍是旧定义时重建
+
+DO $$ BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'battery_hourly_stats' AND column_name = 'voltage_samples'
+    ) THEN
+        DROP MATERIALIZED VIEW IF EXISTS battery_hourly_stats;
+    END IF;
+
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'battery_daily_stats' AND column_name = 'voltage_samples'
+    ) THEN
+        DROP MATERIALIZED VIEW IF EXISTS battery_daily_stats;
+    END IF;
+END $$;
+
+-- ============================================
+-- 1. 每小时统计
+-- ============================================
+CREATE MATERIALIZED VIEW IF NOT EXISTS battery_hourly_stats
+WITH (timescaledb.continuous, timescaledb.materialized_only = false) AS
+SELECT
+    device_id,
+    time_bucket('1 hour', recorded_at) AS bucket,
+    AVG(battery_level) AS avg_level,
+    MIN(battery_level) AS min_level,
+    MAX(battery_level) AS max_level,
+    COUNT(*) AS sample_count,
+    SUM(CASE WHEN is_charging THEN 1 ELSE 0 END) AS charging_samples,
+    AVG(temperature) AS avg_temperature,
+    COUNT(temperature) AS temperature_samples,
+    AVG(voltage) AS avg_voltage,
+    COUNT(voltage) AS voltage_samples
+FROM battery_data
+GROUP BY device_id, bucket
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('battery_hourly_stats',
+    start_offset => INTERVAL '360 days',
+    end_offset => INTERVAL '1 hour',
+    schedule_interval => INTERVAL '1 hour',
+    initial_start => NOW(),
+    if_not_exists => TRUE
+);
+
+-- ============================================
+-- 2. 每日统计
+-- ============================================
+CREATE MATERIALIZED VIEW IF NOT EXISTS battery_daily_stats
+WITH (timescaledb.continuous, timescaledb.materialized_only = false) AS
+SELECT
+    device_id,
+    time_bucket('1 day', recorded_at) AS bucket,
+    AVG(battery_level) AS avg_level,
+    MIN(battery_level) AS min_level,
+    MAX(battery_level) AS max_level,
+    COUNT(*) AS sample_count,
+    AVG(temperature) AS avg_temperature,
+    COUNT(temperature) AS temperature_samples,
+    AVG(voltage) AS avg_voltage,
+    COUNT(voltage) AS voltage_samples
+FROM battery_data
+GROUP BY device_id, bucket
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('battery_daily_stats',
+    start_offset => INTERVAL '360 days',
+    end_offset => INTERVAL '1 day',
+    schedule_interval => INTERVAL '1 hour',
+    initial_start => NOW(),
+    if_not_exists => TRUE
+);
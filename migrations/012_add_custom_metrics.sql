@@ -0,0 +1,59 @@
+-- 012: 自定义指标
+-- 按设备类型注册指标（如电流 mA、剩余容量 mAh、信号强度、亮屏时长），
+-- 上报时额外字段按注册的类型和范围校验后以 JSONB 存入电量数据；
+-- 聚合查询可按指标键聚合，预警规则可引用指标键
+
+-- ============================================
+-- 1. 指标定义
+-- ============================================
+DO $$ BEGIN
+    CREATE TYPE metric_value_type AS ENUM ('integer', 'float', 'boolean');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS metric_definitions (
+    id UUID PRIMARY KEY,
+    device_type VARCHAR(50) NOT NULL,
+    key VARCHAR(50) NOT NULL,
+    value_type metric_value_type NOT NULL,
+    unit VARCHAR(20),
+    description VARCHAR(200),
+    min_value DOUBLE PRECISION,
+    max_value DOUBLE PRECISION,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (device_type, key),
+    CHECK (min_value IS NULL OR max_value IS NULL OR min_value <= max_value)
+);
+
+COMMENT ON TABLE metric_definitions IS '自定义指标定义（按设备类型注册）';
+
+-- ============================================
+-- 2. 电量数据新增指标值
+-- ============================================
+ALTER TABLE battery_data
+    ADD COLUMN IF NOT EXISTS metrics JSONB;
+
+COMMENT ON COLUMN battery_data.metrics IS '自定义指标值（键为指标定义的 key）';
+
+-- ============================================
+-- 3. 预警规则引用指标
+-- ============================================
+ALTER TYPE alert_type ADD VALUE IF NOT EXISTS 'custom_metric';
+
+ALTER TABLE alert_rules
+    ADD COLUMN IF NOT EXISTS metric_key VARCHAR(50);
+
+ALTER TABLE alert_events
+    ADD COLUMN IF NOT EXISTS metric_key VARCHAR(50);
+
+-- 新增的枚举值在同一事务中不可用，约束中按文本比较
+ALTER TABLE alert_rules DROP CONSTRAINT IF EXISTS alert_rules_metric_check;
+ALTER TABLE alert_rules ADD CONSTRAINT alert_rules_metric_check CHECK (
+    (alert_type::text = 'custom_metric' AND metric_key IS NOT NULL AND threshold IS NOT NULL)
+    OR (alert_type::text <> 'custom_metric' AND metric_key IS NULL)
+);
+
+COMMENT ON COLUMN alert_rules.metric_key IS 'custom_metric 规则引用的指标键';
+COMMENT ON COLUMN alert_events.metric_key IS 'custom_metric 预警对应的指标键';
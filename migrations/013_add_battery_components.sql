@@ -0,0 +1,133 @@
+-- 013: 多电池组件
+-- 耳机（左耳 / 右耳 / 充电盒）、双电池笔记本、多组电池的 UPS 等设备包含多个电池，
+-- 设备声明组件后，上报时按组件区分，查询、统计和预警均可按组件进行
+-- 单电池设备不声明组件，数据的组件为空字符串（非 NULL，便于唯一索引去重）
+-- 连续聚合视图按组件分组后重建（同 011，只在视图仍是旧定义时重建），
+-- 不指定组件的聚合查询对各组件上卷，结果与直接聚合原始数据一致
+
+-- ============================================
+-- 1. 设备声明的组件
+-- ============================================
+ALTER TABLE devices
+    ADD COLUMN IF NOT EXISTS components VARCHAR(50)[] NOT NULL DEFAULT '{}';
+
+COMMENT ON COLUMN devices.components IS '电池组件标识（为空表示单电池设备）';
+
+-- ============================================
+-- 2. 电量数据按组件区分
+-- ============================================
+ALTER TABLE battery_data
+    ADD COLUMN IF NOT EXISTS component VARCHAR(50) NOT NULL DEFAULT '';
+
+COMMENT ON COLUMN battery_data.component IS '电池组件标识（空字符串表示单电池设备）';
+
+-- 同一设备同一组件同一记录时间唯一（不同组件可以在同一时间上报）；
+-- 与 008 相同，存在历史重复数据或压缩 chunk 时不建唯一索引（由写入语句去重），
+-- 改建同列的普通索引供去重查询使用
+DO $$ BEGIN
+    BEGIN
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_battery_data_device_component_recorded_unique
+            ON battery_data(device_id, component, recorded_at DESC);
+    EXCEPTION WHEN unique_violation OR feature_not_supported THEN
+        RAISE NOTICE '电量数据存在重复记录或已压缩的 chunk，跳过唯一索引: %', SQLERRM;
+        CREATE INDEX IF NOT EXISTS idx_battery_data_device_component_recorded
+            ON battery_data(device_id, component, recorded_at DESC);
+    END;
+END $$;
+
+-- 不指定组件的查询仍按 (device_id, recorded_at) 扫描
+CREATE INDEX IF NOT EXISTS idx_battery_data_device_recorded
+    ON battery_data(device_id, recorded_at DESC);
+
+DROP INDEX IF EXISTS idx_battery_data_device_recorded_unique;
+
+-- ============================================
+-- 3. 充电会话按组件划分
+-- ============================================
+ALTER TABLE charging_sessions
+    ADD COLUMN IF NOT EXISTS component VARCHAR(50) NOT NULL DEFAULT '';
+
+COMMENT ON COLUMN charging_sessions.component IS '电池组件标识（空字符串表示单电池设备）';
+
+CREATE INDEX IF NOT EXISTS idx_charging_sessions_device_component_started
+    ON charging_sessions(device_id, component, started_at DESC);
+
+-- ============================================
+-- 4. 预警事件记录组件
+-- ============================================
+ALTER TABLE alert_events
+    ADD COLUMN IF NOT EXISTS component VARCHAR(50);
+
+COMMENT ON COLUMN alert_events.component IS '触发预警的电池组件（单电池设备和设备级预警为空）';
+
+-- ============================================
+-- 5. 连续聚合按组件分组
+-- ============================================
+DO $$ BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'battery_hourly_stats' AND column_name = 'component'
+    ) THEN
+        DROP MATERIALIZED VIEW IF EXISTS battery_hourly_stats;
+    END IF;
+
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'battery_daily_stats' AND column_name = 'component'
+    ) THEN
+        DROP MATERIALIZED VIEW IF EXISTS battery_daily_stats;
+    END IF;
+END $$;
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS battery_hourly_stats
+WITH (timescaledb.continuous, timescaledb.materialized_only = false) AS
+SELECT
+    device_id,
+    component,
+    time_bucket('1 hour', recorded_at) AS bucket,
+    AVG(battery_level) AS avg_level,
+    MIN(battery_level) AS min_level,
+    MAX(battery_level) AS max_level,
+    COUNT(*) AS sample_count,
+    SUM(CASE WHEN is_charging THEN 1 ELSE 0 END) AS charging_samples,
+    AVG(temperature) AS avg_temperature,
+    COUNT(temperature) AS temperature_samples,
+    AVG(voltage) AS avg_voltage,
+    COUNT(voltage) AS voltage_samples
+FROM battery_data
+GROUP BY device_id, component, bucket
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('battery_hourly_stats',
+    start_offset => INTERVAL '360 days',
+    end_offset => INTERVAL '1 hour',
+    schedule_interval => INTERVAL '1 hour',
+    initial_start => NOW(),
+    if_not_exists => TRUE
+);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS battery_daily_stats
+WITH (timescaledb.continuous, timescaledb.materialized_only = false) AS
+SELECT
+    device_id,
+    component,
+    time_bucket('1 day', recorded_at) AS bucket,
+    AVG(battery_level) AS avg_level,
+    MIN(battery_level) AS min_level,
+    MAX(battery_level) AS max_level,
+    COUNT(*) AS sample_count,
+    AVG(temperature) AS avg_temperature,
+    COUNT(temperature) AS temperature_samples,
+    AVG(voltage) AS avg_voltage,
+    COUNT(voltage) AS voltage_samples
+FROM battery_data
+GROUP BY device_id, component, bucket
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('battery_daily_stats',
+    start_offset => INTERVAL '360 days',
+    end_offset => INTERVAL '1 day',
+    schedule_interval => INTERVAL '1 hour',
+    initial_start => NOW(),
+    if_not_exists => TRUE
+);
@@ -0,0 +1,18 @@
+-- 014: 网关设备
+-- 一个网关（如收集数十个 BLE 传感器数据的集线器）可以代替其子设备上报电量，
+-- 子设备记录所属网关；网关与子设备属于同一用户，且只有一层（网关不能是其他网关的子设备）
+-- 删除网关时子设备保留，解除关联
+
+ALTER TABLE devices
+    ADD COLUMN IF NOT EXISTS gateway_id UUID REFERENCES devices(id) ON DELETE SET NULL;
+
+COMMENT ON COLUMN devices.gateway_id IS '所属网关设备（网关可代替子设备上报数据）';
+
+DO $$ BEGIN
+    ALTER TABLE devices ADD CONSTRAINT devices_gateway_not_self CHECK (gateway_id <> id);
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE INDEX IF NOT EXISTS idx_devices_gateway
+    ON devices(gateway_id) WHERE gateway_id IS NOT NULL;
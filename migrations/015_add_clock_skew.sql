@@ -0,0 +1,38 @@
+-- 015: 设备时钟偏差
+-- 廉价设备的时钟会漂移数分钟，断电后甚至重置为 1970 年。
+-- 服务端按接收时间测量每台设备的时钟偏差，可按设备配置校正记录时间；
+-- 明显错误的记录时间以接收时间代替，原始时间保留在 device_recorded_at
+
+DO $$ BEGIN
+    CREATE TYPE clock_flag AS ENUM ('corrected', 'replaced', 'skewed');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+-- ============================================
+-- 1. 电量数据记录时间调整
+-- ============================================
+ALTER TABLE battery_data
+    ADD COLUMN IF NOT EXISTS clock_flag clock_flag,
+    ADD COLUMN IF NOT EXISTS device_recorded_at TIMESTAMPTZ;
+
+COMMENT ON COLUMN battery_data.clock_flag IS '记录时间的时钟标记（为空表示设备时间可信）';
+COMMENT ON COLUMN battery_data.device_recorded_at IS '设备上报的原始记录时间（记录时间经过调整时保存）';
+
+-- ============================================
+-- 2. 设备时钟偏差
+-- ============================================
+ALTER TABLE devices
+    ADD COLUMN IF NOT EXISTS clock_offset_seconds BIGINT,
+    ADD COLUMN IF NOT EXISTS clock_offset_measured_at TIMESTAMPTZ;
+
+COMMENT ON COLUMN devices.clock_offset_seconds IS '设备时钟偏差（秒，服务器时间减设备时间）';
+COMMENT ON COLUMN devices.clock_offset_measured_at IS '时钟偏差的测量时间';
+
+-- ============================================
+-- 3. 设备配置：是否校正记录时间
+-- ============================================
+ALTER TABLE device_configs
+    ADD COLUMN IF NOT EXISTS clock_correction BOOLEAN NOT NULL DEFAULT false;
+
+COMMENT ON COLUMN device_configs.clock_correction IS '是否按设备时钟偏差校正记录时间';
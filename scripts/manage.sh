@@ -66,6 +66,16 @@ case "${1:-help}" in
         gunzip -c "$2" | docker exec -i zinnia-timescaledb psql -U zinnia zinnia
         echo "恢复完成"
         ;;
+    import)
+        if [ -z "${2:-}" ] || [ -z "${3:-}" ]; then
+            echo "用法: $0 import <device_id> <file> [csv|ndjson]"
+            exit 1
+        fi
+        format="${4:-${3##*.}}"
+        # 通过 entrypoint 加载 secrets，文件经标准输入传入容器
+        compose_cmd exec -T zinnia /app/entrypoint.sh \
+            /app/zinnia import-battery --device "$2" --format "$format" - < "$3"
+        ;;
     update)
         echo "更新服务..."
         git pull
@@ -100,6 +110,8 @@ Zinnia 生产环境管理脚本
   exec <service> <cmd> 在容器中执行命令
   backup               备份数据库
   restore <file>       从备份恢复数据库
+  import <device_id> <file> [format]
+                       导入设备历史电量数据（CSV / NDJSON，默认按扩展名判断）
   update               更新并重新部署
   clean                清理未使用的 Docker 资源
   ssl-renew            手动续签 SSL 证书
@@ -112,6 +124,7 @@ Zinnia 生产环境管理脚本
   $0 restart nginx            # 重启 Nginx
   $0 exec zinnia bash         # 进入应用容器
   $0 backup                   # 备份数据库
+  $0 import <device_id> data.csv  # 导入历史电量数据
   $0 ssl-status               # 查看 SSL 证书状态
   $0 ssl-renew                # 手动续签 SSL 证书
 EOF
@@ -0,0 +1,163 @@
+//! 管理命令
+//!
+//! 用法：`zinnia <command> [options]`，不带参数时启动 HTTP 服务
+
+use std::io::Read;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use zinnia::{
+    config::Settings,
+    db::{PostgresPool, RedisPool},
+    errors::AppError,
+    models::ImportFormat,
+    repositories::{
+        AlertRepository, BatteryRepository, ChargingSessionRepository, DeviceRepository,
+        MetricRepository,
+    },
+    services::{AlertService, BatteryService},
+};
+
+const USAGE: &str = "\
+用法: zinnia <command> [options]
+
+命令:
+  import-battery --device <id> [--format csv|ndjson] <file>
+                       导入设备的历史电量数据（<file> 为 - 时从标准输入读取，
+                       未指定格式时按文件扩展名判断），导入报告以 JSON 输出
+  help                 显示此帮助
+
+不带命令时启动 HTTP 服务";
+
+/// 执行管理命令，返回进程退出码
+pub async fn run(args: &[String]) -> i32 {
+    let (command, args) = match args.split_first() {
+        Some(split) => split,
+        None => return 0,
+    };
+
+    // 日志输出到标准错误，标准输出只保留命令结果
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let result = match command.as_str() {
+        "import-battery" => import_battery(args).await,
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        other => {
+            eprintln!("未知命令: {}\n\n{}", other, USAGE);
+            return 2;
+        }
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            1
+        }
+    }
+}
+
+/// 导入历史电量数据（与 `POST /battery/import/{device_id}` 规则相同，不限制文件大小）
+async fn import_battery(args: &[String]) -> Result<(), String> {
+    let mut device_id = None;
+    let mut format = None;
+    let mut path = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--device" => {
+                let value = iter.next().ok_or("--device 缺少设备 ID")?;
+                let id = value
+                    .parse::<Uuid>()
+                    .map_err(|_| format!("无效的设备 ID: {}", value))?;
+                device_id = Some(id);
+            }
+            "--format" => {
+                let value = iter.next().ok_or("--format 缺少格式")?;
+                format = Some(value.parse::<ImportFormat>()?);
+            }
+            other if path.is_none() => path = Some(other.to_string()),
+            other => return Err(format!("多余的参数: {}", other)),
+        }
+    }
+
+    let device_id = device_id.ok_or("缺少 --device 参数")?;
+    let path = path.ok_or("缺少导入文件路径")?;
+    let format = match format {
+        Some(format) => format,
+        None => path
+            .rsplit_once('.')
+            .and_then(|(_, ext)| ext.parse().ok())
+            .ok_or("无法从文件扩展名判断格式，请使用 --format 指定")?,
+    };
+
+    let content = if path == "-" {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| format!("读取标准输入失败: {}", e))?;
+        content
+    } else {
+        std::fs::read_to_string(&path).map_err(|e| format!("读取文件 {} 失败: {}", path, e))?
+    };
+
+    let settings = Settings::load().map_err(|e| format!("配置加载失败: {}", e))?;
+    let pg_pool = PostgresPool::new(&settings)
+        .await
+        .map_err(|e| format!("数据库连接失败: {}", e))?;
+    let redis_pool = Arc::new(
+        RedisPool::new(&settings)
+            .await
+            .map_err(|e| format!("Redis 连接失败: {}", e))?,
+    );
+
+    // 导入不检查预警，AlertService 无需注入通知服务
+    let alert_service = Arc::new(AlertService::new(AlertRepository::new(pg_pool.clone())));
+    let battery_service = BatteryService::new(
+        &settings,
+        BatteryRepository::new(pg_pool.clone()),
+        ChargingSessionRepository::new(pg_pool.clone()),
+        DeviceRepository::new(pg_pool.clone()),
+        MetricRepository::new(pg_pool),
+        alert_service,
+        redis_pool,
+    );
+
+    let report = battery_service
+        .import_history(device_id, format, &content)
+        .await
+        .map_err(describe)?;
+
+    let output = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    println!("{}", output);
+
+    Ok(())
+}
+
+/// 命令行输出完整的错误信息（HTTP 响应中隐藏的细节对管理员可见）
+fn describe(error: AppError) -> String {
+    let detail = match &error {
+        AppError::DatabaseError(e) => e.to_string(),
+        AppError::RedisError(e) => e.to_string(),
+        AppError::Unauthorized(msg)
+        | AppError::Forbidden(msg)
+        | AppError::NotFound(msg)
+        | AppError::ValidationError(msg)
+        | AppError::Conflict(msg)
+        | AppError::RateLimited(msg)
+        | AppError::RateLimitExceeded(msg)
+        | AppError::InternalError(msg)
+        | AppError::ConfigError(msg) => msg.clone(),
+    };
+    format!("{}: {}", error, detail)
+}
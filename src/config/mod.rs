@@ -3,6 +3,8 @@
 mod settings;
 
 pub use settings::{
-    DatabaseSettings, JwtSettings, LoggingSettings, RateLimitSettings, RecaptchaSettings,
-    RedisSettings, RegistrationSettings, ServerSettings, Settings, SmtpSettings,
+    BatterySettings, DatabaseSettings, DeviceMonitorSettings, JwtSettings, LoggingSettings,
+    NotificationSettings, RateLimitSettings, RecaptchaSettings, RedisSettings,
+    RegistrationSettings, ServerSettings, Settings, SmtpSettings, WebSocketSettings,
+    WebhookSettings,
 };
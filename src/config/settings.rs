@@ -20,6 +20,16 @@ pub struct Settings {
     pub recaptcha: RecaptchaSettings,
     #[serde(default)]
     pub registration: RegistrationSettings,
+    #[serde(default)]
+    pub websocket: WebSocketSettings,
+    #[serde(default)]
+    pub device_monitor: DeviceMonitorSettings,
+    #[serde(default)]
+    pub webhook: WebhookSettings,
+    #[serde(default)]
+    pub notification: NotificationSettings,
+    #[serde(default)]
+    pub battery: BatterySettings,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -195,6 +205,227 @@ fn default_max_per_day() -> u32 {
     10
 }
 
+/// WebSocket 推送配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebSocketSettings {
+    /// 是否启用 Redis Pub/Sub 跨实例推送
+    #[serde(default = "default_true")]
+    pub backplane_enabled: bool,
+    /// 跨实例推送使用的 Redis 频道
+    #[serde(default = "default_backplane_channel")]
+    pub backplane_channel: String,
+}
+
+impl Default for WebSocketSettings {
+    fn default() -> Self {
+        Self {
+            backplane_enabled: true,
+            backplane_channel: default_backplane_channel(),
+        }
+    }
+}
+
+fn default_backplane_channel() -> String {
+    "zinnia:ws:push".to_string()
+}
+
+/// 设备在线状态检测配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceMonitorSettings {
+    /// 是否启用离线检测
+    #[serde(default = "default_true")]
+    pub offline_check_enabled: bool,
+    /// 检测间隔（秒）
+    #[serde(default = "default_offline_check_interval")]
+    pub offline_check_interval_seconds: u64,
+    /// 宽限倍数（超过 上报间隔 × 倍数 未上报即判定离线）
+    #[serde(default = "default_offline_grace_multiplier")]
+    pub offline_grace_multiplier: f64,
+}
+
+impl Default for DeviceMonitorSettings {
+    fn default() -> Self {
+        Self {
+            offline_check_enabled: true,
+            offline_check_interval_seconds: default_offline_check_interval(),
+            offline_grace_multiplier: default_offline_grace_multiplier(),
+        }
+    }
+}
+
+fn default_offline_check_interval() -> u64 {
+    30
+}
+fn default_offline_grace_multiplier() -> f64 {
+    3.0
+}
+
+/// Webhook 投递配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookSettings {
+    /// 请求总超时（秒）
+    #[serde(default = "default_webhook_timeout")]
+    pub timeout_seconds: u64,
+    /// 连接超时（秒）
+    #[serde(default = "default_webhook_connect_timeout")]
+    pub connect_timeout_seconds: u64,
+    /// 允许投递的内网主机（逗号分隔的主机名或 IP），其余解析为内网地址的目标一律拒绝
+    #[serde(default)]
+    pub allowed_hosts: String,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: default_webhook_timeout(),
+            connect_timeout_seconds: default_webhook_connect_timeout(),
+            allowed_hosts: String::new(),
+        }
+    }
+}
+
+fn default_webhook_timeout() -> u64 {
+    10
+}
+fn default_webhook_connect_timeout() -> u64 {
+    5
+}
+
+/// 通知发件箱配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationSettings {
+    /// 是否启用发件箱投递任务
+    #[serde(default = "default_true")]
+    pub outbox_enabled: bool,
+    /// 轮询间隔（秒）
+    #[serde(default = "default_outbox_poll_interval")]
+    pub outbox_poll_interval_seconds: u64,
+    /// 每批领取的记录数
+    #[serde(default = "default_outbox_batch_size")]
+    pub outbox_batch_size: i64,
+    /// 投递租约（秒），超时未完成的记录可被重新领取
+    #[serde(default = "default_outbox_lease")]
+    pub outbox_lease_seconds: i64,
+    /// 最大尝试次数，超过后转为死信
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: i32,
+    /// 首次重试间隔（秒），之后按 2 的幂递增
+    #[serde(default = "default_retry_base")]
+    pub retry_base_seconds: u64,
+    /// 最大重试间隔（秒）
+    #[serde(default = "default_retry_max")]
+    pub retry_max_seconds: u64,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            outbox_enabled: true,
+            outbox_poll_interval_seconds: default_outbox_poll_interval(),
+            outbox_batch_size: default_outbox_batch_size(),
+            outbox_lease_seconds: default_outbox_lease(),
+            max_attempts: default_max_attempts(),
+            retry_base_seconds: default_retry_base(),
+            retry_max_seconds: default_retry_max(),
+        }
+    }
+}
+
+fn default_outbox_poll_interval() -> u64 {
+    5
+}
+fn default_outbox_batch_size() -> i64 {
+    50
+}
+fn default_outbox_lease() -> i64 {
+    300
+}
+fn default_max_attempts() -> i32 {
+    6
+}
+fn default_retry_base() -> u64 {
+    30
+}
+fn default_retry_max() -> u64 {
+    3600
+}
+
+/// 电量数据配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatterySettings {
+    /// 单次批量上报的最大条数
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: usize,
+    /// 按客户端样本 ID 去重的时间窗口（小时）
+    #[serde(default = "default_sample_dedupe_window_hours")]
+    pub sample_dedupe_window_hours: i64,
+    /// 续航估算使用的历史数据时长（小时）
+    #[serde(default = "default_estimate_window_hours")]
+    pub estimate_window_hours: i64,
+    /// 最新电量附带的续航估算速率的缓存时长（秒），期间同一电池组件不重新扫描估算窗口
+    #[serde(default = "default_estimate_refresh_seconds")]
+    pub estimate_refresh_seconds: u64,
+    /// 导入接口允许的最大文件大小（字节）
+    #[serde(default = "default_import_max_bytes")]
+    pub import_max_bytes: usize,
+    /// 设备时钟偏差不超过该秒数时视为准确
+    #[serde(default = "default_clock_skew_tolerance_seconds")]
+    pub clock_skew_tolerance_seconds: i64,
+    /// 设备时钟偏差的测量窗口（分钟），窗口内取最小偏差（网络延迟最小的测量）
+    #[serde(default = "default_clock_offset_window_minutes")]
+    pub clock_offset_window_minutes: i64,
+    /// 单次聚合查询最多的时间桶数（补齐缺失时间桶时会生成范围内的所有时间桶）
+    #[serde(default = "default_max_aggregate_buckets")]
+    pub max_aggregate_buckets: i64,
+}
+
+impl Default for BatterySettings {
+    fn default() -> Self {
+        Self {
+            max_batch_size: default_max_batch_size(),
+            sample_dedupe_window_hours: default_sample_dedupe_window_hours(),
+            estimate_window_hours: default_estimate_window_hours(),
+            estimate_refresh_seconds: default_estimate_refresh_seconds(),
+            import_max_bytes: default_import_max_bytes(),
+            clock_skew_tolerance_seconds: default_clock_skew_tolerance_seconds(),
+            clock_offset_window_minutes: default_clock_offset_window_minutes(),
+            max_aggregate_buckets: default_max_aggregate_buckets(),
+        }
+    }
+}
+
+fn default_max_batch_size() -> usize {
+    1000
+}
+
+fn default_sample_dedupe_window_hours() -> i64 {
+    24
+}
+
+fn default_estimate_window_hours() -> i64 {
+    24
+}
+
+fn default_estimate_refresh_seconds() -> u64 {
+    60
+}
+
+fn default_import_max_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+fn default_clock_skew_tolerance_seconds() -> i64 {
+    30
+}
+
+fn default_clock_offset_window_minutes() -> i64 {
+    60
+}
+
+fn default_max_aggregate_buckets() -> i64 {
+    50000
+}
+
 impl Settings {
     /// 从环境变量加载配置（不依赖配置文件）
     ///
@@ -295,6 +526,34 @@ impl Settings {
             .set_default("registration.max_per_ip_per_day", 10)?
             .set_default("registration.require_email_verification", true)?
             .set_default("registration.require_recaptcha", true)?
+            // WebSocket 推送默认配置
+            .set_default("websocket.backplane_enabled", true)?
+            .set_default("websocket.backplane_channel", "zinnia:ws:push")?
+            // 设备离线检测默认配置
+            .set_default("device_monitor.offline_check_enabled", true)?
+            .set_default("device_monitor.offline_check_interval_seconds", 30)?
+            .set_default("device_monitor.offline_grace_multiplier", 3.0)?
+            // Webhook 投递默认配置
+            .set_default("webhook.timeout_seconds", 10)?
+            .set_default("webhook.connect_timeout_seconds", 5)?
+            .set_default("webhook.allowed_hosts", "")?
+            // 通知发件箱默认配置
+            .set_default("notification.outbox_enabled", true)?
+            .set_default("notification.outbox_poll_interval_seconds", 5)?
+            .set_default("notification.outbox_batch_size", 50)?
+            .set_default("notification.outbox_lease_seconds", 300)?
+            .set_default("notification.max_attempts", 6)?
+            .set_default("notification.retry_base_seconds", 30)?
+            .set_default("notification.retry_max_seconds", 3600)?
+            // 电量数据默认配置
+            .set_default("battery.max_batch_size", 1000)?
+            .set_default("battery.sample_dedupe_window_hours", 24)?
+            .set_default("battery.estimate_window_hours", 24)?
+            .set_default("battery.estimate_refresh_seconds", 60)?
+            .set_default("battery.import_max_bytes", 10 * 1024 * 1024)?
+            .set_default("battery.clock_skew_tolerance_seconds", 30)?
+            .set_default("battery.clock_offset_window_minutes", 60)?
+            .set_default("battery.max_aggregate_buckets", 50000)?
             // 环境变量覆盖（最高优先级）
             .add_source(
                 Environment::with_prefix("ZINNIA")
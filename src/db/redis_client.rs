@@ -9,6 +9,7 @@ use secrecy::ExposeSecret;
 /// Redis 连接池包装
 #[derive(Clone)]
 pub struct RedisPool {
+    client: Client,
     manager: ConnectionManager,
 }
 
@@ -20,14 +21,14 @@ impl RedisPool {
         let client = Client::open(redis_url.expose_secret().as_str())
             .map_err(|e| AppError::ConfigError(format!("Redis URL 无效: {}", e)))?;
 
-        let manager = ConnectionManager::new(client).await.map_err(|e| {
+        let manager = ConnectionManager::new(client.clone()).await.map_err(|e| {
             tracing::error!("Redis 连接失败: {}", e);
             AppError::RedisError(e)
         })?;
 
         tracing::info!("Redis 连接已建立");
 
-        Ok(Self { manager })
+        Ok(Self { client, manager })
     }
 
     /// 获取连接管理器
@@ -35,6 +36,32 @@ impl RedisPool {
         self.manager.clone()
     }
 
+    /// 创建独立的 Pub/Sub 连接（订阅会独占连接，不能复用连接管理器）
+    pub async fn pubsub(&self) -> Result<redis::aio::PubSub, AppError> {
+        self.client
+            .get_async_pubsub()
+            .await
+            .map_err(AppError::RedisError)
+    }
+
+    /// 发布消息到频道，返回收到消息的订阅者数量
+    pub async fn publish<T: serde::Serialize>(
+        &self,
+        channel: &str,
+        value: &T,
+    ) -> Result<i64, AppError> {
+        let mut conn = self.manager.clone();
+        let serialized = serde_json::to_string(value)
+            .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+
+        redis::cmd("PUBLISH")
+            .arg(channel)
+            .arg(serialized)
+            .query_async(&mut conn)
+            .await
+            .map_err(AppError::RedisError)
+    }
+
     /// 健康检查
     pub async fn health_check(&self) -> Result<(), AppError> {
         let mut conn = self.manager.clone();
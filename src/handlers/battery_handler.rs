@@ -3,12 +3,15 @@
 use crate::errors::AppError;
 use crate::middleware::AuthInfo;
 use crate::models::{
-    ApiResponse, BatchBatteryReportRequest, BatteryAggregateRequest, BatteryQueryRequest,
-    BatteryReportRequest,
+    ApiResponse, BatchBatteryReportRequest, BatteryAggregateRequest, BatteryComponentQuery,
+    BatteryExportRequest, BatteryHealthQuery, BatteryImportQuery, BatteryQueryRequest,
+    BatteryReportRequest, BatteryStreamRequest, ChargingSessionListQuery, GatewayReportRequest,
 };
 use crate::repositories::DeviceRepository;
 use crate::services::BatteryService;
-use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use actix_web::{http::header, web, HttpMessage, HttpRequest, HttpResponse};
+use chrono::Utc;
+use futures::StreamExt;
 use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
@@ -62,15 +65,45 @@ pub async fn batch_report_battery(
         .ok_or_else(|| AppError::Unauthorized("无效的设备令牌".to_string()))?;
 
     // 批量上报
-    let count = battery_service
-        .batch_report(device_id, body.into_inner().data)
+    let body = body.into_inner();
+    let result = battery_service
+        .batch_report(
+            device_id,
+            body.data,
+            body.notify_max_age_seconds,
+            body.sent_at,
+        )
         .await?;
 
-    Ok(
-        HttpResponse::Ok().json(ApiResponse::success(serde_json::json!({
-            "inserted_count": count
-        }))),
-    )
+    Ok(HttpResponse::Ok().json(ApiResponse::success(result)))
+}
+
+/// 网关代子设备批量上报电量数据
+pub async fn gateway_report_battery(
+    req: HttpRequest,
+    battery_service: web::Data<Arc<BatteryService>>,
+    body: web::Json<GatewayReportRequest>,
+) -> Result<HttpResponse, AppError> {
+    // 验证请求
+    body.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // 从认证信息获取网关设备 ID
+    let auth_info = req
+        .extensions()
+        .get::<AuthInfo>()
+        .cloned()
+        .ok_or_else(|| AppError::Unauthorized("未认证".to_string()))?;
+
+    let gateway_id = auth_info
+        .device_id
+        .ok_or_else(|| AppError::Unauthorized("无效的设备令牌".to_string()))?;
+
+    let result = battery_service
+        .gateway_report(gateway_id, body.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(result)))
 }
 
 /// 获取最新电量
@@ -79,17 +112,91 @@ pub async fn get_latest_battery(
     battery_service: web::Data<Arc<BatteryService>>,
     device_repo: web::Data<Arc<DeviceRepository>>,
     path: web::Path<Uuid>,
+    query: web::Query<BatteryComponentQuery>,
 ) -> Result<HttpResponse, AppError> {
     let device_id = path.into_inner();
 
     // 验证访问权限（用户只能访问自己绑定的设备）
     verify_device_access(&req, device_id, &device_repo).await?;
 
-    let response = battery_service.get_latest(device_id).await?;
+    let response = battery_service
+        .get_latest(device_id, query.into_inner().component)
+        .await?;
 
     Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
 }
 
+/// 估算续航 / 充满时间
+pub async fn get_battery_estimate(
+    req: HttpRequest,
+    battery_service: web::Data<Arc<BatteryService>>,
+    device_repo: web::Data<Arc<DeviceRepository>>,
+    path: web::Path<Uuid>,
+    query: web::Query<BatteryComponentQuery>,
+) -> Result<HttpResponse, AppError> {
+    let device_id = path.into_inner();
+
+    // 验证访问权限
+    verify_device_access(&req, device_id, &device_repo).await?;
+
+    let response = battery_service
+        .get_estimate(device_id, query.into_inner().component)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+}
+
+/// 查询充电会话
+pub async fn list_charging_sessions(
+    req: HttpRequest,
+    battery_service: web::Data<Arc<BatteryService>>,
+    device_repo: web::Data<Arc<DeviceRepository>>,
+    path: web::Path<Uuid>,
+    query: web::Query<ChargingSessionListQuery>,
+) -> Result<HttpResponse, AppError> {
+    let device_id = path.into_inner();
+
+    // 验证访问权限
+    verify_device_access(&req, device_id, &device_repo).await?;
+
+    // 验证请求
+    query
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let response = battery_service
+        .list_charging_sessions(device_id, query.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(response)))
+}
+
+/// 获取电池健康报告
+pub async fn get_battery_health(
+    req: HttpRequest,
+    battery_service: web::Data<Arc<BatteryService>>,
+    device_repo: web::Data<Arc<DeviceRepository>>,
+    path: web::Path<Uuid>,
+    query: web::Query<BatteryHealthQuery>,
+) -> Result<HttpResponse, AppError> {
+    let device_id = path.into_inner();
+
+    // 验证访问权限
+    verify_device_access(&req, device_id, &device_repo).await?;
+
+    // 验证请求
+    query
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let query = query.into_inner();
+    let report = battery_service
+        .get_health(device_id, query.days, query.component)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(report)))
+}
+
 /// 查询历史数据
 pub async fn get_battery_history(
     req: HttpRequest,
@@ -108,13 +215,140 @@ pub async fn get_battery_history(
         .validate()
         .map_err(|e| AppError::ValidationError(e.to_string()))?;
 
-    let data = battery_service
-        .get_history(device_id, query.into_inner())
-        .await?;
+    let query = query.into_inner();
+    if query.uses_cursor() {
+        let page = battery_service.get_history_page(device_id, query).await?;
+        return Ok(HttpResponse::Ok().json(ApiResponse::success(page)));
+    }
+
+    let data = battery_service.get_history(device_id, query).await?;
 
     Ok(HttpResponse::Ok().json(ApiResponse::success(data)))
 }
 
+/// 流式查询历史数据（NDJSON，每行一条记录）
+pub async fn stream_battery_history(
+    req: HttpRequest,
+    battery_service: web::Data<Arc<BatteryService>>,
+    device_repo: web::Data<Arc<DeviceRepository>>,
+    path: web::Path<Uuid>,
+    query: web::Query<BatteryStreamRequest>,
+) -> Result<HttpResponse, AppError> {
+    let device_id = path.into_inner();
+
+    // 验证访问权限
+    verify_device_access(&req, device_id, &device_repo).await?;
+
+    let batches = battery_service
+        .stream_history(device_id, query.into_inner())
+        .await?;
+
+    // 每批记录编码为一个分块；响应头已发出后出错只能中断连接
+    let body = batches.map(|batch| {
+        let mut buf = Vec::new();
+        for data in batch? {
+            serde_json::to_writer(&mut buf, &data)
+                .map_err(|e| AppError::InternalError(format!("序列化失败: {}", e)))?;
+            buf.push(b'\n');
+        }
+        Ok::<_, AppError>(web::Bytes::from(buf))
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(body))
+}
+
+/// 导出历史数据（CSV / NDJSON，流式传输）
+pub async fn export_battery_history(
+    req: HttpRequest,
+    battery_service: web::Data<Arc<BatteryService>>,
+    device_repo: web::Data<Arc<DeviceRepository>>,
+    query: web::Query<BatteryExportRequest>,
+) -> Result<HttpResponse, AppError> {
+    let device_ids = query
+        .parse_device_ids()
+        .map_err(AppError::ValidationError)?;
+    let exporter = query.exporter().map_err(AppError::ValidationError)?;
+
+    // 验证访问权限（每台设备都需要有权访问）
+    for device_id in &device_ids {
+        verify_device_access(&req, *device_id, &device_repo).await?;
+    }
+
+    let batches = battery_service.export_history(
+        device_ids,
+        BatteryStreamRequest {
+            start_time: query.start_time,
+            end_time: query.end_time,
+            component: None,
+        },
+    )?;
+
+    let format = exporter.format();
+    let file_header = exporter.header();
+    let rows = batches.map(move |batch| {
+        let mut buf = Vec::new();
+        for data in batch? {
+            exporter.write_row(&data, &mut buf);
+        }
+        Ok::<_, AppError>(web::Bytes::from(buf))
+    });
+    let body = futures::stream::once(async move { Ok(web::Bytes::from(file_header)) }).chain(rows);
+
+    let filename = format!(
+        "battery_export_{}.{}",
+        Utc::now().format("%Y%m%d%H%M%S"),
+        format.extension()
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        ))
+        .streaming(body))
+}
+
+/// 导入历史电量数据（CSV / NDJSON）
+pub async fn import_battery_history(
+    req: HttpRequest,
+    battery_service: web::Data<Arc<BatteryService>>,
+    device_repo: web::Data<Arc<DeviceRepository>>,
+    path: web::Path<Uuid>,
+    query: web::Query<BatteryImportQuery>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, AppError> {
+    let device_id = path.into_inner();
+
+    // 仅管理员和设备所有者可以导入
+    verify_device_owner(&req, device_id, &device_repo).await?;
+
+    // 读取请求体（分块传输时没有 Content-Length，需要边读边检查大小）
+    let max_bytes = battery_service.import_max_bytes();
+    let mut body = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk =
+            chunk.map_err(|e| AppError::ValidationError(format!("读取导入文件失败: {}", e)))?;
+        if body.len() + chunk.len() > max_bytes {
+            return Err(AppError::ValidationError(format!(
+                "导入文件过大，最大允许 {} 字节",
+                max_bytes
+            )));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let content = std::str::from_utf8(&body)
+        .map_err(|_| AppError::ValidationError("导入文件必须使用 UTF-8 编码".to_string()))?;
+
+    let report = battery_service
+        .import_history(device_id, query.format, content)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(report)))
+}
+
 /// 获取聚合统计
 pub async fn get_battery_aggregated(
     req: HttpRequest,
@@ -128,12 +362,19 @@ pub async fn get_battery_aggregated(
     // 验证访问权限
     verify_device_access(&req, device_id, &device_repo).await?;
 
+    let metric_keys = query
+        .parse_metric_keys()
+        .map_err(AppError::ValidationError)?;
+
     let data = battery_service
         .get_aggregated(
             device_id,
+            query.component.clone(),
             query.start_time,
             query.end_time,
-            query.interval.clone(),
+            query.interval,
+            query.fill,
+            &metric_keys,
         )
         .await?;
 
@@ -154,7 +395,12 @@ pub async fn get_battery_stats(
     verify_device_access(&req, device_id, &device_repo).await?;
 
     let stats = battery_service
-        .get_stats(device_id, query.start_time, query.end_time)
+        .get_stats(
+            device_id,
+            query.component.clone(),
+            query.start_time,
+            query.end_time,
+        )
         .await?;
 
     Ok(HttpResponse::Ok().json(ApiResponse::success(stats)))
@@ -202,3 +448,32 @@ async fn verify_device_access(
 
     Err(AppError::Forbidden("无权访问此设备的数据".to_string()))
 }
+
+/// 验证设备所有权（管理员或设备所有者，不含共享用户和设备自身）
+async fn verify_device_owner(
+    req: &HttpRequest,
+    device_id: Uuid,
+    device_repo: &DeviceRepository,
+) -> Result<(), AppError> {
+    let auth_info = req
+        .extensions()
+        .get::<AuthInfo>()
+        .cloned()
+        .ok_or_else(|| AppError::Unauthorized("未认证".to_string()))?;
+
+    if auth_info.is_admin() {
+        return Ok(());
+    }
+
+    if let Some(user_id) = auth_info.user_id {
+        if auth_info.role.as_deref() != Some("readonly")
+            && device_repo.user_owns_device(device_id, user_id).await?
+        {
+            return Ok(());
+        }
+    }
+
+    Err(AppError::Forbidden(
+        "仅设备所有者或管理员可以执行此操作".to_string(),
+    ))
+}
@@ -54,7 +54,8 @@ fn get_client_ip(req: &HttpRequest) -> Option<String> {
 /// 兼容模式 - 上报电量
 /// GET/POST /api/v1/compat/battery/report?token=xxx&level=75&charging=true&...
 ///
-/// 支持 GET 方法，便于资源受限的设备直接通过 URL 上报数据
+/// 支持 GET 方法，便于资源受限的设备直接通过 URL 上报数据。
+/// 重试时携带相同的 `sample_id`（或 `ts`）可避免重复写入
 pub async fn compat_report_battery(
     req: HttpRequest,
     token_service: web::Data<Arc<DeviceAccessTokenService>>,
@@ -81,16 +82,7 @@ pub async fn compat_report_battery(
     }
 
     // 构建上报请求
-    let report = BatteryReportRequest {
-        battery_level: query.level,
-        is_charging: query.charging.map(|c| c != 0).unwrap_or(false),
-        power_saving_mode: PowerSavingMode::Off,
-        temperature: query.temp,
-        voltage: query.voltage,
-        recorded_at: query
-            .ts
-            .and_then(|ts| chrono::TimeZone::timestamp_opt(&chrono::Utc, ts, 0).single()),
-    };
+    let report = query.to_battery_report();
 
     // 上报数据
     let data = battery_service.report(device_id, report).await?;
@@ -119,7 +111,7 @@ pub async fn compat_get_latest_battery(
     }
 
     // 获取最新电量
-    let response = battery_service.get_latest(device_id).await?;
+    let response = battery_service.get_latest(device_id, None).await?;
 
     // 转换为简化响应
     let compat_response = CompatBatteryResponse {
@@ -144,6 +136,8 @@ pub struct SimpleReportQuery {
     /// 充电状态 (charging): 1=true, 0=false
     #[serde(default)]
     pub c: u8,
+    /// 样本 ID (sample id)，重试时保持不变，用于去重
+    pub s: Option<String>,
 }
 
 pub async fn compat_simple_report(
@@ -179,6 +173,10 @@ pub async fn compat_simple_report(
         temperature: None,
         voltage: None,
         recorded_at: None,
+        sample_id: query.s.clone(),
+        metrics: None,
+        component: None,
+        clock: None,
     };
 
     // 上报数据
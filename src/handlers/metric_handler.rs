@@ -0,0 +1,71 @@
+//! 自定义指标 API 处理器
+
+use crate::errors::AppError;
+use crate::middleware::require_admin;
+use crate::models::{
+    ApiResponse, CreateMetricDefinitionRequest, MetricDefinitionQuery,
+    UpdateMetricDefinitionRequest,
+};
+use crate::services::MetricService;
+use actix_web::{web, HttpRequest, HttpResponse};
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// 查询指标定义
+pub async fn list_metric_definitions(
+    metric_service: web::Data<Arc<MetricService>>,
+    query: web::Query<MetricDefinitionQuery>,
+) -> Result<HttpResponse, AppError> {
+    let definitions = metric_service.list(query.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(definitions)))
+}
+
+/// 注册指标定义（管理员）
+pub async fn create_metric_definition(
+    req: HttpRequest,
+    metric_service: web::Data<Arc<MetricService>>,
+    body: web::Json<CreateMetricDefinitionRequest>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req)?;
+
+    body.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let definition = metric_service.create(body.into_inner()).await?;
+
+    Ok(HttpResponse::Created().json(ApiResponse::created(definition)))
+}
+
+/// 更新指标定义（管理员）
+pub async fn update_metric_definition(
+    req: HttpRequest,
+    metric_service: web::Data<Arc<MetricService>>,
+    path: web::Path<Uuid>,
+    body: web::Json<UpdateMetricDefinitionRequest>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req)?;
+
+    body.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let definition = metric_service
+        .update(path.into_inner(), body.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(definition)))
+}
+
+/// 删除指标定义（管理员）
+pub async fn delete_metric_definition(
+    req: HttpRequest,
+    metric_service: web::Data<Arc<MetricService>>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req)?;
+
+    metric_service.delete(path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::<()>::success_message("指标定义已删除")))
+}
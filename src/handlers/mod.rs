@@ -7,6 +7,7 @@ mod compat_handler;
 mod device_handler;
 mod device_token_handler;
 mod health_handler;
+mod metric_handler;
 mod notification_handler;
 mod user_handler;
 mod verification_handler;
@@ -18,6 +19,7 @@ pub use compat_handler::*;
 pub use device_handler::*;
 pub use device_token_handler::*;
 pub use health_handler::*;
+pub use metric_handler::*;
 pub use notification_handler::*;
 pub use user_handler::*;
 pub use verification_handler::*;
@@ -1,9 +1,9 @@
 //! 通知偏好 API 处理器
 
 use crate::errors::AppError;
-use crate::middleware::AuthInfo;
+use crate::middleware::{require_admin, AuthInfo};
 use crate::models::{
-    ApiResponse, NotificationPreferenceResponse, SubscribeWebPushRequest,
+    ApiResponse, NotificationOutboxQuery, NotificationPreferenceResponse, SubscribeWebPushRequest,
     UpdateNotificationPreferenceRequest, WebPushSubscriptionResponse,
 };
 use crate::services::{NotificationService, WebPushService};
@@ -188,3 +188,33 @@ pub async fn unsubscribe_web_push(
         }))),
     )
 }
+
+/// 查询通知发件箱（管理员）
+pub async fn list_notification_outbox(
+    req: HttpRequest,
+    notification_service: web::Data<Arc<NotificationService>>,
+    query: web::Query<NotificationOutboxQuery>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req)?;
+
+    query
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let result = notification_service.list_outbox(query.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(result)))
+}
+
+/// 重放失败或死信通知（管理员）
+pub async fn replay_notification(
+    req: HttpRequest,
+    notification_service: web::Data<Arc<NotificationService>>,
+    path: web::Path<uuid::Uuid>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&req)?;
+
+    let history = notification_service.replay(path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::success(history)))
+}
@@ -1,7 +1,7 @@
 //! 用户 API 处理器
 
 use crate::errors::AppError;
-use crate::middleware::AuthInfo;
+use crate::middleware::{require_admin, AuthInfo};
 use crate::models::{
     ApiResponse, ChangePasswordRequest, LoginRequest, RefreshTokenRequest, RegisterRequest,
     ShareDeviceRequest, UpdateUserRequest, UserInfo, UserListQuery, UserRole,
@@ -31,34 +31,41 @@ pub async fn register(
     // 为新用户创建默认预警规则（非阻塞，出错记录但不影响注册）
     let user_id = user_info.id;
     let defaults = vec![
-        crate::models::CreateAlertRuleRequest {
-            name: "低电量预警".to_string(),
-            alert_type: crate::models::AlertType::LowBattery,
-            level: crate::models::AlertLevel::Warning,
-            cooldown_minutes: 20,
-            enabled: true,
-        },
-        crate::models::CreateAlertRuleRequest {
-            name: "临界电量预警".to_string(),
-            alert_type: crate::models::AlertType::CriticalBattery,
-            level: crate::models::AlertLevel::Critical,
-            cooldown_minutes: 5,
-            enabled: false,
-        },
-        crate::models::CreateAlertRuleRequest {
-            name: "高温预警".to_string(),
-            alert_type: crate::models::AlertType::HighTemperature,
-            level: crate::models::AlertLevel::Warning,
-            cooldown_minutes: 50,
-            enabled: false,
-        },
-        crate::models::CreateAlertRuleRequest {
-            name: "设备离线".to_string(),
-            alert_type: crate::models::AlertType::DeviceOffline,
-            level: crate::models::AlertLevel::Info,
-            cooldown_minutes: 1440,
-            enabled: false,
-        },
+        crate::models::CreateAlertRuleRequest::for_all_devices(
+            "低电量预警",
+            crate::models::AlertType::LowBattery,
+            crate::models::AlertLevel::Warning,
+            20,
+            true,
+        ),
+        crate::models::CreateAlertRuleRequest::for_all_devices(
+            "临界电量预警",
+            crate::models::AlertType::CriticalBattery,
+            crate::models::AlertLevel::Critical,
+            5,
+            false,
+        ),
+        crate::models::CreateAlertRuleRequest::for_all_devices(
+            "高温预警",
+            crate::models::AlertType::HighTemperature,
+            crate::models::AlertLevel::Warning,
+            50,
+            false,
+        ),
+        crate::models::CreateAlertRuleRequest::for_all_devices(
+            "设备离线",
+            crate::models::AlertType::DeviceOffline,
+            crate::models::AlertLevel::Info,
+            1440,
+            false,
+        ),
+        crate::models::CreateAlertRuleRequest::for_all_devices(
+            "电量快速下降",
+            crate::models::AlertType::RapidDrain,
+            crate::models::AlertLevel::Warning,
+            60,
+            true,
+        ),
     ];
 
     for d in defaults {
@@ -399,20 +406,6 @@ fn extract_user_id(req: &HttpRequest) -> Result<Uuid, AppError> {
         .map_err(|_| AppError::Unauthorized("无效的用户令牌".to_string()))
 }
 
-/// 检查是否是管理员
-fn require_admin(req: &HttpRequest) -> Result<(), AppError> {
-    let auth_info = req
-        .extensions()
-        .get::<AuthInfo>()
-        .cloned()
-        .ok_or_else(|| AppError::Unauthorized("未认证".to_string()))?;
-
-    match auth_info.role.as_deref() {
-        Some("admin") => Ok(()),
-        _ => Err(AppError::Forbidden("需要管理员权限".to_string())),
-    }
-}
-
 /// 检查是否是管理员（不返回错误）
 fn is_admin(req: &HttpRequest) -> bool {
     req.extensions()
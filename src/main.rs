@@ -2,6 +2,8 @@
 //!
 //! 设备电量监控与预警系统
 
+mod cli;
+
 use actix_cors::Cors;
 use actix_web::{middleware, web, App, HttpServer};
 use std::sync::Arc;
@@ -11,19 +13,24 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use zinnia::{
     config::Settings,
     db::{PostgresPool, RedisPool},
-    middleware::{JwtAuth, JwtOrApiKeyAuth, RequestLogger, RequestValidator, SecurityHeaders},
+    middleware::{
+        JwtAuth, JwtOrApiKeyAuth, RequestLogger, RequestValidator, RequestValidatorConfig,
+        SecurityHeaders,
+    },
     repositories::{
-        AlertRepository, BatteryRepository, DeviceAccessTokenRepository, DeviceRepository,
-        NotificationRepository, UserRepository,
+        AlertRepository, BatteryRepository, ChargingSessionRepository, DeviceAccessTokenRepository,
+        DeviceRepository, MetricRepository, NotificationRepository, UserRepository,
     },
     routes,
     security::{JwtManager, Secrets},
     services::{
-        AlertService, AuthService, BatteryService, CacheService, DeviceAccessTokenService,
-        DeviceService, EmailService, NotificationService, RecaptchaService,
+        AlertPushSender, AlertService, AuthService, BatteryPushSender, BatteryService,
+        CacheService, DeviceAccessTokenService, DeviceMonitorService, DeviceService, EmailService,
+        MetricService, NotificationOutboxWorker, NotificationService, RecaptchaService,
         RegistrationSecurityService, UserService, VerificationService, WebPushService,
+        WebhookService,
     },
-    websocket,
+    websocket::{self, RedisBackplane, WsHub},
 };
 
 #[actix_web::main]
@@ -31,6 +38,12 @@ async fn main() -> std::io::Result<()> {
     // 加载环境变量
     dotenvy::dotenv().ok();
 
+    // 管理命令（如数据导入），执行完直接退出，不启动 HTTP 服务
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args).await);
+    }
+
     // 初始化日志
     init_tracing();
 
@@ -88,6 +101,8 @@ async fn main() -> std::io::Result<()> {
     // 初始化仓库
     let device_repo = Arc::new(DeviceRepository::new((*pg_pool).clone()));
     let battery_repo = BatteryRepository::new((*pg_pool).clone());
+    let charging_repo = ChargingSessionRepository::new((*pg_pool).clone());
+    let metric_repo = MetricRepository::new((*pg_pool).clone());
     let alert_repo = AlertRepository::new((*pg_pool).clone());
     let user_repo = UserRepository::new((*pg_pool).clone());
     let device_token_repo = DeviceAccessTokenRepository::new((*pg_pool).clone());
@@ -95,6 +110,7 @@ async fn main() -> std::io::Result<()> {
 
     // 初始化服务
     let cache_service = Arc::new(CacheService::new(redis_pool.clone()));
+    let metric_service = Arc::new(MetricService::new(metric_repo.clone()));
     let mut alert_service = AlertService::new(alert_repo);
     let device_service = Arc::new(DeviceService::new(
         (*device_repo).clone(),
@@ -137,10 +153,18 @@ async fn main() -> std::io::Result<()> {
     ));
 
     // 初始化通知服务
+    let webhook_service = Arc::new(match WebhookService::new(&settings) {
+        Ok(w) => w,
+        Err(err) => {
+            eprintln!("❌ Webhook 服务初始化失败: {}", err);
+            std::process::exit(1);
+        }
+    });
     let mut notification_service = NotificationService::new(
         (*notification_repo).clone(),
         (*device_repo).clone(),
         email_service.clone(),
+        webhook_service,
     );
 
     // 尝试初始化 Web Push 服务（需要 VAPID 密钥）
@@ -159,20 +183,75 @@ async fn main() -> std::io::Result<()> {
 
     let notification_service = Arc::new(notification_service);
 
-    // 设置 AlertService 的通知服务（避免循环依赖）
+    // 启动通知发件箱投递任务
+    if settings.notification.outbox_enabled {
+        let outbox_worker = Arc::new(NotificationOutboxWorker::new(
+            &settings,
+            notification_service.clone(),
+            (*notification_repo).clone(),
+        ));
+        outbox_worker.start();
+        info!("✅ 通知发件箱投递任务已启动");
+    }
+
+    // WebSocket 会话注册中心（所有 worker 共享）
+    let ws_hub = Arc::new(WsHub::new());
+
+    // 多实例部署时通过 Redis Pub/Sub 跨实例推送
+    let (battery_push_sender, alert_push_sender): (
+        Arc<dyn BatteryPushSender>,
+        Arc<dyn AlertPushSender>,
+    ) = if settings.websocket.backplane_enabled {
+        let backplane = Arc::new(RedisBackplane::new(
+            &settings,
+            redis_pool.clone(),
+            ws_hub.clone(),
+        ));
+        backplane.start();
+        info!("✅ WebSocket 跨实例推送已启用");
+        (backplane.clone(), backplane)
+    } else {
+        (ws_hub.clone(), ws_hub.clone())
+    };
+
+    // 设置 AlertService 的通知服务和推送器（避免循环依赖）
     alert_service.set_notification_service(notification_service.clone());
+    alert_service.set_push_sender(alert_push_sender);
     let alert_service = Arc::new(alert_service);
 
     // 现在初始化 BatteryService（需要 alert_service 的 Arc）
-    let battery_service = Arc::new(BatteryService::new(
+    let mut battery_service = BatteryService::new(
+        &settings,
         battery_repo,
+        charging_repo,
         (*device_repo).clone(),
+        metric_repo.clone(),
         alert_service.clone(),
         redis_pool.clone(),
-    ));
+    );
+    battery_service.set_push_sender(battery_push_sender);
+    let battery_service = Arc::new(battery_service);
+
+    // 启动设备离线检测
+    if settings.device_monitor.offline_check_enabled {
+        let device_monitor = Arc::new(DeviceMonitorService::new(
+            &settings,
+            (*device_repo).clone(),
+            alert_service.clone(),
+        ));
+        device_monitor.start();
+        info!("✅ 设备离线检测已启动");
+    }
 
     info!("✅ 安全服务初始化完成");
 
+    // 导入接口接收 CSV / NDJSON 文件，单独放宽请求体大小和 Content-Type
+    let request_validator_config = RequestValidatorConfig::default().with_upload_route(
+        "/api/v1/battery/import/",
+        settings.battery.import_max_bytes,
+        &["text/csv", "application/x-ndjson", "text/plain"],
+    );
+
     let server_addr = settings.server_addr();
     let workers = if settings.server.workers == 0 {
         num_cpus::get()
@@ -217,7 +296,7 @@ async fn main() -> std::io::Result<()> {
             .wrap(cors)
             .wrap(SecurityHeaders::new())
             .wrap(RequestLogger::new())
-            .wrap(RequestValidator::default())
+            .wrap(RequestValidator::new(request_validator_config.clone()))
             .wrap(middleware::Compress::default())
             // 注入服务
             .app_data(web::Data::new(pg_pool.clone()))
@@ -227,6 +306,7 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(device_service.clone()))
             .app_data(web::Data::new(battery_service.clone()))
             .app_data(web::Data::new(alert_service.clone()))
+            .app_data(web::Data::new(metric_service.clone()))
             .app_data(web::Data::new(auth_service.clone()))
             .app_data(web::Data::new(cache_service.clone()))
             .app_data(web::Data::new(user_service.clone()))
@@ -237,6 +317,7 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(registration_security_service.clone()))
             .app_data(web::Data::new(notification_service.clone()))
             .app_data(web::Data::new(web_push_service_opt.clone()))
+            .app_data(web::Data::new(ws_hub.clone()))
             // 配置 HTTP 路由
             .configure(|cfg| routes::configure(cfg, jwt_auth.clone(), jwt_or_apikey_auth.clone()))
             // 配置 WebSocket 路由
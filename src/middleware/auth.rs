@@ -7,7 +7,7 @@ use crate::security::{mask_token, JwtManager};
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
     http::header::AUTHORIZATION,
-    Error, HttpMessage,
+    Error, HttpMessage, HttpRequest,
 };
 use futures::future::{ok, LocalBoxFuture, Ready};
 use std::rc::Rc;
@@ -271,6 +271,21 @@ pub fn get_auth_info(req: &ServiceRequest) -> Option<AuthInfo> {
     req.extensions().get::<AuthInfo>().cloned()
 }
 
+/// 要求当前请求由管理员发起（未认证返回 `Unauthorized`，非管理员返回 `Forbidden`）
+pub fn require_admin(req: &HttpRequest) -> Result<(), AppError> {
+    let auth_info = req
+        .extensions()
+        .get::<AuthInfo>()
+        .cloned()
+        .ok_or_else(|| AppError::Unauthorized("未认证".to_string()))?;
+
+    if auth_info.is_admin() {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden("需要管理员权限".to_string()))
+    }
+}
+
 /// JWT 或 API Key 认证中间件（支持两种认证方式）
 #[derive(Clone)]
 pub struct JwtOrApiKeyAuth {
@@ -18,6 +18,36 @@ pub struct RequestValidatorConfig {
     pub timeout_seconds: u64,
     /// 允许的 Content-Type
     pub allowed_content_types: Vec<String>,
+    /// 文件上传接口（按路径前缀匹配），使用单独的大小限制和 Content-Type
+    pub upload_routes: Vec<UploadRoute>,
+}
+
+/// 文件上传接口配置
+#[derive(Debug, Clone)]
+pub struct UploadRoute {
+    pub path_prefix: String,
+    pub max_body_size: usize,
+    pub allowed_content_types: Vec<String>,
+}
+
+impl RequestValidatorConfig {
+    /// 添加文件上传接口
+    pub fn with_upload_route(
+        mut self,
+        path_prefix: &str,
+        max_body_size: usize,
+        allowed_content_types: &[&str],
+    ) -> Self {
+        self.upload_routes.push(UploadRoute {
+            path_prefix: path_prefix.to_string(),
+            max_body_size,
+            allowed_content_types: allowed_content_types
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        });
+        self
+    }
 }
 
 impl Default for RequestValidatorConfig {
@@ -29,6 +59,7 @@ impl Default for RequestValidatorConfig {
                 "application/json".to_string(),
                 "application/json; charset=utf-8".to_string(),
             ],
+            upload_routes: Vec::new(),
         }
     }
 }
@@ -92,13 +123,22 @@ where
         let config = self.config.clone();
 
         Box::pin(async move {
+            let upload = config
+                .upload_routes
+                .iter()
+                .find(|route| req.path().starts_with(&route.path_prefix));
+            let (max_body_size, allowed_content_types) = match upload {
+                Some(route) => (route.max_body_size, &route.allowed_content_types),
+                None => (config.max_body_size, &config.allowed_content_types),
+            };
+
             // 检查 Content-Length
             if let Some(content_length) = req.headers().get("Content-Length") {
                 if let Ok(length) = content_length.to_str().unwrap_or("0").parse::<usize>() {
-                    if length > config.max_body_size {
+                    if length > max_body_size {
                         return Err(AppError::ValidationError(format!(
                             "请求体过大，最大允许 {} 字节",
-                            config.max_body_size
+                            max_body_size
                         ))
                         .into());
                     }
@@ -110,15 +150,19 @@ where
             if matches!(method, "POST" | "PUT" | "PATCH") {
                 if let Some(content_type) = req.headers().get(CONTENT_TYPE) {
                     let ct = content_type.to_str().unwrap_or("").to_lowercase();
-                    let is_valid = config
-                        .allowed_content_types
+                    let is_valid = allowed_content_types
                         .iter()
                         .any(|allowed| ct.starts_with(&allowed.to_lowercase()));
 
                     if !is_valid {
-                        return Err(AppError::ValidationError(
-                            "不支持的 Content-Type，请使用 application/json".to_string(),
-                        )
+                        let expected = match upload {
+                            Some(route) => route.allowed_content_types.join(" 或 "),
+                            None => "application/json".to_string(),
+                        };
+                        return Err(AppError::ValidationError(format!(
+                            "不支持的 Content-Type，请使用 {}",
+                            expected
+                        ))
                         .into());
                     }
                 } else {
@@ -1,5 +1,6 @@
 //! 预警模型
 
+use super::{deserialize_nullable, DeviceConfig};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
@@ -36,6 +37,57 @@ pub enum AlertType {
     HighTemperature,
     DeviceOffline,
     RapidDrain,
+    /// 自定义指标越过规则阈值
+    CustomMetric,
+}
+
+impl AlertType {
+    /// 规则未指定比较方式时的默认比较方式
+    pub fn default_comparison(&self) -> AlertComparison {
+        match self {
+            AlertType::LowBattery | AlertType::CriticalBattery => AlertComparison::Lt,
+            AlertType::HighTemperature
+            | AlertType::DeviceOffline
+            | AlertType::RapidDrain
+            | AlertType::CustomMetric => AlertComparison::Gt,
+        }
+    }
+}
+
+/// 预警规则作用范围
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Default)]
+#[sqlx(type_name = "alert_rule_scope", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AlertRuleScope {
+    /// 用户的全部设备
+    #[default]
+    All,
+    /// 指定设备列表
+    Devices,
+    /// 指定设备类型
+    DeviceType,
+}
+
+/// 阈值比较方式（`value <op> threshold` 成立即触发）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "alert_comparison", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum AlertComparison {
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+impl AlertComparison {
+    pub fn matches(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            AlertComparison::Lt => value < threshold,
+            AlertComparison::Lte => value <= threshold,
+            AlertComparison::Gt => value > threshold,
+            AlertComparison::Gte => value >= threshold,
+        }
+    }
 }
 
 /// 预警规则（用户独立）
@@ -50,6 +102,73 @@ pub struct AlertRule {
     pub enabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+
+    /// 作用范围
+    pub scope: AlertRuleScope,
+    /// scope = devices 时的设备列表
+    pub device_ids: Vec<Uuid>,
+    /// scope = device_type 时的设备类型
+    pub device_type: Option<String>,
+    /// 规则阈值，为空时使用设备配置的阈值
+    pub threshold: Option<f64>,
+    /// 比较方式，为空时使用预警类型的默认比较方式
+    pub comparison: Option<AlertComparison>,
+    /// alert_type = custom_metric 时的指标键
+    pub metric_key: Option<String>,
+}
+
+impl AlertRule {
+    /// 判断指标是否越过阈值
+    ///
+    /// 规则未设置阈值时使用 `default_threshold`（设备配置），越过时返回实际使用的阈值
+    pub fn breached(&self, value: f64, default_threshold: f64) -> Option<f64> {
+        let threshold = self.threshold.unwrap_or(default_threshold);
+        let comparison = self
+            .comparison
+            .unwrap_or_else(|| self.alert_type.default_comparison());
+
+        comparison.matches(value, threshold).then_some(threshold)
+    }
+}
+
+/// 对一台设备生效的启用规则（批量检查预警时只查询一次）
+#[derive(Debug, Clone, Default)]
+pub struct DeviceAlertRules {
+    rules: Vec<AlertRule>,
+}
+
+impl DeviceAlertRules {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        Self { rules }
+    }
+
+    /// 最具体的匹配规则：指定设备优先于设备类型，其次是全部设备，同级取最近更新的
+    pub fn matching(&self, alert_type: &AlertType, metric_key: Option<&str>) -> Option<&AlertRule> {
+        self.rules
+            .iter()
+            .filter(|r| &r.alert_type == alert_type && r.metric_key.as_deref() == metric_key)
+            .max_by_key(|r| {
+                let specificity = match r.scope {
+                    AlertRuleScope::Devices => 2,
+                    AlertRuleScope::DeviceType => 1,
+                    AlertRuleScope::All => 0,
+                };
+                (specificity, r.updated_at)
+            })
+    }
+
+    /// 自定义指标规则引用的指标键（去重排序）
+    pub fn metric_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .rules
+            .iter()
+            .filter(|r| r.alert_type == AlertType::CustomMetric)
+            .filter_map(|r| r.metric_key.clone())
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
 }
 
 /// 预警事件
@@ -67,6 +186,48 @@ pub struct AlertEvent {
     pub triggered_at: DateTime<Utc>,
     pub acknowledged_at: Option<DateTime<Utc>>,
     pub resolved_at: Option<DateTime<Utc>>,
+    /// 自定义指标预警对应的指标键
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metric_key: Option<String>,
+    /// 触发预警的电池组件（多电池设备）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
+}
+
+impl AlertEvent {
+    /// 判断记录时间为 `at` 的数据能否自动解决该预警
+    ///
+    /// 离线预警在设备上报时直接解决；电量需回升到 `阈值 + battery_hysteresis` 及以上，
+    /// 温度需回落到 `阈值 - temperature_hysteresis` 及以下（阈值取事件记录的实际阈值），
+    /// 电量和温度预警只由同一组件、记录时间不早于触发时间的数据解决
+    pub fn is_recovered(
+        &self,
+        component: Option<&str>,
+        battery_level: f64,
+        temperature: Option<f64>,
+        config: &DeviceConfig,
+        at: DateTime<Utc>,
+    ) -> bool {
+        if self.status == AlertStatus::Resolved {
+            return false;
+        }
+        if self.alert_type == AlertType::DeviceOffline {
+            return true;
+        }
+        if self.component.as_deref() != component || self.triggered_at > at {
+            return false;
+        }
+
+        match self.alert_type {
+            AlertType::LowBattery | AlertType::CriticalBattery => {
+                battery_level >= self.threshold + config.battery_hysteresis as f64
+            }
+            AlertType::HighTemperature => {
+                temperature.is_some_and(|t| t <= self.threshold - config.temperature_hysteresis)
+            }
+            _ => false,
+        }
+    }
 }
 
 /// 创建预警规则请求
@@ -84,6 +245,48 @@ pub struct CreateAlertRuleRequest {
 
     #[serde(default = "default_enabled")]
     pub enabled: bool,
+
+    #[serde(default)]
+    pub scope: AlertRuleScope,
+
+    #[validate(length(max = 100, message = "设备列表最多 100 台设备"))]
+    #[serde(default)]
+    pub device_ids: Vec<Uuid>,
+
+    #[validate(length(min = 1, max = 50, message = "设备类型长度应在 1-50 字符之间"))]
+    pub device_type: Option<String>,
+
+    pub threshold: Option<f64>,
+    pub comparison: Option<AlertComparison>,
+
+    /// 自定义指标键（仅 custom_metric 规则，且必须设置阈值）
+    #[validate(length(min = 1, max = 50, message = "指标键长度应在 1-50 字符之间"))]
+    pub metric_key: Option<String>,
+}
+
+impl CreateAlertRuleRequest {
+    /// 作用于全部设备、使用设备配置阈值的规则
+    pub fn for_all_devices(
+        name: &str,
+        alert_type: AlertType,
+        level: AlertLevel,
+        cooldown_minutes: i32,
+        enabled: bool,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            alert_type,
+            level,
+            cooldown_minutes,
+            enabled,
+            scope: AlertRuleScope::All,
+            device_ids: Vec::new(),
+            device_type: None,
+            threshold: None,
+            comparison: None,
+            metric_key: None,
+        }
+    }
 }
 
 fn default_cooldown() -> i32 {
@@ -106,6 +309,27 @@ pub struct UpdateAlertRuleRequest {
     pub cooldown_minutes: Option<i32>,
 
     pub enabled: Option<bool>,
+
+    pub scope: Option<AlertRuleScope>,
+
+    #[validate(length(max = 100, message = "设备列表最多 100 台设备"))]
+    pub device_ids: Option<Vec<Uuid>>,
+
+    #[validate(length(min = 1, max = 50, message = "设备类型长度应在 1-50 字符之间"))]
+    pub device_type: Option<String>,
+
+    /// 传 `null` 清除规则阈值（恢复使用设备配置）
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub threshold: Option<Option<f64>>,
+
+    /// 传 `null` 清除比较方式（恢复使用默认值）
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub comparison: Option<Option<AlertComparison>>,
+
+    /// 传 `null` 清除指标键（改为其他预警类型时）
+    #[validate(length(min = 1, max = 50, message = "指标键长度应在 1-50 字符之间"))]
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub metric_key: Option<Option<String>>,
 }
 
 /// 更新预警状态请求
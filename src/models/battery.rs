@@ -1,6 +1,8 @@
 //! 电量数据模型
 
-use chrono::{DateTime, Utc};
+use super::{ClockAdjustment, ClockFlag, MetricAggregate};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Datelike, Duration, DurationRound, SubsecRound, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -24,6 +26,9 @@ pub enum PowerSavingMode {
 pub struct BatteryData {
     pub id: Uuid,
     pub device_id: Uuid,
+    /// 电池组件标识（单电池设备为空字符串）
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub component: String,
     pub battery_level: i32,
     pub is_charging: bool,
     pub power_saving_mode: PowerSavingMode,
@@ -31,6 +36,385 @@ pub struct BatteryData {
     pub voltage: Option<f64>,
     pub recorded_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
+    /// 客户端样本 ID
+    pub sample_id: Option<String>,
+    /// 自定义指标值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<serde_json::Value>,
+    /// 记录时间的时钟标记（记录时间为设备上报的原始时间时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock_flag: Option<ClockFlag>,
+    /// 设备上报的原始记录时间（仅记录时间被校正或替换时保存）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_recorded_at: Option<DateTime<Utc>>,
+}
+
+impl BatteryData {
+    /// 电池组件标识，单电池设备为 `None`
+    pub fn component_key(&self) -> Option<&str> {
+        (!self.component.is_empty()).then_some(self.component.as_str())
+    }
+}
+
+/// 计算放电速率（%/小时）
+///
+/// `samples` 需按记录时间升序排列。仅统计相邻两点均未充电的区间，
+/// 充电期间的电量变化和时长不计入；放电总时长不足 `min_discharge_minutes` 时返回 `None`。
+pub fn discharge_rate_per_hour(samples: &[BatteryData], min_discharge_minutes: i64) -> Option<f64> {
+    let mut dropped = 0i64;
+    let mut discharge_seconds = 0i64;
+
+    for pair in samples.windows(2) {
+        let (prev, curr) = (&pair[0], &pair[1]);
+        if prev.is_charging || curr.is_charging {
+            continue;
+        }
+
+        let seconds = (curr.recorded_at - prev.recorded_at).num_seconds();
+        if seconds <= 0 {
+            continue;
+        }
+
+        dropped += (prev.battery_level - curr.battery_level) as i64;
+        discharge_seconds += seconds;
+    }
+
+    if discharge_seconds < min_discharge_minutes.max(1) * 60 {
+        return None;
+    }
+
+    Some(dropped as f64 * 3600.0 / discharge_seconds as f64)
+}
+
+/// 以 `end` 结束、时长为 `window` 的滑动窗口内的数据
+///
+/// `samples` 需按记录时间升序排列，返回记录时间在 `[end - window, end]` 内的连续切片
+pub fn window_ending_at(
+    samples: &[BatteryData],
+    end: DateTime<Utc>,
+    window: Duration,
+) -> &[BatteryData] {
+    let start = samples.partition_point(|d| d.recorded_at < end - window);
+    let stop = samples.partition_point(|d| d.recorded_at <= end);
+    &samples[start..stop.max(start)]
+}
+
+/// 每个组件记录时间最新的数据（`samples` 需按记录时间升序排列，结果同样升序）
+pub fn latest_by_component<'a, I>(samples: I) -> Vec<&'a BatteryData>
+where
+    I: IntoIterator<Item = &'a BatteryData>,
+    I::IntoIter: DoubleEndedIterator,
+{
+    let mut latest: Vec<&BatteryData> = Vec::new();
+    for data in samples.into_iter().rev() {
+        if !latest.iter().any(|d| d.component == data.component) {
+            latest.push(data);
+        }
+    }
+    latest.reverse();
+    latest
+}
+
+/// 预警检查顺序：按记录时间升序排列，并标记是否发送通知
+///
+/// 记录时间早于 `notify_cutoff` 的数据（补传的离线缓存）仍检查预警，但不发送通知
+pub fn alert_evaluation_order(
+    samples: &[BatteryData],
+    notify_cutoff: Option<DateTime<Utc>>,
+) -> Vec<(&BatteryData, bool)> {
+    let mut ordered: Vec<&BatteryData> = samples.iter().collect();
+    ordered.sort_by_key(|d| d.recorded_at);
+    ordered
+        .into_iter()
+        .map(|d| {
+            (
+                d,
+                notify_cutoff.is_none_or(|cutoff| d.recorded_at >= cutoff),
+            )
+        })
+        .collect()
+}
+
+/// 按 `notify_max_age_seconds` 计算发送通知的最早记录时间（未设置时全部通知）
+pub fn notify_cutoff(
+    notify_max_age_seconds: Option<i64>,
+    now: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, String> {
+    match notify_max_age_seconds {
+        Some(secs) if secs < 0 => Err("notify_max_age_seconds 不能为负数".to_string()),
+        Some(secs) => Ok(Some(now - Duration::seconds(secs))),
+        None => Ok(None),
+    }
+}
+
+/// 95% 置信区间对应的 z 值
+const CONFIDENCE_Z: f64 = 1.96;
+
+/// 电量为整数百分比，残差方差不低于量化噪声（1/12）
+const QUANTIZATION_VARIANCE: f64 = 1.0 / 12.0;
+
+/// 电量变化速率估算（%/小时，取当前充放电方向为正）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RateEstimate {
+    pub rate_per_hour: f64,
+    /// 95% 置信区间下限
+    pub lower: f64,
+    /// 95% 置信区间上限
+    pub upper: f64,
+    /// 参与估算的样本数
+    pub sample_count: usize,
+}
+
+impl RateEstimate {
+    /// 从 `from` 起变化 `remaining` 个百分点所需时间
+    ///
+    /// 速率不为正（如放电状态下电量不降反升）时返回 `None`；
+    /// 速率下限不为正时最晚时间无法确定
+    pub fn time_to(&self, remaining: f64, from: DateTime<Utc>) -> Option<TimeEstimate> {
+        if remaining <= 0.0 || self.rate_per_hour <= 0.0 {
+            return None;
+        }
+
+        let after =
+            |rate: f64| from + chrono::Duration::seconds((remaining / rate * 3600.0) as i64);
+        let at = after(self.rate_per_hour);
+
+        Some(TimeEstimate {
+            minutes: (at - from).num_minutes(),
+            at,
+            earliest_at: after(self.upper),
+            latest_at: (self.lower > 0.0).then(|| after(self.lower)),
+        })
+    }
+}
+
+/// 预计到达时间
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimeEstimate {
+    /// 距最新记录时间的分钟数
+    pub minutes: i64,
+    pub at: DateTime<Utc>,
+    /// 置信区间内最早到达时间
+    pub earliest_at: DateTime<Utc>,
+    /// 置信区间内最晚到达时间（速率可能为零时为空）
+    pub latest_at: Option<DateTime<Utc>>,
+}
+
+/// 续航 / 充满时间估算
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BatteryEstimate {
+    pub rate: RateEstimate,
+    /// 是否仅使用了与当前省电模式相同的数据（数据不足时退化为只区分充放电）
+    pub mode_matched: bool,
+    /// 预计耗尽时间（仅放电时）
+    pub time_to_empty: Option<TimeEstimate>,
+    /// 预计到达临界电量时间（仅放电且高于临界阈值时）
+    pub time_to_critical: Option<TimeEstimate>,
+    /// 预计充满时间（仅充电时）
+    pub time_to_full: Option<TimeEstimate>,
+}
+
+/// 估算电量变化速率
+///
+/// `samples` 需按记录时间升序排列。只使用充电状态（以及 `mode` 不为空时的省电模式）
+/// 与当前一致的连续区间，各区间分别去均值后合并做最小二乘回归；
+/// 有效时长不足 `min_minutes` 时返回 `None`
+pub fn estimate_rate_per_hour(
+    samples: &[BatteryData],
+    is_charging: bool,
+    mode: Option<&PowerSavingMode>,
+    min_minutes: i64,
+) -> Option<RateEstimate> {
+    let matches = |d: &BatteryData| {
+        d.is_charging == is_charging && mode.is_none_or(|m| d.power_saving_mode == *m)
+    };
+
+    // 按状态切分连续区间，每段为 (小时, 电量) 序列
+    let mut segments: Vec<Vec<(f64, f64)>> = Vec::new();
+    let mut current: Vec<(f64, f64)> = Vec::new();
+    let origin = samples.first()?.recorded_at;
+    for data in samples {
+        if matches(data) {
+            let hours = (data.recorded_at - origin).num_milliseconds() as f64 / 3_600_000.0;
+            current.push((hours, data.battery_level as f64));
+        } else if !current.is_empty() {
+            segments.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        segments.push(current);
+    }
+    segments.retain(|s| s.len() >= 2);
+
+    let mut span_hours = 0.0;
+    let mut sxx = 0.0;
+    let mut sxy = 0.0;
+    let mut n = 0usize;
+    for segment in &segments {
+        let len = segment.len() as f64;
+        let mean_t = segment.iter().map(|p| p.0).sum::<f64>() / len;
+        let mean_y = segment.iter().map(|p| p.1).sum::<f64>() / len;
+        for (t, y) in segment {
+            sxx += (t - mean_t).powi(2);
+            sxy += (t - mean_t) * (y - mean_y);
+        }
+        span_hours += segment[segment.len() - 1].0 - segment[0].0;
+        n += segment.len();
+    }
+
+    if span_hours * 60.0 < min_minutes.max(1) as f64 || sxx <= 0.0 {
+        return None;
+    }
+
+    let slope = sxy / sxx;
+
+    let mut ssr = 0.0;
+    for segment in &segments {
+        let len = segment.len() as f64;
+        let mean_t = segment.iter().map(|p| p.0).sum::<f64>() / len;
+        let mean_y = segment.iter().map(|p| p.1).sum::<f64>() / len;
+        for (t, y) in segment {
+            ssr += (y - mean_y - slope * (t - mean_t)).powi(2);
+        }
+    }
+
+    // 自由度 = 样本数 - 区间数（各自的截距） - 1（斜率）
+    let dof = n as f64 - segments.len() as f64 - 1.0;
+    let variance = if dof > 0.0 { ssr / dof } else { 0.0 }.max(QUANTIZATION_VARIANCE);
+    let margin = CONFIDENCE_Z * (variance / sxx).sqrt();
+
+    let rate = if is_charging { slope } else { -slope };
+    Some(RateEstimate {
+        rate_per_hour: rate,
+        lower: rate - margin,
+        upper: rate + margin,
+        sample_count: n,
+    })
+}
+
+/// 基于最新数据估算续航 / 充满时间
+///
+/// 优先使用与当前省电模式相同的数据，不足时只区分充放电状态
+pub fn estimate_battery(
+    samples: &[BatteryData],
+    latest: &BatteryData,
+    critical_threshold: i32,
+    min_minutes: i64,
+) -> Option<BatteryEstimate> {
+    let (rate, mode_matched) = estimate_battery_rate(samples, latest, min_minutes)?;
+    Some(BatteryEstimate::from_rate(
+        rate,
+        mode_matched,
+        latest,
+        critical_threshold,
+    ))
+}
+
+/// 估算与最新数据充电状态一致的电量变化速率，返回速率及是否按省电模式匹配
+pub fn estimate_battery_rate(
+    samples: &[BatteryData],
+    latest: &BatteryData,
+    min_minutes: i64,
+) -> Option<(RateEstimate, bool)> {
+    match estimate_rate_per_hour(
+        samples,
+        latest.is_charging,
+        Some(&latest.power_saving_mode),
+        min_minutes,
+    ) {
+        Some(rate) => Some((rate, true)),
+        None => estimate_rate_per_hour(samples, latest.is_charging, None, min_minutes)
+            .map(|rate| (rate, false)),
+    }
+}
+
+impl BatteryEstimate {
+    /// 按估算速率从最新数据推算续航 / 充满时间
+    pub fn from_rate(
+        rate: RateEstimate,
+        mode_matched: bool,
+        latest: &BatteryData,
+        critical_threshold: i32,
+    ) -> Self {
+        let level = latest.battery_level as f64;
+        let from = latest.recorded_at;
+        let (time_to_empty, time_to_critical, time_to_full) = if latest.is_charging {
+            (None, None, rate.time_to(100.0 - level, from))
+        } else {
+            (
+                rate.time_to(level, from),
+                rate.time_to(level - critical_threshold as f64, from),
+                None,
+            )
+        };
+
+        Self {
+            rate,
+            mode_matched,
+            time_to_empty,
+            time_to_critical,
+            time_to_full,
+        }
+    }
+}
+
+/// 使用 LTTB（Largest-Triangle-Three-Buckets）算法降采样
+///
+/// `samples` 需按记录时间升序排列。以记录时间和电量为坐标，首尾两点保留，
+/// 其余每个分组保留与前一选中点、下一分组均值构成三角形面积最大的点，
+/// 从而保留充电尖峰、骤降等视觉特征。返回的均为原始数据点，数量不超过 `max_points`
+pub fn downsample_lttb(samples: Vec<BatteryData>, max_points: usize) -> Vec<BatteryData> {
+    let n = samples.len();
+    if max_points >= n || max_points < 3 {
+        return samples;
+    }
+
+    let origin = samples[0].recorded_at;
+    let point = |data: &BatteryData| {
+        (
+            (data.recorded_at - origin).num_milliseconds() as f64 / 1000.0,
+            data.battery_level as f64,
+        )
+    };
+
+    // 除首尾两点外分为 max_points - 2 组
+    let every = (n - 2) as f64 / (max_points - 2) as f64;
+    let mut keep = vec![false; n];
+    keep[0] = true;
+    keep[n - 1] = true;
+
+    let mut selected = 0;
+    for i in 0..max_points - 2 {
+        let range_start = (i as f64 * every) as usize + 1;
+        let range_end = ((i + 1) as f64 * every) as usize + 1;
+        let next_start = range_end;
+        let next_end = (((i + 2) as f64 * every) as usize + 1).min(n);
+
+        let next = &samples[next_start..next_end];
+        let (sum_x, sum_y) = next
+            .iter()
+            .map(point)
+            .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+        let (avg_x, avg_y) = (sum_x / next.len() as f64, sum_y / next.len() as f64);
+
+        let (ax, ay) = point(&samples[selected]);
+        let mut max_area = -1.0;
+        for (j, data) in samples.iter().enumerate().take(range_end).skip(range_start) {
+            let (bx, by) = point(data);
+            let area = ((ax - avg_x) * (by - ay) - (ax - bx) * (avg_y - ay)).abs();
+            if area > max_area {
+                max_area = area;
+                selected = j;
+            }
+        }
+        keep[selected] = true;
+    }
+
+    samples
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(data, keep)| keep.then_some(data))
+        .collect()
 }
 
 /// 电量上报请求
@@ -53,14 +437,166 @@ pub struct BatteryReportRequest {
 
     /// 设备端记录时间（可选，默认使用服务器时间）
     pub recorded_at: Option<DateTime<Utc>>,
+
+    /// 客户端样本 ID（可选，重试上报时保持不变，用于去重）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(length(min = 1, max = 64, message = "样本 ID 长度应在 1-64 之间"))]
+    pub sample_id: Option<String>,
+
+    /// 自定义指标（可选，键须为设备类型已注册的指标）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<serde_json::Map<String, serde_json::Value>>,
+
+    /// 电池组件标识（多电池设备必填，须为设备声明的组件）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(length(min = 1, max = 50, message = "组件标识长度应在 1-50 之间"))]
+    pub component: Option<String>,
+
+    /// 服务端按设备时钟状态对记录时间的调整
+    #[serde(skip)]
+    pub clock: Option<ClockAdjustment>,
+}
+
+impl BatteryReportRequest {
+    /// 写入数据库的组件标识（单电池设备为空字符串）
+    pub fn stored_component(&self) -> &str {
+        self.component.as_deref().unwrap_or_default()
+    }
+
+    /// 是否携带自定义指标
+    pub fn has_metrics(&self) -> bool {
+        self.metrics.as_ref().is_some_and(|m| !m.is_empty())
+    }
+
+    /// 写入数据库的指标值（去掉 `null`，没有指标时为空）
+    pub fn stored_metrics(&self) -> Option<serde_json::Value> {
+        let metrics: serde_json::Map<String, serde_json::Value> = self
+            .metrics
+            .as_ref()?
+            .iter()
+            .filter(|(_, v)| !v.is_null())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        (!metrics.is_empty()).then_some(serde_json::Value::Object(metrics))
+    }
+
+    /// 写入 `battery_data` 时会违反的表约束（与迁移中的 CHECK / 列长度一致），没有时为空
+    ///
+    /// 批量写入前据此单独拒绝记录，避免一条坏数据使整批写入失败
+    pub fn storage_violation(&self) -> Option<String> {
+        if !(0..=100).contains(&self.battery_level) {
+            return Some("违反数据约束: battery_data_battery_level_check".to_string());
+        }
+        if self.stored_component().chars().count() > 50 {
+            return Some("组件标识超过 50 个字符".to_string());
+        }
+        if self
+            .sample_id
+            .as_ref()
+            .is_some_and(|id| id.chars().count() > 64)
+        {
+            return Some("样本 ID 超过 64 个字符".to_string());
+        }
+        None
+    }
+
+    /// 批量写入时每条记录的记录时间
+    ///
+    /// 未提供记录时间的记录按批次顺序依次间隔 1 微秒、最后一条为 `now`，
+    /// 避免同一批中多条记录落在同一时间而被唯一索引当作重复样本丢弃
+    pub fn batch_recorded_at(requests: &[Self], now: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        let now = now.trunc_subsecs(6);
+        let mut pending = requests.iter().filter(|r| r.recorded_at.is_none()).count() as i64;
+
+        requests
+            .iter()
+            .map(|request| {
+                request.recorded_at.unwrap_or_else(|| {
+                    pending -= 1;
+                    now - Duration::microseconds(pending)
+                })
+            })
+            .collect()
+    }
 }
 
 /// 批量上报请求
+///
+/// 单条记录在服务端逐条校验，不合法的记录单独拒绝，不影响其余记录；
+/// 条数上限由 `battery.max_batch_size` 配置。
+/// 已存在的样本（相同记录时间或样本 ID）视为重复，确认接收但不再写入
 #[derive(Debug, Clone, Deserialize, Validate)]
 pub struct BatchBatteryReportRequest {
-    #[validate(length(min = 1, max = 1000, message = "批量上报数据条数应在 1-1000 之间"))]
-    #[validate]
+    #[validate(length(min = 1, message = "批量上报数据不能为空"))]
     pub data: Vec<BatteryReportRequest>,
+
+    /// 记录时间早于该秒数的样本只记录预警事件，不发送通知（补传离线缓存时使用）
+    #[validate(range(min = 0, max = 31536000, message = "通知时效应在 0-31536000 秒之间"))]
+    pub notify_max_age_seconds: Option<i64>,
+
+    /// 设备发送请求时的本地时间，用于测量设备时钟偏差
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+/// 批量上报中被拒绝的记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RejectedBatteryRecord {
+    /// 在请求 `data` 数组中的下标（从 0 开始）
+    pub index: usize,
+    pub error: String,
+}
+
+/// 批量上报结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchReportResponse {
+    pub inserted_count: usize,
+    /// 重复样本数（已确认接收，未重复写入）
+    pub duplicate_count: usize,
+    pub rejected_count: usize,
+    pub rejected: Vec<RejectedBatteryRecord>,
+}
+
+/// 网关代子设备批量上报请求
+///
+/// 每个条目对应一台子设备，记录总条数上限由 `battery.max_batch_size` 配置
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct GatewayReportRequest {
+    #[validate(length(min = 1, max = 100, message = "子设备数量应在 1-100 之间"))]
+    pub devices: Vec<GatewayDeviceReport>,
+
+    /// 同批量上报，记录时间早于该秒数的样本不发送预警通知
+    #[validate(range(min = 0, max = 31536000, message = "通知时效应在 0-31536000 秒之间"))]
+    pub notify_max_age_seconds: Option<i64>,
+}
+
+/// 单台子设备的上报数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GatewayDeviceReport {
+    pub device_id: Uuid,
+    pub data: Vec<BatteryReportRequest>,
+}
+
+/// 单台子设备的上报结果
+///
+/// 成功时包含批量上报结果，子设备不属于该网关或数据无法处理时只包含 `error`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GatewayDeviceResult {
+    pub device_id: Uuid,
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<BatchReportResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 网关上报结果（按请求中子设备的顺序）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GatewayReportResponse {
+    /// 上报成功的子设备数
+    pub accepted_devices: usize,
+    /// 整体被拒绝的子设备数
+    pub failed_devices: usize,
+    pub results: Vec<GatewayDeviceResult>,
 }
 
 /// 电量查询请求
@@ -78,44 +614,191 @@ pub struct BatteryQueryRequest {
 
     #[serde(default)]
     pub offset: i64,
+
+    /// 降采样后的最大点数（LTTB），设置后返回整个时间范围的降采样结果，忽略 `limit` / `offset`
+    #[validate(range(min = 3, max = 5000, message = "降采样点数应在 3-5000 之间"))]
+    pub max_points: Option<i64>,
+
+    /// 分页方式，传入 `cursor` 时自动使用游标分页
+    #[serde(default)]
+    pub pagination: HistoryPagination,
+
+    /// 上一页返回的 `next_cursor`
+    pub cursor: Option<String>,
+
+    /// 只查询指定电池组件，省略时返回所有组件的数据
+    pub component: Option<String>,
 }
 
-fn default_limit() -> i64 {
-    100
+/// 历史数据分页方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryPagination {
+    /// `limit` / `offset` 分页（兼容旧版本）
+    #[default]
+    Offset,
+    /// 按 (recorded_at, id) 的游标分页
+    Cursor,
 }
 
-impl BatteryQueryRequest {
-    /// 验证时间范围（最大 30 天）
+/// 历史数据游标，指向上一页的最后一条记录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryCursor {
+    pub recorded_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl HistoryCursor {
+    pub fn from_data(data: &BatteryData) -> Self {
+        Self {
+            recorded_at: data.recorded_at,
+            id: data.id,
+        }
+    }
+
+    /// 编码为不透明字符串
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!(
+            "{}:{}",
+            self.recorded_at.timestamp_micros(),
+            self.id
+        ))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, String> {
+        let invalid = || "无效的分页游标".to_string();
+
+        let raw = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+        let (micros, id) = raw.split_once(':').ok_or_else(invalid)?;
+
+        Ok(Self {
+            recorded_at: micros
+                .parse()
+                .ok()
+                .and_then(DateTime::from_timestamp_micros)
+                .ok_or_else(invalid)?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// 历史数据流式查询请求
+///
+/// 流式查询逐批读取，不受分页查询 30 天时间范围的限制
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct BatteryStreamRequest {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+
+    /// 只查询指定电池组件，省略时返回所有组件的数据
+    pub component: Option<String>,
+}
+
+impl BatteryStreamRequest {
+    /// 验证时间范围（不限制跨度）
     pub fn validate_time_range(&self) -> Result<(), String> {
-        let duration = self.end_time - self.start_time;
-        let max_days = 30;
+        check_time_range(self.start_time, self.end_time, None)
+    }
+}
 
-        if duration.num_days() > max_days {
+/// 验证查询时间范围，`max_days` 为空时不限制跨度
+fn check_time_range(
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    max_days: Option<i64>,
+) -> Result<(), String> {
+    if let Some(max_days) = max_days {
+        if (end_time - start_time).num_days() > max_days {
             return Err(format!("查询时间范围不能超过 {} 天", max_days));
         }
+    }
 
-        if self.start_time > self.end_time {
-            return Err("开始时间不能晚于结束时间".to_string());
-        }
+    if start_time > end_time {
+        return Err("开始时间不能晚于结束时间".to_string());
+    }
 
-        if self.end_time > Utc::now() {
-            return Err("结束时间不能是未来时间".to_string());
-        }
+    if end_time > Utc::now() {
+        return Err("结束时间不能是未来时间".to_string());
+    }
 
-        Ok(())
+    Ok(())
+}
+
+fn default_limit() -> i64 {
+    100
+}
+
+impl BatteryQueryRequest {
+    /// 分页查询的最大时间范围（天）
+    pub const MAX_RANGE_DAYS: i64 = 30;
+
+    /// 验证时间范围（最大 30 天）
+    pub fn validate_time_range(&self) -> Result<(), String> {
+        check_time_range(self.start_time, self.end_time, Some(Self::MAX_RANGE_DAYS))
+    }
+
+    /// 是否使用游标分页
+    pub fn uses_cursor(&self) -> bool {
+        self.pagination == HistoryPagination::Cursor || self.cursor.is_some()
     }
 }
 
+/// 按电池组件查询的参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatteryComponentQuery {
+    pub component: Option<String>,
+}
+
 /// 最新电量响应
+///
+/// 多电池设备不指定组件时为最近上报的一条，并在 `components` 中列出各组件的最新电量
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LatestBatteryResponse {
     pub device_id: Uuid,
+    /// 数据所属的电池组件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
     pub battery_level: i32,
     pub is_charging: bool,
     pub power_saving_mode: PowerSavingMode,
     pub recorded_at: DateTime<Utc>,
     pub is_low_battery: bool,
     pub is_critical: bool,
+    /// 续航 / 充满时间估算（数据不足时为空）
+    #[serde(default)]
+    pub estimate: Option<BatteryEstimate>,
+    /// 自定义指标值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<serde_json::Value>,
+    /// 各电池组件的最新电量（仅多电池设备不指定组件时返回）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub components: Option<Vec<ComponentBattery>>,
+}
+
+/// 电池组件的最新电量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentBattery {
+    pub component: String,
+    pub battery_level: i32,
+    pub is_charging: bool,
+    pub recorded_at: DateTime<Utc>,
+    pub is_low_battery: bool,
+    pub is_critical: bool,
+}
+
+/// 续航估算响应
+#[derive(Debug, Clone, Serialize)]
+pub struct BatteryEstimateResponse {
+    pub device_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
+    pub battery_level: i32,
+    pub is_charging: bool,
+    pub power_saving_mode: PowerSavingMode,
+    /// 估算基于的最新记录时间
+    pub recorded_at: DateTime<Utc>,
+    pub estimate: Option<BatteryEstimate>,
 }
 
 /// 电量统计响应
@@ -133,24 +816,229 @@ pub struct BatteryStatsResponse {
 }
 
 /// 时间聚合间隔
-#[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all = "lowercase")]
+///
+/// 支持 `minute` / `hour` / `day` / `week` / `month`、简写（`5m`、`15m`、`6h`、`2d`、`1w`、`3mo`、`1y`）
+/// 以及 ISO-8601 时长（`PT15M`、`PT6H`、`P1D`、`P1W`、`P1M`），最小 1 分钟
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
 pub enum AggregateInterval {
-    Minute,
-    Hour,
-    Day,
+    /// 固定时长（秒）
+    Fixed(i64),
+    /// 自然月
+    Months(i64),
 }
 
 impl AggregateInterval {
-    pub fn to_timescaledb_interval(&self) -> &'static str {
+    pub const MINUTE: Self = AggregateInterval::Fixed(60);
+    pub const HOUR: Self = AggregateInterval::Fixed(3600);
+    pub const DAY: Self = AggregateInterval::Fixed(86400);
+    pub const WEEK: Self = AggregateInterval::Fixed(7 * 86400);
+    pub const MONTH: Self = AggregateInterval::Months(1);
+
+    /// 无法使用连续聚合视图时的最大查询范围（天），此时直接扫描原始数据
+    pub const MAX_RAW_RANGE_DAYS: i64 = 30;
+
+    pub fn to_timescaledb_interval(&self) -> String {
         match self {
-            AggregateInterval::Minute => "1 minute",
-            AggregateInterval::Hour => "1 hour",
-            AggregateInterval::Day => "1 day",
+            AggregateInterval::Fixed(seconds) => format!("{} seconds", seconds),
+            AggregateInterval::Months(months) => format!("{} months", months),
+        }
+    }
+
+    /// 可用于上卷的连续聚合视图及其时间桶间隔
+    ///
+    /// 整小时的间隔由小时视图上卷，整天、整周和自然月由天视图上卷，其余间隔没有视图
+    pub fn continuous_aggregate(&self) -> Option<(&'static str, AggregateInterval)> {
+        match *self {
+            AggregateInterval::Months(_) => Some(("battery_daily_stats", Self::DAY)),
+            AggregateInterval::Fixed(seconds) if seconds % 86400 == 0 => {
+                Some(("battery_daily_stats", Self::DAY))
+            }
+            AggregateInterval::Fixed(seconds) if seconds % 3600 == 0 => {
+                Some(("battery_hourly_stats", Self::HOUR))
+            }
+            AggregateInterval::Fixed(_) => None,
+        }
+    }
+
+    /// 完全落在 `[start, end]` 内的时间桶范围 `[from, to)`（仅适用于固定时长）
+    ///
+    /// 这些桶可以直接读取连续聚合视图；首尾不完整的桶需要从原始数据聚合，
+    /// 以保证结果与直接按时间范围聚合原始数据一致
+    pub fn full_bucket_range(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let step = match self {
+            AggregateInterval::Fixed(seconds) => Duration::seconds(*seconds),
+            AggregateInterval::Months(_) => return None,
+        };
+        let from = start.duration_trunc(step).ok()?;
+        let from = if from < start { from + step } else { from };
+        let to = end.duration_trunc(step).ok()?;
+
+        (from < to).then_some((from, to))
+    }
+
+    /// `[start, end]` 覆盖的时间桶数（含首尾不完整的时间桶）
+    pub fn bucket_count(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> i64 {
+        match *self {
+            AggregateInterval::Fixed(seconds) => (end - start).num_seconds() / seconds + 1,
+            AggregateInterval::Months(months) => {
+                let elapsed = (end.year() - start.year()) as i64 * 12 + end.month() as i64
+                    - start.month() as i64;
+                elapsed / months + 1
+            }
+        }
+    }
+
+    /// 验证时间范围
+    ///
+    /// 时间桶数不能超过 `max_buckets`，避免补齐缺失时间桶时生成过多的行
+    pub fn validate_time_range(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        max_buckets: i64,
+    ) -> Result<(), String> {
+        if start > end {
+            return Err("开始时间不能晚于结束时间".to_string());
+        }
+
+        if self.bucket_count(start, end) > max_buckets {
+            return Err(format!(
+                "查询时间范围内的时间桶数不能超过 {}，请缩小时间范围或增大聚合间隔",
+                max_buckets
+            ));
+        }
+
+        if self.continuous_aggregate().is_none()
+            && (end - start).num_days() > Self::MAX_RAW_RANGE_DAYS
+        {
+            return Err(format!(
+                "聚合间隔不是整小时时查询时间范围不能超过 {} 天",
+                Self::MAX_RAW_RANGE_DAYS
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// 解析简写形式，如 `15m`、`6h`、`3mo`
+    fn parse_shorthand(s: &str) -> Option<Self> {
+        let split = s.find(|c: char| !c.is_ascii_digit())?;
+        let (value, unit) = s.split_at(split);
+        let value: i64 = value.parse().ok()?;
+
+        let interval = match unit {
+            "m" | "min" => AggregateInterval::Fixed(value.checked_mul(60)?),
+            "h" => AggregateInterval::Fixed(value.checked_mul(3600)?),
+            "d" => AggregateInterval::Fixed(value.checked_mul(86400)?),
+            "w" => AggregateInterval::Fixed(value.checked_mul(7 * 86400)?),
+            "mo" => AggregateInterval::Months(value),
+            "y" => AggregateInterval::Months(value.checked_mul(12)?),
+            _ => return None,
+        };
+        Some(interval)
+    }
+
+    /// 解析 ISO-8601 时长（`P` 之后的部分），不支持小数，月份不能与固定时长混用
+    fn parse_iso8601(s: &str) -> Option<Self> {
+        let (date, time) = match s.split_once('t') {
+            Some((date, time)) if !time.is_empty() => (date, time),
+            Some(_) => return None,
+            None => (s, ""),
+        };
+
+        let mut months: i64 = 0;
+        let mut seconds: i64 = 0;
+        for (part, in_time) in [(date, false), (time, true)] {
+            let mut value = String::new();
+            for c in part.chars() {
+                if c.is_ascii_digit() {
+                    value.push(c);
+                    continue;
+                }
+                let n: i64 = value.parse().ok()?;
+                value.clear();
+                match (c, in_time) {
+                    ('y', false) => months = months.checked_add(n.checked_mul(12)?)?,
+                    ('m', false) => months = months.checked_add(n)?,
+                    ('w', false) => seconds = seconds.checked_add(n.checked_mul(7 * 86400)?)?,
+                    ('d', false) => seconds = seconds.checked_add(n.checked_mul(86400)?)?,
+                    ('h', true) => seconds = seconds.checked_add(n.checked_mul(3600)?)?,
+                    ('m', true) => seconds = seconds.checked_add(n.checked_mul(60)?)?,
+                    ('s', true) => seconds = seconds.checked_add(n)?,
+                    _ => return None,
+                }
+            }
+            if !value.is_empty() {
+                return None;
+            }
+        }
+
+        match (months, seconds) {
+            (0, seconds) => Some(AggregateInterval::Fixed(seconds)),
+            (months, 0) => Some(AggregateInterval::Months(months)),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for AggregateInterval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_ascii_lowercase();
+        let interval = match lower.as_str() {
+            "minute" => Some(Self::MINUTE),
+            "hour" => Some(Self::HOUR),
+            "day" => Some(Self::DAY),
+            "week" => Some(Self::WEEK),
+            "month" => Some(Self::MONTH),
+            _ => match lower.strip_prefix('p') {
+                Some(duration) => Self::parse_iso8601(duration),
+                None => Self::parse_shorthand(&lower),
+            },
         }
+        .ok_or_else(|| format!("无效的聚合间隔: {}", s))?;
+
+        let too_small = match interval {
+            AggregateInterval::Fixed(seconds) => seconds < 60,
+            AggregateInterval::Months(months) => months < 1,
+        };
+        if too_small {
+            return Err("聚合间隔不能小于 1 分钟".to_string());
+        }
+
+        Ok(interval)
+    }
+}
+
+impl TryFrom<String> for AggregateInterval {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
     }
 }
 
+/// 缺失时间桶的填充方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GapFill {
+    /// 不填充，只返回有数据的时间桶
+    #[default]
+    None,
+    /// 补齐时间桶，数值为空
+    Null,
+    /// 沿用上一个时间桶的值
+    Locf,
+    /// 在前后时间桶之间线性插值
+    Interpolate,
+}
+
 /// 聚合查询请求
 #[derive(Debug, Clone, Deserialize, Validate)]
 pub struct BatteryAggregateRequest {
@@ -159,18 +1047,61 @@ pub struct BatteryAggregateRequest {
 
     #[serde(default = "default_interval")]
     pub interval: AggregateInterval,
+
+    #[serde(default)]
+    pub fill: GapFill,
+
+    /// 同时聚合的自定义指标键，逗号分隔
+    pub metrics: Option<String>,
+
+    /// 只统计指定电池组件，省略时合并统计所有组件
+    pub component: Option<String>,
+}
+
+impl BatteryAggregateRequest {
+    /// 单次最多聚合的自定义指标数
+    pub const MAX_METRICS: usize = 10;
+
+    /// 解析自定义指标键（去重并保持顺序），未指定时为空
+    pub fn parse_metric_keys(&self) -> Result<Vec<String>, String> {
+        let mut keys: Vec<String> = Vec::new();
+        for key in self
+            .metrics
+            .iter()
+            .flat_map(|m| m.split(','))
+            .map(str::trim)
+        {
+            if !key.is_empty() && !keys.iter().any(|k| k == key) {
+                keys.push(key.to_string());
+            }
+        }
+
+        if keys.len() > Self::MAX_METRICS {
+            return Err(format!("单次最多聚合 {} 个自定义指标", Self::MAX_METRICS));
+        }
+
+        Ok(keys)
+    }
 }
 
 fn default_interval() -> AggregateInterval {
-    AggregateInterval::Hour
+    AggregateInterval::HOUR
 }
 
 /// 聚合数据点
+///
+/// 补齐的时间桶 `count` 为 0，数值按填充方式计算（可能为空）
 #[derive(Debug, Clone, Serialize, FromRow)]
 pub struct BatteryAggregatePoint {
     pub bucket: DateTime<Utc>,
-    pub avg_level: f64,
-    pub min_level: i32,
-    pub max_level: i32,
+    pub avg_level: Option<f64>,
+    pub min_level: Option<i32>,
+    pub max_level: Option<i32>,
     pub count: i64,
+    pub avg_temperature: Option<f64>,
+    pub avg_voltage: Option<f64>,
+    /// 请求的自定义指标聚合（键为指标键，时间桶内没有该指标时不返回；补齐的时间桶不填充）
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<std::collections::BTreeMap<String, MetricAggregate>>,
 }
@@ -0,0 +1,163 @@
+//! 充电会话模型
+
+use super::BatteryData;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// 充电会话
+///
+/// 从第一条充电样本开始，到第一条非充电样本结束
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq)]
+pub struct ChargingSession {
+    pub id: Uuid,
+    pub device_id: Uuid,
+    /// 电池组件标识（单电池设备为空字符串）
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub component: String,
+    pub started_at: DateTime<Utc>,
+    /// 结束时间，`None` 表示仍在充电
+    pub ended_at: Option<DateTime<Utc>>,
+    /// 最后一条充电样本的记录时间
+    pub last_sample_at: DateTime<Utc>,
+    pub start_level: i32,
+    pub end_level: i32,
+    pub peak_temperature: Option<f64>,
+    pub sample_count: i32,
+    /// 充电时长（分钟，未结束时计算到最后一条充电样本）
+    pub duration_minutes: i64,
+    /// 平均充电速率（%/小时）
+    pub avg_charge_rate: Option<f64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ChargingSession {
+    /// 以充电样本开始新会话
+    pub fn start(data: &BatteryData) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            device_id: data.device_id,
+            component: data.component.clone(),
+            started_at: data.recorded_at,
+            ended_at: None,
+            last_sample_at: data.recorded_at,
+            start_level: data.battery_level,
+            end_level: data.battery_level,
+            peak_temperature: data.temperature,
+            sample_count: 1,
+            duration_minutes: 0,
+            avg_charge_rate: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.ended_at.is_none()
+    }
+
+    /// 追加充电样本
+    fn extend(&mut self, data: &BatteryData) {
+        self.last_sample_at = data.recorded_at;
+        self.end_level = data.battery_level;
+        self.sample_count += 1;
+        if let Some(temp) = data.temperature {
+            self.peak_temperature = Some(self.peak_temperature.map_or(temp, |p| p.max(temp)));
+        }
+        self.refresh();
+    }
+
+    /// 在非充电样本的记录时间结束会话
+    fn close(&mut self, at: DateTime<Utc>) {
+        self.ended_at = Some(at);
+        self.refresh();
+    }
+
+    /// 重新计算时长和平均速率
+    fn refresh(&mut self) {
+        let end = self.ended_at.unwrap_or(self.last_sample_at);
+        self.duration_minutes = (end - self.started_at).num_minutes();
+
+        let charging_seconds = (self.last_sample_at - self.started_at).num_seconds();
+        self.avg_charge_rate = (charging_seconds > 0)
+            .then(|| (self.end_level - self.start_level) as f64 * 3600.0 / charging_seconds as f64);
+        self.updated_at = Utc::now();
+    }
+}
+
+/// 根据新样本推进充电会话
+///
+/// `last` 为设备（多电池设备为该组件）最近一次会话（可能已结束），
+/// `samples` 需属于同一组件并按记录时间升序排列。
+/// 返回需要保存的会话（被更新或结束的会话，以及新开始的会话）；
+/// 不晚于最近会话最后状态的乱序 / 补传数据不参与划分
+pub fn track_charging_sessions(
+    last: Option<ChargingSession>,
+    samples: &[BatteryData],
+) -> Vec<ChargingSession> {
+    let cutoff = last
+        .as_ref()
+        .map(|s| s.ended_at.unwrap_or(s.last_sample_at));
+    let mut current = last.filter(ChargingSession::is_open);
+    let mut changed = Vec::new();
+    let mut touched = false;
+
+    for data in samples {
+        if cutoff.is_some_and(|c| data.recorded_at <= c) {
+            continue;
+        }
+
+        match (current.as_mut(), data.is_charging) {
+            (Some(session), true) => {
+                session.extend(data);
+                touched = true;
+            }
+            (Some(session), false) => {
+                session.close(data.recorded_at);
+                changed.extend(current.take());
+                touched = false;
+            }
+            (None, true) => {
+                current = Some(ChargingSession::start(data));
+                touched = true;
+            }
+            (None, false) => {}
+        }
+    }
+
+    if touched {
+        changed.extend(current);
+    }
+
+    changed
+}
+
+/// 充电会话列表查询参数
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ChargingSessionListQuery {
+    /// 只返回在此时间之后开始的会话
+    pub start_time: Option<DateTime<Utc>>,
+    /// 只返回在此时间之前开始的会话
+    pub end_time: Option<DateTime<Utc>>,
+    /// 只返回指定电池组件的会话，省略时返回所有组件
+    pub component: Option<String>,
+
+    #[validate(range(min = 1, max = 100, message = "每页数量应在 1-100 之间"))]
+    #[serde(default = "default_page_size")]
+    pub page_size: i64,
+
+    #[validate(range(min = 1, message = "页码应大于 0"))]
+    #[serde(default = "default_page")]
+    pub page: i64,
+}
+
+fn default_page_size() -> i64 {
+    20
+}
+fn default_page() -> i64 {
+    1
+}
@@ -0,0 +1,104 @@
+//! 设备时钟偏差模型
+
+use super::BatteryReportRequest;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 记录时间的时钟标记
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "clock_flag", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ClockFlag {
+    /// 已按设备时钟偏差校正
+    Corrected,
+    /// 设备时间明显错误（如断电后重置为 1970 年），以服务器接收时间代替
+    Replaced,
+    /// 设备时钟偏差超过容差但未开启校正，记录时间可能不准确
+    Skewed,
+}
+
+/// 服务端对记录时间的调整（随数据一起写入）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockAdjustment {
+    pub flag: ClockFlag,
+    /// 设备上报的原始记录时间
+    pub device_recorded_at: DateTime<Utc>,
+}
+
+/// 早于此时间的记录时间视为设备时钟错误
+pub fn min_valid_recorded_at() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2010, 1, 1, 0, 0, 0).unwrap()
+}
+
+/// 测量设备时钟偏差（秒）：服务器时间减设备时间，设备时钟偏慢时为正
+pub fn clock_offset_seconds(device_time: DateTime<Utc>, received_at: DateTime<Utc>) -> i64 {
+    ((received_at - device_time).num_milliseconds() as f64 / 1000.0).round() as i64
+}
+
+/// 设备时钟状态
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeviceClock {
+    /// 时钟偏差（秒），尚未测量时为 `None`
+    pub offset_seconds: Option<i64>,
+    /// 是否按偏差校正记录时间（设备配置）
+    pub correction: bool,
+    /// 偏差不超过该秒数时视为准确
+    pub tolerance_seconds: i64,
+}
+
+impl DeviceClock {
+    /// 超过容差的时钟偏差
+    pub fn skew(&self) -> Option<i64> {
+        self.offset_seconds
+            .filter(|offset| offset.abs() > self.tolerance_seconds)
+    }
+
+    /// 按时钟状态调整上报数据的记录时间
+    ///
+    /// 偏差超过容差时，开启校正则加上偏差，否则只标记为 `skewed`；
+    /// 调整后早于 [`min_valid_recorded_at`] 或晚于接收时间超过容差的时间视为设备时钟错误，
+    /// 实时上报（`live`）以接收时间代替，补传的数据无法确定真实时间，返回错误；
+    /// 晚于接收时间但在容差内的时间按接收时间记录。未提供记录时间的数据不做调整
+    pub fn apply(
+        &self,
+        request: &mut BatteryReportRequest,
+        received_at: DateTime<Utc>,
+        live: bool,
+    ) -> Result<(), String> {
+        let Some(device_time) = request.recorded_at else {
+            return Ok(());
+        };
+
+        let (recorded_at, flag) = match self.skew() {
+            Some(offset) if self.correction => (
+                device_time + Duration::seconds(offset),
+                Some(ClockFlag::Corrected),
+            ),
+            Some(_) => (device_time, Some(ClockFlag::Skewed)),
+            None => (device_time, None),
+        };
+
+        let latest_valid = received_at + Duration::seconds(self.tolerance_seconds);
+        let (recorded_at, flag) = if recorded_at < min_valid_recorded_at() {
+            if !live {
+                return Err("记录时间早于 2010-01-01，设备时钟可能已重置".to_string());
+            }
+            (received_at, Some(ClockFlag::Replaced))
+        } else if recorded_at > latest_valid {
+            if !live {
+                return Err("记录时间不能是未来时间".to_string());
+            }
+            (received_at, Some(ClockFlag::Replaced))
+        } else {
+            (recorded_at.min(received_at), flag)
+        };
+
+        request.recorded_at = Some(recorded_at);
+        request.clock = flag.map(|flag| ClockAdjustment {
+            flag,
+            device_recorded_at: device_time,
+        });
+
+        Ok(())
+    }
+}
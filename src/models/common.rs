@@ -94,6 +94,31 @@ impl<T: Serialize> PaginatedResponse<T> {
     }
 }
 
+/// 游标分页响应
+#[derive(Debug, Serialize)]
+pub struct CursorPage<T: Serialize> {
+    pub items: Vec<T>,
+    /// 下一页游标，没有更多数据时为空
+    pub next_cursor: Option<String>,
+}
+
+impl<T: Serialize> CursorPage<T> {
+    pub fn new(items: Vec<T>, next_cursor: Option<String>) -> Self {
+        Self { items, next_cursor }
+    }
+}
+
+/// 区分「字段缺省」（外层 None）与「显式 null」（Some(None)）
+pub(crate) fn deserialize_nullable<'de, T, D>(
+    deserializer: D,
+) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 /// 时间范围
 #[derive(Debug, Clone, Deserialize)]
 pub struct TimeRange {
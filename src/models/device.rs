@@ -1,6 +1,7 @@
 //! 设备数据模型
 
-use chrono::{DateTime, Utc};
+use super::deserialize_nullable;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -26,6 +27,10 @@ pub struct Device {
     pub owner_id: Option<Uuid>,
     pub name: String,
     pub device_type: String,
+    /// 电池组件标识（为空表示单电池设备）
+    pub components: Vec<String>,
+    /// 所属网关设备（网关可代替子设备上报数据）
+    pub gateway_id: Option<Uuid>,
     pub status: DeviceStatus,
     /// API Key 哈希值（不返回给客户端）
     #[serde(skip_serializing)]
@@ -37,6 +42,36 @@ pub struct Device {
     pub last_seen_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<serde_json::Value>,
+    /// 设备时钟偏差（秒，服务器时间减设备时间），尚未测量时为空
+    pub clock_offset_seconds: Option<i64>,
+    /// 时钟偏差的测量时间
+    pub clock_offset_measured_at: Option<DateTime<Utc>>,
+}
+
+impl Device {
+    /// 单台设备最多声明的电池组件数
+    pub const MAX_COMPONENTS: usize = 16;
+
+    /// 是否为多电池设备
+    pub fn has_components(&self) -> bool {
+        !self.components.is_empty()
+    }
+
+    /// 校验上报数据的组件
+    ///
+    /// 声明了组件的设备必须指定其中之一，未声明组件的设备不能指定组件
+    pub fn check_component(&self, component: Option<&str>) -> Result<(), String> {
+        match component {
+            None if self.has_components() => Err(format!(
+                "设备包含多个电池组件，必须指定 component（{}）",
+                self.components.join(" / ")
+            )),
+            None => Ok(()),
+            Some(c) if self.components.iter().any(|declared| declared == c) => Ok(()),
+            Some(c) if self.has_components() => Err(format!("设备未声明组件: {}", c)),
+            Some(_) => Err("设备未声明电池组件，不能指定 component".to_string()),
+        }
+    }
 }
 
 /// 设备配置
@@ -47,7 +82,17 @@ pub struct DeviceConfig {
     pub critical_battery_threshold: i32,
     pub report_interval_seconds: i32,
     pub high_temperature_threshold: f64,
+    /// 快速耗电阈值（%/小时）
+    pub rapid_drain_threshold: f64,
+    /// 计算放电速率的滑动窗口（分钟）
+    pub rapid_drain_window_minutes: i32,
     pub updated_at: DateTime<Utc>,
+    /// 电量回差（百分点），电量回升到阈值加回差以上才自动解决预警
+    pub battery_hysteresis: i32,
+    /// 温度回差（摄氏度），温度回落到阈值减回差以下才自动解决预警
+    pub temperature_hysteresis: f64,
+    /// 按测量的时钟偏差校正上报数据的记录时间
+    pub clock_correction: bool,
 }
 
 impl Default for DeviceConfig {
@@ -58,11 +103,29 @@ impl Default for DeviceConfig {
             critical_battery_threshold: 10,
             report_interval_seconds: 60,
             high_temperature_threshold: 45.0,
+            rapid_drain_threshold: 20.0,
+            rapid_drain_window_minutes: 30,
             updated_at: Utc::now(),
+            battery_hysteresis: 5,
+            temperature_hysteresis: 2.0,
+            clock_correction: false,
         }
     }
 }
 
+/// 离线判定的截止时间：最后上报早于该时间的在线设备视为离线
+///
+/// 超时时长为上报间隔 × 宽限倍数（宽限倍数至少为 1，上报间隔至少 1 秒），
+/// 离线检测查询（`DeviceRepository::find_stale_online`）按同一公式计算
+pub fn offline_cutoff(
+    now: DateTime<Utc>,
+    report_interval_seconds: i32,
+    grace_multiplier: f64,
+) -> DateTime<Utc> {
+    let timeout_ms = report_interval_seconds.max(1) as f64 * grace_multiplier.max(1.0) * 1000.0;
+    now - Duration::milliseconds(timeout_ms.round() as i64)
+}
+
 /// 创建设备请求
 #[derive(Debug, Clone, Deserialize, Validate)]
 pub struct CreateDeviceRequest {
@@ -72,6 +135,14 @@ pub struct CreateDeviceRequest {
     #[validate(length(min = 1, max = 50, message = "设备类型长度应在 1-50 字符之间"))]
     pub device_type: String,
 
+    /// 电池组件标识，如 `["left", "right", "case"]`（单电池设备省略）
+    #[validate(custom(function = "validate_components"))]
+    #[serde(default)]
+    pub components: Vec<String>,
+
+    /// 所属网关设备（须为同一用户的设备）
+    pub gateway_id: Option<Uuid>,
+
     #[serde(default)]
     pub metadata: Option<serde_json::Value>,
 }
@@ -93,9 +164,45 @@ pub struct UpdateDeviceRequest {
 
     pub status: Option<DeviceStatus>,
 
+    /// 替换声明的电池组件（传空数组改为单电池设备，已上报的数据保留）
+    #[validate(custom(function = "validate_components"))]
+    pub components: Option<Vec<String>>,
+
+    /// 设置所属网关，传 `null` 解除关联
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub gateway_id: Option<Option<Uuid>>,
+
     pub metadata: Option<serde_json::Value>,
 }
 
+/// 组件标识：小写字母或数字开头，只包含小写字母、数字、下划线和连字符，最长 50 字符，
+/// 单台设备最多 16 个且不能重复
+fn validate_components(components: &[String]) -> Result<(), validator::ValidationError> {
+    lazy_static::lazy_static! {
+        static ref COMPONENT_REGEX: regex::Regex = regex::Regex::new(r"^[a-z0-9][a-z0-9_-]{0,49}$").unwrap();
+    }
+
+    if components.len() > Device::MAX_COMPONENTS {
+        return Err(validator::ValidationError::new(
+            "单台设备最多声明 16 个电池组件",
+        ));
+    }
+    if components.iter().any(|c| !COMPONENT_REGEX.is_match(c)) {
+        return Err(validator::ValidationError::new(
+            "组件标识应以小写字母或数字开头，只包含小写字母、数字、下划线和连字符，最长 50 字符",
+        ));
+    }
+    if components
+        .iter()
+        .enumerate()
+        .any(|(i, c)| components[..i].contains(c))
+    {
+        return Err(validator::ValidationError::new("组件标识不能重复"));
+    }
+
+    Ok(())
+}
+
 /// 更新设备配置请求
 #[derive(Debug, Clone, Deserialize, Validate)]
 pub struct UpdateDeviceConfigRequest {
@@ -110,6 +217,20 @@ pub struct UpdateDeviceConfigRequest {
 
     #[validate(range(min = -40.0, max = 200.0, message = "温度阈值应在 -40 到 200 摄氏度之间"))]
     pub high_temperature_threshold: Option<f64>,
+
+    #[validate(range(min = 1.0, max = 100.0, message = "快速耗电阈值应在 1-100 %/小时之间"))]
+    pub rapid_drain_threshold: Option<f64>,
+
+    #[validate(range(min = 5, max = 1440, message = "快速耗电检测窗口应在 5-1440 分钟之间"))]
+    pub rapid_drain_window_minutes: Option<i32>,
+
+    #[validate(range(min = 0, max = 50, message = "电量回差应在 0-50 之间"))]
+    pub battery_hysteresis: Option<i32>,
+
+    #[validate(range(min = 0.0, max = 20.0, message = "温度回差应在 0-20 摄氏度之间"))]
+    pub temperature_hysteresis: Option<f64>,
+
+    pub clock_correction: Option<bool>,
 }
 
 /// 设备列表查询参数
@@ -126,6 +247,9 @@ pub struct DeviceListQuery {
     pub status: Option<DeviceStatus>,
     pub device_type: Option<String>,
 
+    /// 只返回指定网关的子设备
+    pub gateway_id: Option<Uuid>,
+
     /// 按所有者筛选（用于用户查看自己的设备）
     #[serde(skip)]
     pub owner_id: Option<Uuid>,
@@ -228,6 +228,9 @@ pub struct CompatBatteryReportQuery {
 
     /// 时间戳（Unix 秒）
     pub ts: Option<i64>,
+
+    /// 客户端样本 ID（重试时保持不变，用于去重）
+    pub sample_id: Option<String>,
 }
 
 impl CompatBatteryReportQuery {
@@ -244,6 +247,10 @@ impl CompatBatteryReportQuery {
             temperature: self.temp,
             voltage: self.voltage,
             recorded_at,
+            sample_id: self.sample_id.clone(),
+            metrics: None,
+            component: None,
+            clock: None,
         }
     }
 }
@@ -0,0 +1,275 @@
+//! 数据导出模型
+
+use super::BatteryData;
+use chrono::{DateTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use uuid::Uuid;
+use validator::Validate;
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// 逗号分隔，首行为列名
+    #[default]
+    Csv,
+    /// 每行一个 JSON 对象
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// 可导出的列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportColumn {
+    Id,
+    DeviceId,
+    Component,
+    RecordedAt,
+    BatteryLevel,
+    IsCharging,
+    PowerSavingMode,
+    Temperature,
+    Voltage,
+    SampleId,
+    CreatedAt,
+    Metrics,
+}
+
+impl ExportColumn {
+    pub const ALL: [ExportColumn; 12] = [
+        ExportColumn::Id,
+        ExportColumn::DeviceId,
+        ExportColumn::Component,
+        ExportColumn::RecordedAt,
+        ExportColumn::BatteryLevel,
+        ExportColumn::IsCharging,
+        ExportColumn::PowerSavingMode,
+        ExportColumn::Temperature,
+        ExportColumn::Voltage,
+        ExportColumn::SampleId,
+        ExportColumn::CreatedAt,
+        ExportColumn::Metrics,
+    ];
+
+    /// 未指定 `columns` 时导出的列
+    pub const DEFAULT: [ExportColumn; 8] = [
+        ExportColumn::DeviceId,
+        ExportColumn::Component,
+        ExportColumn::RecordedAt,
+        ExportColumn::BatteryLevel,
+        ExportColumn::IsCharging,
+        ExportColumn::PowerSavingMode,
+        ExportColumn::Temperature,
+        ExportColumn::Voltage,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportColumn::Id => "id",
+            ExportColumn::DeviceId => "device_id",
+            ExportColumn::Component => "component",
+            ExportColumn::RecordedAt => "recorded_at",
+            ExportColumn::BatteryLevel => "battery_level",
+            ExportColumn::IsCharging => "is_charging",
+            ExportColumn::PowerSavingMode => "power_saving_mode",
+            ExportColumn::Temperature => "temperature",
+            ExportColumn::Voltage => "voltage",
+            ExportColumn::SampleId => "sample_id",
+            ExportColumn::CreatedAt => "created_at",
+            ExportColumn::Metrics => "metrics",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+}
+
+/// 导出请求
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct BatteryExportRequest {
+    /// 设备 ID，多个以逗号分隔
+    pub device_ids: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+
+    #[serde(default)]
+    pub format: ExportFormat,
+
+    /// 导出列，逗号分隔，默认见 [`ExportColumn::DEFAULT`]
+    pub columns: Option<String>,
+
+    /// 时间戳使用的时区（IANA 名称，如 `Asia/Shanghai`），默认 UTC
+    pub timezone: Option<String>,
+}
+
+impl BatteryExportRequest {
+    /// 单次导出的最大设备数
+    pub const MAX_DEVICES: usize = 50;
+
+    /// 解析设备 ID 列表（去重并保持顺序）
+    pub fn parse_device_ids(&self) -> Result<Vec<Uuid>, String> {
+        let mut seen = HashSet::new();
+        let mut ids = Vec::new();
+        for part in self.device_ids.split(',').map(str::trim) {
+            if part.is_empty() {
+                continue;
+            }
+            let id: Uuid = part
+                .parse()
+                .map_err(|_| format!("无效的设备 ID: {}", part))?;
+            if seen.insert(id) {
+                ids.push(id);
+            }
+        }
+
+        if ids.is_empty() {
+            return Err("至少需要一个设备 ID".to_string());
+        }
+        if ids.len() > Self::MAX_DEVICES {
+            return Err(format!("单次最多导出 {} 台设备的数据", Self::MAX_DEVICES));
+        }
+
+        Ok(ids)
+    }
+
+    /// 根据导出列和时区创建编码器
+    pub fn exporter(&self) -> Result<BatteryExporter, String> {
+        let columns = match self.columns.as_deref() {
+            None => ExportColumn::DEFAULT.to_vec(),
+            Some(columns) => {
+                let mut seen = HashSet::new();
+                let mut parsed = Vec::new();
+                for name in columns.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                    let column = ExportColumn::from_name(name)
+                        .ok_or_else(|| format!("不支持的导出列: {}", name))?;
+                    if seen.insert(column) {
+                        parsed.push(column);
+                    }
+                }
+                if parsed.is_empty() {
+                    return Err("至少需要导出一列".to_string());
+                }
+                parsed
+            }
+        };
+
+        let timezone = match self.timezone.as_deref() {
+            None => Tz::UTC,
+            Some(name) => name.parse().map_err(|_| format!("无效的时区: {}", name))?,
+        };
+
+        Ok(BatteryExporter {
+            format: self.format,
+            columns,
+            timezone,
+        })
+    }
+}
+
+/// 导出编码器，将电量数据逐行编码为 CSV / NDJSON
+#[derive(Debug, Clone)]
+pub struct BatteryExporter {
+    format: ExportFormat,
+    columns: Vec<ExportColumn>,
+    timezone: Tz,
+}
+
+impl BatteryExporter {
+    pub fn format(&self) -> ExportFormat {
+        self.format
+    }
+
+    /// 文件头（CSV 列名行），NDJSON 没有文件头
+    pub fn header(&self) -> Vec<u8> {
+        match self.format {
+            ExportFormat::Csv => {
+                let names: Vec<&str> = self.columns.iter().map(ExportColumn::name).collect();
+                format!("{}\r\n", names.join(",")).into_bytes()
+            }
+            ExportFormat::Ndjson => Vec::new(),
+        }
+    }
+
+    /// 编码一行数据并追加到 `buf`
+    pub fn write_row(&self, data: &BatteryData, buf: &mut Vec<u8>) {
+        match self.format {
+            ExportFormat::Csv => {
+                let fields: Vec<String> = self
+                    .columns
+                    .iter()
+                    .map(|column| match self.value(data, *column) {
+                        Value::Null => String::new(),
+                        Value::String(s) => csv_escape(&s),
+                        // 自定义指标以 JSON 文本写入
+                        other @ Value::Object(_) => csv_escape(&other.to_string()),
+                        other => other.to_string(),
+                    })
+                    .collect();
+                buf.extend_from_slice(fields.join(",").as_bytes());
+                buf.extend_from_slice(b"\r\n");
+            }
+            ExportFormat::Ndjson => {
+                let row: Map<String, Value> = self
+                    .columns
+                    .iter()
+                    .map(|column| (column.name().to_string(), self.value(data, *column)))
+                    .collect();
+                buf.extend_from_slice(Value::Object(row).to_string().as_bytes());
+                buf.push(b'\n');
+            }
+        }
+    }
+
+    fn value(&self, data: &BatteryData, column: ExportColumn) -> Value {
+        match column {
+            ExportColumn::Id => Value::String(data.id.to_string()),
+            ExportColumn::DeviceId => Value::String(data.device_id.to_string()),
+            ExportColumn::Component => data.component_key().map_or(Value::Null, Value::from),
+            ExportColumn::RecordedAt => Value::String(self.timestamp(data.recorded_at)),
+            ExportColumn::BatteryLevel => Value::from(data.battery_level),
+            ExportColumn::IsCharging => Value::from(data.is_charging),
+            ExportColumn::PowerSavingMode => {
+                serde_json::to_value(&data.power_saving_mode).unwrap_or(Value::Null)
+            }
+            ExportColumn::Temperature => data.temperature.map_or(Value::Null, Value::from),
+            ExportColumn::Voltage => data.voltage.map_or(Value::Null, Value::from),
+            ExportColumn::SampleId => data.sample_id.clone().map_or(Value::Null, Value::from),
+            ExportColumn::CreatedAt => Value::String(self.timestamp(data.created_at)),
+            ExportColumn::Metrics => data.metrics.clone().unwrap_or(Value::Null),
+        }
+    }
+
+    /// RFC 3339 格式，带所选时区的偏移量
+    fn timestamp(&self, at: DateTime<Utc>) -> String {
+        at.with_timezone(&self.timezone)
+            .to_rfc3339_opts(SecondsFormat::AutoSi, true)
+    }
+}
+
+/// CSV 字段转义（RFC 4180）
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,195 @@
+//! 电池健康模型
+
+use super::PowerSavingMode;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// 满充电量下降到该值（百分点）视为完全衰减
+const FULL_CHARGE_DROP_LIMIT: f64 = 20.0;
+/// 放电速率上升到该比例视为完全衰减
+const DISCHARGE_RATE_RISE_LIMIT: f64 = 0.5;
+/// 参考电量下电压下降到该值（伏特）视为完全衰减
+const VOLTAGE_DROP_LIMIT: f64 = 0.3;
+
+/// 各项指标在衰减评分中的权重（满充电量、放电速率、电压）
+const SCORE_WEIGHTS: [f64; 3] = [0.4, 0.35, 0.25];
+
+/// 电池健康查询参数
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct BatteryHealthQuery {
+    /// 统计最近多少天的数据
+    #[validate(range(min = 7, max = 365, message = "统计天数应在 7-365 之间"))]
+    #[serde(default = "default_health_days")]
+    pub days: i64,
+
+    /// 电池组件（多电池设备必填）
+    pub component: Option<String>,
+}
+
+fn default_health_days() -> i64 {
+    90
+}
+
+/// 趋势数据点（按周聚合）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq)]
+pub struct HealthTrendPoint {
+    pub bucket: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// 按周、按省电模式聚合的放电速率
+#[derive(Debug, Clone, FromRow)]
+pub struct ModeDischargeRate {
+    pub bucket: DateTime<Utc>,
+    pub power_saving_mode: PowerSavingMode,
+    /// 放电速率（%/小时）
+    pub value: f64,
+    /// 参与统计的放电时长（小时）
+    pub hours: f64,
+}
+
+/// 指标趋势
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HealthTrend {
+    pub points: Vec<HealthTrendPoint>,
+    /// 线性拟合斜率（每 30 天的变化量），少于 2 个数据点时为空
+    pub slope_per_30_days: Option<f64>,
+    /// 拟合线在首尾数据点之间的变化量
+    pub change: Option<f64>,
+    /// 拟合线在第一个数据点处的值
+    pub baseline: Option<f64>,
+}
+
+impl HealthTrend {
+    /// 对数据点做线性拟合
+    pub fn fit(points: Vec<HealthTrendPoint>) -> Self {
+        let (first, last) = match (points.first(), points.last()) {
+            (Some(f), Some(l)) if points.len() >= 2 && l.bucket > f.bucket => (f.bucket, l.bucket),
+            _ => {
+                return Self {
+                    points,
+                    slope_per_30_days: None,
+                    change: None,
+                    baseline: None,
+                }
+            }
+        };
+
+        let days = |t: DateTime<Utc>| (t - first).num_seconds() as f64 / 86400.0;
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|p| days(p.bucket)).sum::<f64>() / n;
+        let mean_y = points.iter().map(|p| p.value).sum::<f64>() / n;
+        let sxx: f64 = points
+            .iter()
+            .map(|p| (days(p.bucket) - mean_x).powi(2))
+            .sum();
+        let sxy: f64 = points
+            .iter()
+            .map(|p| (days(p.bucket) - mean_x) * (p.value - mean_y))
+            .sum();
+
+        let slope = sxy / sxx;
+        let baseline = mean_y - slope * mean_x;
+
+        Self {
+            slope_per_30_days: Some(slope * 30.0),
+            change: Some(slope * days(last)),
+            baseline: Some(baseline),
+            points,
+        }
+    }
+}
+
+/// 健康状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// 良好（评分 < 30）
+    Good,
+    /// 一般，建议关注（评分 30-60）
+    Fair,
+    /// 较差，建议更换（评分 ≥ 60）
+    Replace,
+}
+
+impl HealthStatus {
+    pub fn from_score(score: f64) -> Self {
+        if score >= 60.0 {
+            HealthStatus::Replace
+        } else if score >= 30.0 {
+            HealthStatus::Fair
+        } else {
+            HealthStatus::Good
+        }
+    }
+}
+
+/// 电池健康报告
+#[derive(Debug, Clone, Serialize)]
+pub struct BatteryHealthReport {
+    pub device_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+
+    /// 等效满充放循环次数
+    pub equivalent_cycles: f64,
+    /// 累计放电量（百分点）
+    pub discharged_percent: f64,
+    /// 充电会话累计充入电量（百分点）
+    pub charged_percent: f64,
+    pub charge_session_count: i64,
+
+    /// 每周充电会话达到的最高电量
+    pub full_charge_level: HealthTrend,
+    /// 每周放电速率（%/小时，取放电时长最多的省电模式）
+    pub discharge_rate: HealthTrend,
+    pub discharge_rate_mode: Option<PowerSavingMode>,
+    /// 每周参考电量（45%-55%）下的平均电压
+    pub voltage_at_reference: HealthTrend,
+
+    /// 衰减评分（0-100，越高衰减越严重），数据不足时为空
+    pub degradation_score: Option<f64>,
+    pub status: Option<HealthStatus>,
+}
+
+/// 计算衰减评分
+///
+/// 满充电量下降、同一省电模式下放电速率上升、参考电量下电压下降三项各自归一化到 0-100，
+/// 按权重对有数据的指标求加权平均；三项均无数据时返回 `None`
+pub fn degradation_score(
+    full_charge_level: &HealthTrend,
+    discharge_rate: &HealthTrend,
+    voltage_at_reference: &HealthTrend,
+) -> Option<f64> {
+    let normalize = |value: f64| (value * 100.0).clamp(0.0, 100.0);
+
+    let components = [
+        full_charge_level
+            .change
+            .map(|c| normalize(-c / FULL_CHARGE_DROP_LIMIT)),
+        discharge_rate
+            .change
+            .zip(discharge_rate.baseline)
+            .filter(|(_, base)| *base > 0.0)
+            .map(|(c, base)| normalize(c / base / DISCHARGE_RATE_RISE_LIMIT)),
+        voltage_at_reference
+            .change
+            .map(|c| normalize(-c / VOLTAGE_DROP_LIMIT)),
+    ];
+
+    let mut weighted = 0.0;
+    let mut total_weight = 0.0;
+    for (component, weight) in components.iter().zip(SCORE_WEIGHTS) {
+        if let Some(value) = component {
+            weighted += value * weight;
+            total_weight += weight;
+        }
+    }
+
+    (total_weight > 0.0).then(|| weighted / total_weight)
+}
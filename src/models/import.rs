@@ -0,0 +1,272 @@
+//! 数据导入模型
+
+use super::{BatteryReportRequest, PowerSavingMode};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// 导入格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// 逗号分隔，首行为列名
+    #[default]
+    Csv,
+    /// 每行一个 JSON 对象
+    Ndjson,
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "csv" => Ok(ImportFormat::Csv),
+            "ndjson" | "jsonl" => Ok(ImportFormat::Ndjson),
+            _ => Err(format!("不支持的导入格式: {}", s)),
+        }
+    }
+}
+
+/// 导入请求参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatteryImportQuery {
+    #[serde(default)]
+    pub format: ImportFormat,
+}
+
+/// 解析后的一条记录
+#[derive(Debug, Clone)]
+pub struct ImportRecord {
+    /// 所在行号（从 1 开始）
+    pub line: usize,
+    pub request: BatteryReportRequest,
+}
+
+/// 行级错误
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ImportLineError {
+    pub line: usize,
+    pub error: String,
+}
+
+/// 文件解析结果
+#[derive(Debug, Clone, Default)]
+pub struct ParsedImport {
+    pub records: Vec<ImportRecord>,
+    pub errors: Vec<ImportLineError>,
+}
+
+/// 导入报告
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub device_id: Uuid,
+    /// 数据行数（不含表头和空行）
+    pub total_rows: usize,
+    pub inserted_count: usize,
+    /// 已存在或文件内重复的样本数
+    pub duplicate_count: usize,
+    pub rejected_count: usize,
+    /// 被拒绝的行（按行号排序，最多返回 [`ImportReport::MAX_ERRORS`] 条）
+    pub errors: Vec<ImportLineError>,
+    pub errors_truncated: bool,
+}
+
+impl ImportReport {
+    /// 报告中最多返回的行级错误数
+    pub const MAX_ERRORS: usize = 1000;
+}
+
+/// CSV 中可识别的列，其余列（如导出文件中的 `device_id`、`id`）忽略
+const CSV_COLUMNS: [&str; 9] = [
+    "recorded_at",
+    "battery_level",
+    "is_charging",
+    "power_saving_mode",
+    "temperature",
+    "voltage",
+    "sample_id",
+    "metrics",
+    "component",
+];
+
+/// 解析导入文件
+///
+/// 无法解析或缺少记录时间的行记为行级错误，不影响其余行；
+/// 文件本身无法处理（如 CSV 表头缺少必需列）时返回错误
+pub fn parse_import(format: ImportFormat, content: &str) -> Result<ParsedImport, String> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let lines = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    match format {
+        ImportFormat::Csv => parse_csv(lines),
+        ImportFormat::Ndjson => Ok(parse_ndjson(lines)),
+    }
+}
+
+fn parse_ndjson<'a>(lines: impl Iterator<Item = (usize, &'a str)>) -> ParsedImport {
+    let mut parsed = ParsedImport::default();
+    for (line, text) in lines {
+        let result = serde_json::from_str::<BatteryReportRequest>(text)
+            .map_err(|e| format!("JSON 解析失败: {}", e))
+            .and_then(require_recorded_at);
+        push_result(&mut parsed, line, result);
+    }
+    parsed
+}
+
+fn parse_csv<'a>(
+    mut lines: impl Iterator<Item = (usize, &'a str)>,
+) -> Result<ParsedImport, String> {
+    let (header_line, header) = lines.next().ok_or("导入文件没有数据")?;
+    let names = split_csv_line(header).map_err(|e| format!("第 {} 行表头{}", header_line, e))?;
+
+    // 每个可识别列在表头中的位置
+    let mut positions: [Option<usize>; CSV_COLUMNS.len()] = [None; CSV_COLUMNS.len()];
+    for (index, name) in names.iter().enumerate() {
+        if let Some(column) = CSV_COLUMNS.iter().position(|c| *c == name.trim()) {
+            if positions[column].replace(index).is_some() {
+                return Err(format!("表头列重复: {}", CSV_COLUMNS[column]));
+            }
+        }
+    }
+    for required in ["recorded_at", "battery_level"] {
+        let column = CSV_COLUMNS.iter().position(|c| *c == required).unwrap();
+        if positions[column].is_none() {
+            return Err(format!("表头缺少必需列: {}", required));
+        }
+    }
+
+    let mut parsed = ParsedImport::default();
+    for (line, text) in lines {
+        let result = split_csv_line(text).and_then(|fields| {
+            if fields.len() != names.len() {
+                return Err(format!(
+                    "列数与表头不一致（{} 列，表头 {} 列）",
+                    fields.len(),
+                    names.len()
+                ));
+            }
+            let field = |column: &str| {
+                let index = CSV_COLUMNS.iter().position(|c| *c == column).unwrap();
+                positions[index]
+                    .map(|i| fields[i].trim())
+                    .filter(|v| !v.is_empty())
+            };
+            csv_record(field)
+        });
+        push_result(&mut parsed, line, result);
+    }
+
+    Ok(parsed)
+}
+
+/// 由 CSV 字段构造上报请求，空字段视为未提供
+fn csv_record<'a>(field: impl Fn(&str) -> Option<&'a str>) -> Result<BatteryReportRequest, String> {
+    let recorded_at = field("recorded_at")
+        .map(|v| {
+            DateTime::parse_from_rfc3339(v)
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(|_| format!("无效的记录时间: {}", v))
+        })
+        .transpose()?;
+
+    let battery_level = field("battery_level")
+        .ok_or("缺少电量值 battery_level")?
+        .parse::<i32>()
+        .map_err(|_| "电量值应为整数".to_string())?;
+
+    let is_charging = match field("is_charging").map(str::to_ascii_lowercase).as_deref() {
+        None | Some("false") | Some("0") => false,
+        Some("true") | Some("1") => true,
+        Some(v) => return Err(format!("无效的充电状态: {}", v)),
+    };
+
+    let power_saving_mode = field("power_saving_mode")
+        .map(|v| {
+            serde_json::from_value::<PowerSavingMode>(serde_json::Value::from(v))
+                .map_err(|_| format!("无效的省电模式: {}", v))
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    // 自定义指标为 JSON 对象文本（与导出格式一致）
+    let metrics = field("metrics")
+        .map(|v| {
+            serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(v)
+                .map_err(|_| "自定义指标应为 JSON 对象".to_string())
+        })
+        .transpose()?;
+
+    let number = |column: &str, label: &str| {
+        field(column)
+            .map(|v| v.parse::<f64>().map_err(|_| format!("{}应为数字", label)))
+            .transpose()
+    };
+
+    require_recorded_at(BatteryReportRequest {
+        battery_level,
+        is_charging,
+        power_saving_mode,
+        temperature: number("temperature", "温度值")?,
+        voltage: number("voltage", "电压值")?,
+        recorded_at,
+        sample_id: field("sample_id").map(str::to_string),
+        metrics,
+        component: field("component").map(str::to_string),
+        clock: None,
+    })
+}
+
+/// 导入的历史数据必须带记录时间
+fn require_recorded_at(request: BatteryReportRequest) -> Result<BatteryReportRequest, String> {
+    if request.recorded_at.is_none() {
+        return Err("缺少记录时间 recorded_at".to_string());
+    }
+    Ok(request)
+}
+
+fn push_result(
+    parsed: &mut ParsedImport,
+    line: usize,
+    result: Result<BatteryReportRequest, String>,
+) {
+    match result {
+        Ok(request) => parsed.records.push(ImportRecord { line, request }),
+        Err(error) => parsed.errors.push(ImportLineError { line, error }),
+    }
+}
+
+/// 拆分一行 CSV（RFC 4180 引号转义，不支持字段内换行）
+fn split_csv_line(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => fields.push(std::mem::take(&mut field)),
+            (false, c) => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err("引号未闭合".to_string());
+    }
+    fields.push(field);
+
+    Ok(fields)
+}
@@ -0,0 +1,164 @@
+//! 自定义指标模型
+
+use super::deserialize_nullable;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// 指标值类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "metric_value_type", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum MetricValueType {
+    Integer,
+    Float,
+    /// 聚合和预警时按 0 / 1 计算
+    Boolean,
+}
+
+/// 指标定义（按设备类型注册）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MetricDefinition {
+    pub id: Uuid,
+    pub device_type: String,
+    /// 上报时使用的字段名
+    pub key: String,
+    pub value_type: MetricValueType,
+    pub unit: Option<String>,
+    pub description: Option<String>,
+    /// 允许的最小值（含），为空时不限制
+    pub min_value: Option<f64>,
+    /// 允许的最大值（含），为空时不限制
+    pub max_value: Option<f64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MetricDefinition {
+    /// 校验上报的指标值，`null` 视为未上报
+    pub fn check(&self, value: &Value) -> Result<(), String> {
+        let number = match (self.value_type, value) {
+            (_, Value::Null) => return Ok(()),
+            (MetricValueType::Boolean, Value::Bool(_)) => return Ok(()),
+            (MetricValueType::Boolean, _) => {
+                return Err(format!("指标 {} 应为布尔值", self.key));
+            }
+            (MetricValueType::Integer, Value::Number(n)) if n.is_i64() || n.is_u64() => {
+                n.as_f64().unwrap_or_default()
+            }
+            (MetricValueType::Integer, _) => return Err(format!("指标 {} 应为整数", self.key)),
+            (MetricValueType::Float, Value::Number(n)) => n.as_f64().unwrap_or_default(),
+            (MetricValueType::Float, _) => return Err(format!("指标 {} 应为数字", self.key)),
+        };
+
+        match (self.min_value, self.max_value) {
+            (Some(min), Some(max)) if number < min || number > max => {
+                Err(format!("指标 {} 应在 {} 到 {} 之间", self.key, min, max))
+            }
+            (Some(min), None) if number < min => Err(format!("指标 {} 不能小于 {}", self.key, min)),
+            (None, Some(max)) if number > max => Err(format!("指标 {} 不能大于 {}", self.key, max)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 按设备类型的指标定义校验上报的指标，未注册的指标视为错误
+pub fn validate_metrics(
+    definitions: &[MetricDefinition],
+    metrics: &Map<String, Value>,
+) -> Result<(), String> {
+    for (key, value) in metrics {
+        definitions
+            .iter()
+            .find(|d| d.key == *key)
+            .ok_or_else(|| format!("未注册的指标: {}", key))?
+            .check(value)?;
+    }
+
+    Ok(())
+}
+
+/// 读取指标的数值（布尔值按 0 / 1），缺失或为 `null` 时返回 `None`
+pub fn metric_value(metrics: &Value, key: &str) -> Option<f64> {
+    match metrics.get(key)? {
+        Value::Number(n) => n.as_f64(),
+        Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+        _ => None,
+    }
+}
+
+/// 创建指标定义请求
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateMetricDefinitionRequest {
+    #[validate(length(min = 1, max = 50, message = "设备类型长度应在 1-50 字符之间"))]
+    pub device_type: String,
+
+    #[validate(custom(function = "validate_metric_key"))]
+    pub key: String,
+
+    pub value_type: MetricValueType,
+
+    #[validate(length(min = 1, max = 20, message = "单位长度应在 1-20 字符之间"))]
+    pub unit: Option<String>,
+
+    #[validate(length(max = 200, message = "描述最多 200 字符"))]
+    pub description: Option<String>,
+
+    pub min_value: Option<f64>,
+    pub max_value: Option<f64>,
+}
+
+/// 更新指标定义请求（设备类型、键和值类型不可修改）
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct UpdateMetricDefinitionRequest {
+    /// 传 `null` 清除单位
+    #[validate(length(min = 1, max = 20, message = "单位长度应在 1-20 字符之间"))]
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub unit: Option<Option<String>>,
+
+    /// 传 `null` 清除描述
+    #[validate(length(max = 200, message = "描述最多 200 字符"))]
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub description: Option<Option<String>>,
+
+    /// 传 `null` 取消最小值限制
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub min_value: Option<Option<f64>>,
+
+    /// 传 `null` 取消最大值限制
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub max_value: Option<Option<f64>>,
+}
+
+/// 指标定义列表查询参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetricDefinitionQuery {
+    pub device_type: Option<String>,
+}
+
+/// 指标键：小写字母开头，只包含小写字母、数字和下划线，最长 50 字符
+fn validate_metric_key(key: &str) -> Result<(), validator::ValidationError> {
+    lazy_static::lazy_static! {
+        static ref METRIC_KEY_REGEX: regex::Regex = regex::Regex::new(r"^[a-z][a-z0-9_]{0,49}$").unwrap();
+    }
+    if METRIC_KEY_REGEX.is_match(key) {
+        Ok(())
+    } else {
+        Err(validator::ValidationError::new(
+            "指标键应以小写字母开头，只包含小写字母、数字和下划线，最长 50 字符",
+        ))
+    }
+}
+
+/// 单个时间桶内的指标聚合
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MetricAggregate {
+    pub avg: f64,
+    pub min: f64,
+    pub max: f64,
+    /// 非空样本数
+    pub count: i64,
+}
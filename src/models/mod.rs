@@ -3,17 +3,29 @@
 mod alert;
 mod audit;
 mod battery;
+mod charging;
+mod clock;
 mod common;
 mod device;
 mod device_token;
+mod export;
+mod health;
+mod import;
+mod metric;
 mod notification;
 mod user;
 
 pub use alert::*;
 pub use audit::*;
 pub use battery::*;
+pub use charging::*;
+pub use clock::*;
 pub use common::*;
 pub use device::*;
 pub use device_token::*;
+pub use export::*;
+pub use health::*;
+pub use import::*;
+pub use metric::*;
 pub use notification::*;
 pub use user::*;
@@ -1,5 +1,6 @@
 //! 通知模型
 
+use crate::models::AlertEvent;
 use chrono::{DateTime, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
@@ -152,11 +153,81 @@ pub struct NotificationHistory {
     pub channel: NotificationChannel,
     pub recipient: String,
 
-    pub status: String, // 'pending', 'sent', 'failed', 'skipped'
+    pub status: String, // 'pending', 'sent', 'failed', 'skipped', 'dead'
     pub error_message: Option<String>,
 
     pub sent_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
+
+    /// 投递内容快照（发件箱记录）
+    pub payload: Option<serde_json::Value>,
+    /// 已尝试次数
+    pub attempts: i32,
+    /// 下次投递时间
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    /// 最近一次投递时间
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    /// 投递租约到期时间
+    #[serde(skip_serializing)]
+    pub locked_until: Option<DateTime<Utc>>,
+
+    /// 事件类型（alert_triggered / alert_resolved）
+    pub event_type: String,
+}
+
+/// 通知事件类型：预警触发
+pub const EVENT_ALERT_TRIGGERED: &str = "alert_triggered";
+/// 通知事件类型：预警解决
+pub const EVENT_ALERT_RESOLVED: &str = "alert_resolved";
+
+/// 事件类型是否受通知频率限制（仅触发通知受限，解决通知总是发送）
+pub fn is_rate_limited_event(event_type: &str) -> bool {
+    event_type == EVENT_ALERT_TRIGGERED
+}
+
+/// 发件箱投递内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPayload {
+    pub alert_event: AlertEvent,
+    pub device_name: String,
+}
+
+impl NotificationPayload {
+    /// 推送通知标题（解决通知以「已恢复」开头）
+    pub fn title(&self, event_type: &str) -> String {
+        if event_type == EVENT_ALERT_RESOLVED {
+            format!("已恢复 - {:?}", self.alert_event.alert_type)
+        } else {
+            format!(
+                "{:?} - {:?}",
+                self.alert_event.level, self.alert_event.alert_type
+            )
+        }
+    }
+}
+
+/// 通知发件箱查询参数（管理员）
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct NotificationOutboxQuery {
+    /// 按状态筛选（pending / failed / dead / sent / skipped）
+    pub status: Option<String>,
+    pub channel: Option<NotificationChannel>,
+    pub user_id: Option<Uuid>,
+
+    #[validate(range(min = 1, max = 100, message = "每页数量应在 1-100 之间"))]
+    #[serde(default = "default_page_size")]
+    pub page_size: i64,
+
+    #[validate(range(min = 1, message = "页码应大于 0"))]
+    #[serde(default = "default_page")]
+    pub page: i64,
+}
+
+fn default_page_size() -> i64 {
+    20
+}
+fn default_page() -> i64 {
+    1
 }
 
 /// 创建/更新通知偏好请求
@@ -4,9 +4,9 @@ use crate::db::PostgresPool;
 use crate::errors::AppError;
 use crate::models::{
     AlertEvent, AlertListQuery, AlertRule, AlertStatus, AlertType, CreateAlertRuleRequest,
-    UpdateAlertRuleRequest, UpdateAlertStatusRequest,
+    UpdateAlertStatusRequest,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 /// 预警数据仓库
@@ -33,8 +33,11 @@ impl AlertRepository {
 
         let rule = sqlx::query_as::<_, AlertRule>(
             r#"
-            INSERT INTO alert_rules (id, user_id, name, alert_type, level, cooldown_minutes, enabled, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            INSERT INTO alert_rules (
+                id, user_id, name, alert_type, level, cooldown_minutes, enabled, created_at, updated_at,
+                scope, device_ids, device_type, threshold, comparison, metric_key
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
             RETURNING *
             "#,
         )
@@ -47,6 +50,12 @@ impl AlertRepository {
         .bind(request.enabled)
         .bind(now)
         .bind(now)
+        .bind(request.scope)
+        .bind(&request.device_ids)
+        .bind(&request.device_type)
+        .bind(request.threshold)
+        .bind(request.comparison)
+        .bind(&request.metric_key)
         .fetch_one(self.pool.pool())
         .await?;
 
@@ -65,21 +74,70 @@ impl AlertRepository {
         Ok(rules)
     }
 
-    /// 根据类型获取用户的规则
-    pub async fn get_rule_by_type(
+    /// 获取对设备生效的启用规则（所有者的全部设备、指定设备或设备类型）
+    pub async fn find_device_rules(
         &self,
         user_id: Uuid,
-        alert_type: &AlertType,
-    ) -> Result<Option<AlertRule>, AppError> {
-        let rule = sqlx::query_as::<_, AlertRule>(
-            "SELECT * FROM alert_rules WHERE user_id = $1 AND alert_type = $2 AND enabled = true",
+        device_id: Uuid,
+        device_type: &str,
+    ) -> Result<Vec<AlertRule>, AppError> {
+        let rules = sqlx::query_as::<_, AlertRule>(
+            r#"
+            SELECT * FROM alert_rules
+            WHERE user_id = $1
+              AND enabled = true
+              AND (
+                  scope = 'all'
+                  OR (scope = 'devices' AND $2 = ANY(device_ids))
+                  OR (scope = 'device_type' AND device_type = $3)
+              )
+            "#,
         )
         .bind(user_id)
-        .bind(alert_type)
-        .fetch_optional(self.pool.pool())
+        .bind(device_id)
+        .bind(device_type)
+        .fetch_all(self.pool.pool())
         .await?;
 
-        Ok(rule)
+        Ok(rules)
+    }
+
+    /// 指标键是否已注册（指定设备类型时只查该类型）
+    pub async fn metric_registered(
+        &self,
+        key: &str,
+        device_type: Option<&str>,
+    ) -> Result<bool, AppError> {
+        let result: (bool,) = sqlx::query_as(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM metric_definitions
+                WHERE key = $1 AND ($2::text IS NULL OR device_type = $2)
+            )
+            "#,
+        )
+        .bind(key)
+        .bind(device_type)
+        .fetch_one(self.pool.pool())
+        .await?;
+
+        Ok(result.0)
+    }
+
+    /// 统计设备列表中属于用户的设备数
+    pub async fn count_owned_devices(
+        &self,
+        user_id: Uuid,
+        device_ids: &[Uuid],
+    ) -> Result<i64, AppError> {
+        let result: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM devices WHERE owner_id = $1 AND id = ANY($2)")
+                .bind(user_id)
+                .bind(device_ids)
+                .fetch_one(self.pool.pool())
+                .await?;
+
+        Ok(result.0)
     }
 
     /// 根据 ID 获取规则（仅限用户自己的规则）
@@ -99,42 +157,47 @@ impl AlertRepository {
         Ok(rule)
     }
 
-    /// 更新预警规则（仅限用户自己的规则）
-    pub async fn update_rule(
-        &self,
-        rule_id: Uuid,
-        user_id: Uuid,
-        request: &UpdateAlertRuleRequest,
-    ) -> Result<AlertRule, AppError> {
-        let now = Utc::now();
-
-        // 使用 COALESCE 实现部分更新
+    /// 保存预警规则（仅限用户自己的规则）
+    ///
+    /// 由服务层合并部分更新后写入完整的可变字段
+    pub async fn update_rule(&self, rule: &AlertRule) -> Result<AlertRule, AppError> {
         let rule = sqlx::query_as::<_, AlertRule>(
             r#"
             UPDATE alert_rules SET
-                name = COALESCE($3, name),
-                alert_type = COALESCE($4, alert_type),
-                level = COALESCE($5, level),
-                cooldown_minutes = COALESCE($6, cooldown_minutes),
-                enabled = COALESCE($7, enabled),
-                updated_at = $8
+                name = $3,
+                alert_type = $4,
+                level = $5,
+                cooldown_minutes = $6,
+                enabled = $7,
+                scope = $8,
+                device_ids = $9,
+                device_type = $10,
+                threshold = $11,
+                comparison = $12,
+                metric_key = $13,
+                updated_at = NOW()
             WHERE id = $1 AND user_id = $2
             RETURNING *
             "#,
         )
-        .bind(rule_id)
-        .bind(user_id)
-        .bind(&request.name)
-        .bind(&request.alert_type)
-        .bind(&request.level)
-        .bind(request.cooldown_minutes)
-        .bind(request.enabled)
-        .bind(now)
+        .bind(rule.id)
+        .bind(rule.user_id)
+        .bind(&rule.name)
+        .bind(&rule.alert_type)
+        .bind(&rule.level)
+        .bind(rule.cooldown_minutes)
+        .bind(rule.enabled)
+        .bind(rule.scope)
+        .bind(&rule.device_ids)
+        .bind(&rule.device_type)
+        .bind(rule.threshold)
+        .bind(rule.comparison)
+        .bind(&rule.metric_key)
         .fetch_one(self.pool.pool())
         .await
         .map_err(|e| match e {
             sqlx::Error::RowNotFound => {
-                AppError::NotFound(format!("预警规则不存在或无权访问: {}", rule_id))
+                AppError::NotFound(format!("预警规则不存在或无权访问: {}", rule.id))
             }
             _ => e.into(),
         })?;
@@ -162,21 +225,26 @@ impl AlertRepository {
 
     // ========== 预警事件 ==========
 
-    /// 创建预警事件（使用设备配置的阈值）
+    /// 创建预警事件（记录实际使用的阈值和触发预警的电池组件）
+    ///
+    /// `triggered_at` 为触发预警的数据的记录时间（补传的历史数据不使用写入时间）
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_event(
         &self,
         device_id: Uuid,
+        component: Option<&str>,
         rule: &AlertRule,
         value: f64,
         threshold: f64,
         message: &str,
+        triggered_at: DateTime<Utc>,
     ) -> Result<AlertEvent, AppError> {
         let id = Uuid::new_v4();
 
         let event = sqlx::query_as::<_, AlertEvent>(
             r#"
-            INSERT INTO alert_events (id, device_id, rule_id, alert_type, level, status, message, value, threshold, triggered_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())
+            INSERT INTO alert_events (id, device_id, rule_id, alert_type, level, status, message, value, threshold, metric_key, component, triggered_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
             RETURNING *
             "#,
         )
@@ -189,30 +257,45 @@ impl AlertRepository {
         .bind(message)
         .bind(value)
         .bind(threshold)
+        .bind(&rule.metric_key)
+        .bind(component)
+        .bind(triggered_at)
         .fetch_one(self.pool.pool())
         .await?;
 
         Ok(event)
     }
 
-    /// 检查是否在冷却期内
+    /// 检查是否在冷却期内（自定义指标按指标键、多电池设备按组件分别冷却）
+    ///
+    /// 以数据的记录时间 `at` 为准：前后冷却时长内已有同类预警即处于冷却期，
+    /// 补传的历史数据中相隔较远的越限仍会分别触发
     pub async fn is_in_cooldown(
         &self,
         device_id: Uuid,
         alert_type: &AlertType,
+        metric_key: Option<&str>,
+        component: Option<&str>,
         cooldown_minutes: i32,
+        at: DateTime<Utc>,
     ) -> Result<bool, AppError> {
         let result: Option<(i64,)> = sqlx::query_as(
             r#"
             SELECT COUNT(*) FROM alert_events
             WHERE device_id = $1 
               AND alert_type = $2 
-              AND triggered_at > NOW() - INTERVAL '1 minute' * $3
+              AND metric_key IS NOT DISTINCT FROM $4
+              AND component IS NOT DISTINCT FROM $5
+              AND triggered_at > $6 - INTERVAL '1 minute' * $3
+              AND triggered_at < $6 + INTERVAL '1 minute' * $3
             "#,
         )
         .bind(device_id)
         .bind(alert_type)
         .bind(cooldown_minutes)
+        .bind(metric_key)
+        .bind(component)
+        .bind(at)
         .fetch_optional(self.pool.pool())
         .await?;
 
@@ -292,6 +375,97 @@ impl AlertRepository {
         Ok(event)
     }
 
+    /// 查询设备未解决的预警（活跃和已确认）
+    pub async fn find_unresolved_events(
+        &self,
+        device_id: Uuid,
+    ) -> Result<Vec<AlertEvent>, AppError> {
+        let events = sqlx::query_as::<_, AlertEvent>(
+            r#"
+            SELECT * FROM alert_events
+            WHERE device_id = $1 AND status IN ('active', 'acknowledged')
+            "#,
+        )
+        .bind(device_id)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(events)
+    }
+
+    /// 自动解决指定的未解决预警
+    ///
+    /// 解决时间取数据的记录时间 `at`，离线预警取当前时间；已被解决的预警不重复返回
+    pub async fn resolve_events(
+        &self,
+        device_id: Uuid,
+        event_ids: &[Uuid],
+        at: DateTime<Utc>,
+    ) -> Result<Vec<AlertEvent>, AppError> {
+        let events = sqlx::query_as::<_, AlertEvent>(
+            r#"
+            UPDATE alert_events
+            SET status = 'resolved',
+                resolved_at = CASE WHEN alert_type = 'device_offline' THEN NOW() ELSE $3 END
+            WHERE device_id = $1
+              AND id = ANY($2)
+              AND status IN ('active', 'acknowledged')
+            RETURNING *
+            "#,
+        )
+        .bind(device_id)
+        .bind(event_ids)
+        .bind(at)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(events)
+    }
+
+    /// 自动解决指标已恢复正常的自定义指标预警
+    ///
+    /// `keys` 与 `values` 一一对应，只处理本次上报了的指标；
+    /// 按触发规则的比较方式判断，指标不再越过事件记录的阈值即解决
+    /// （只处理同一组件、触发时间不晚于记录时间 `at` 的预警，解决时间取 `at`）
+    pub async fn resolve_recovered_metric_events(
+        &self,
+        device_id: Uuid,
+        component: Option<&str>,
+        keys: &[String],
+        values: &[f64],
+        at: DateTime<Utc>,
+    ) -> Result<Vec<AlertEvent>, AppError> {
+        let events = sqlx::query_as::<_, AlertEvent>(
+            r#"
+            UPDATE alert_events e SET status = 'resolved', resolved_at = $5
+            FROM alert_rules r, UNNEST($2::text[], $3::float8[]) AS m(key, value)
+            WHERE e.device_id = $1
+              AND e.rule_id = r.id
+              AND e.alert_type = 'custom_metric'
+              AND e.status IN ('active', 'acknowledged')
+              AND e.metric_key = m.key
+              AND e.component IS NOT DISTINCT FROM $4
+              AND e.triggered_at <= $5
+              AND NOT CASE COALESCE(r.comparison, 'gt')
+                  WHEN 'lt' THEN m.value < e.threshold
+                  WHEN 'lte' THEN m.value <= e.threshold
+                  WHEN 'gt' THEN m.value > e.threshold
+                  ELSE m.value >= e.threshold
+              END
+            RETURNING e.*
+            "#,
+        )
+        .bind(device_id)
+        .bind(keys)
+        .bind(values)
+        .bind(component)
+        .bind(at)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(events)
+    }
+
     /// 查询预警事件列表（限制用户只能查询自己设备的预警）
     pub async fn list_events(
         &self,
@@ -374,6 +548,22 @@ impl AlertRepository {
         Ok((events, total))
     }
 
+    /// 获取设备预警的接收用户（所有者及共享用户）
+    pub async fn get_device_audience(&self, device_id: Uuid) -> Result<Vec<Uuid>, AppError> {
+        let rows: Vec<(Uuid,)> = sqlx::query_as(
+            r#"
+            SELECT owner_id FROM devices WHERE id = $1 AND owner_id IS NOT NULL
+            UNION
+            SELECT user_id FROM device_shares WHERE device_id = $1
+            "#,
+        )
+        .bind(device_id)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.0).collect())
+    }
+
     /// 获取设备的活跃预警数
     pub async fn count_active_alerts(&self, device_id: Uuid) -> Result<i64, AppError> {
         let result: (i64,) = sqlx::query_as(
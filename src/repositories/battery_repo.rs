@@ -4,11 +4,25 @@ use crate::db::PostgresPool;
 use crate::errors::AppError;
 use crate::models::{
     AggregateInterval, BatteryAggregatePoint, BatteryData, BatteryQueryRequest,
-    BatteryReportRequest, BatteryStatsResponse,
+    BatteryReportRequest, BatteryStatsResponse, GapFill, HealthTrendPoint, HistoryCursor,
+    MetricAggregate, ModeDischargeRate, RejectedBatteryRecord,
 };
 use chrono::{DateTime, Utc};
+use sqlx::Acquire;
+use std::collections::HashSet;
 use uuid::Uuid;
 
+/// 批量写入结果
+#[derive(Debug, Default)]
+pub struct BatchInsertResult {
+    /// 新写入的数据
+    pub inserted: Vec<BatteryData>,
+    /// 违反约束被拒绝的记录
+    pub rejected: Vec<RejectedBatteryRecord>,
+    /// 重复样本在请求中的下标
+    pub duplicates: Vec<usize>,
+}
+
 /// 电量数据仓库
 #[derive(Clone)]
 pub struct BatteryRepository {
@@ -21,18 +35,32 @@ impl BatteryRepository {
     }
 
     /// 插入电量数据
+    ///
+    /// 同一设备（组件）已存在相同记录时间的数据，或 `dedupe_since` 之后已存在相同样本 ID 的数据时不写入，
+    /// 返回已存在的数据；第二个返回值表示是否为新写入。
+    /// 去重由语句本身判断，历史数据存在重复、未能建立唯一索引时同样生效
     pub async fn insert(
         &self,
         device_id: Uuid,
         request: &BatteryReportRequest,
-    ) -> Result<BatteryData, AppError> {
+        dedupe_since: DateTime<Utc>,
+    ) -> Result<(BatteryData, bool), AppError> {
         let id = Uuid::new_v4();
         let recorded_at = request.recorded_at.unwrap_or_else(Utc::now);
 
-        let data = sqlx::query_as::<_, BatteryData>(
+        let inserted = sqlx::query_as::<_, BatteryData>(
             r#"
-            INSERT INTO battery_data (id, device_id, battery_level, is_charging, power_saving_mode, temperature, voltage, recorded_at, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+            INSERT INTO battery_data (id, device_id, component, battery_level, is_charging, power_saving_mode, temperature, voltage, recorded_at, sample_id, metrics, clock_flag, device_recorded_at, created_at)
+            SELECT $1, $2, $12, $3, $4, $5, $6, $7, $8, $9, $11, $13, $14, NOW()
+            WHERE NOT EXISTS (
+                SELECT 1 FROM battery_data
+                WHERE device_id = $2 AND component = $12 AND recorded_at = $8
+            )
+            AND ($9::text IS NULL OR NOT EXISTS (
+                SELECT 1 FROM battery_data
+                WHERE device_id = $2 AND component = $12 AND sample_id = $9 AND recorded_at >= $10
+            ))
+            ON CONFLICT DO NOTHING
             RETURNING *
             "#,
         )
@@ -44,64 +72,281 @@ impl BatteryRepository {
         .bind(request.temperature)
         .bind(request.voltage)
         .bind(recorded_at)
-        .fetch_one(self.pool.pool())
+        .bind(&request.sample_id)
+        .bind(dedupe_since)
+        .bind(request.stored_metrics())
+        .bind(request.stored_component())
+        .bind(request.clock.map(|c| c.flag))
+        .bind(request.clock.map(|c| c.device_recorded_at))
+        .fetch_optional(self.pool.pool())
+        .await?;
+
+        if let Some(data) = inserted {
+            return Ok((data, true));
+        }
+
+        let existing = self
+            .find_duplicate(
+                device_id,
+                request.stored_component(),
+                request.sample_id.as_deref(),
+                recorded_at,
+                dedupe_since,
+            )
+            .await?
+            .ok_or_else(|| AppError::InternalError("重复电量数据查询失败".to_string()))?;
+
+        Ok((existing, false))
+    }
+
+    /// 查找组件已存在的同一样本（相同记录时间，或 `dedupe_since` 之后相同样本 ID）
+    pub async fn find_duplicate(
+        &self,
+        device_id: Uuid,
+        component: &str,
+        sample_id: Option<&str>,
+        recorded_at: DateTime<Utc>,
+        dedupe_since: DateTime<Utc>,
+    ) -> Result<Option<BatteryData>, AppError> {
+        let data = sqlx::query_as::<_, BatteryData>(
+            r#"
+            SELECT * FROM battery_data
+            WHERE device_id = $1 AND component = $5
+              AND (recorded_at = $2 OR (sample_id = $3 AND recorded_at >= $4))
+            ORDER BY recorded_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(device_id)
+        .bind(recorded_at)
+        .bind(sample_id)
+        .bind(dedupe_since)
+        .bind(component)
+        .fetch_optional(self.pool.pool())
         .await?;
 
         Ok(data)
     }
 
     /// 批量插入电量数据
+    ///
+    /// 使用 UNNEST 单条语句写入整批数据，重复样本（判定同 [`Self::insert`]）跳过；
+    /// 若有记录违反 CHECK 约束，改为逐条写入（每条使用独立保存点），
+    /// 被拒绝的记录以下标返回，其余记录照常写入。
+    /// 未提供记录时间的记录按 [`BatteryReportRequest::batch_recorded_at`] 分配各不相同的时间
     pub async fn batch_insert(
         &self,
         device_id: Uuid,
         requests: &[BatteryReportRequest],
-    ) -> Result<usize, AppError> {
+        dedupe_since: DateTime<Utc>,
+    ) -> Result<BatchInsertResult, AppError> {
         if requests.is_empty() {
-            return Ok(0);
+            return Ok(BatchInsertResult::default());
         }
 
-        // 限制单次批量插入数量
-        let max_batch_size = 1000;
-        if requests.len() > max_batch_size {
-            return Err(AppError::ValidationError(format!(
-                "批量插入数量不能超过 {}",
-                max_batch_size
-            )));
+        let recorded = BatteryReportRequest::batch_recorded_at(requests, Utc::now());
+        let mut ids = Vec::with_capacity(requests.len());
+        let mut levels = Vec::with_capacity(requests.len());
+        let mut charging = Vec::with_capacity(requests.len());
+        let mut modes = Vec::with_capacity(requests.len());
+        let mut temperatures = Vec::with_capacity(requests.len());
+        let mut voltages = Vec::with_capacity(requests.len());
+        let mut sample_ids = Vec::with_capacity(requests.len());
+        let mut metrics = Vec::with_capacity(requests.len());
+        let mut components = Vec::with_capacity(requests.len());
+        let mut clock_flags = Vec::with_capacity(requests.len());
+        let mut device_recorded = Vec::with_capacity(requests.len());
+        let mut batch_ids = Vec::with_capacity(requests.len());
+        let mut batch_recorded = Vec::with_capacity(requests.len());
+        let mut seen = HashSet::with_capacity(requests.len());
+        let mut rejected = Vec::new();
+
+        for (index, request) in requests.iter().enumerate() {
+            ids.push(Uuid::new_v4());
+            // 预先拒绝违反表约束的记录，不让整批写入失败
+            if let Some(error) = request.storage_violation() {
+                rejected.push(RejectedBatteryRecord { index, error });
+                continue;
+            }
+            // 同一批中相同组件、相同记录时间的记录只写入第一条（未建唯一索引时也不会重复）
+            if !seen.insert((request.stored_component(), recorded[index])) {
+                continue;
+            }
+            batch_ids.push(ids[index]);
+            batch_recorded.push(recorded[index]);
+            levels.push(request.battery_level);
+            charging.push(request.is_charging);
+            modes.push(request.power_saving_mode.clone());
+            temperatures.push(request.temperature);
+            voltages.push(request.voltage);
+            sample_ids.push(request.sample_id.clone());
+            metrics.push(request.stored_metrics());
+            components.push(request.stored_component());
+            clock_flags.push(request.clock.map(|c| c.flag));
+            device_recorded.push(request.clock.map(|c| c.device_recorded_at));
+        }
+
+        if batch_ids.is_empty() {
+            return Ok(BatchInsertResult {
+                rejected,
+                ..Default::default()
+            });
+        }
+
+        let result = sqlx::query_as::<_, BatteryData>(
+            r#"
+            INSERT INTO battery_data (id, device_id, component, battery_level, is_charging, power_saving_mode, temperature, voltage, recorded_at, sample_id, metrics, clock_flag, device_recorded_at, created_at)
+            SELECT t.id, $1, t.component, t.battery_level, t.is_charging, t.power_saving_mode, t.temperature, t.voltage, t.recorded_at, t.sample_id, t.metrics, t.clock_flag, t.device_recorded_at, NOW()
+            FROM UNNEST($2::uuid[], $3::int4[], $4::bool[], $5::power_saving_mode[], $6::float8[], $7::float8[], $8::timestamptz[], $9::text[], $11::jsonb[], $12::text[], $13::clock_flag[], $14::timestamptz[])
+                AS t(id, battery_level, is_charging, power_saving_mode, temperature, voltage, recorded_at, sample_id, metrics, component, clock_flag, device_recorded_at)
+            WHERE NOT EXISTS (
+                SELECT 1 FROM battery_data d
+                WHERE d.device_id = $1 AND d.component = t.component AND d.recorded_at = t.recorded_at
+            )
+            AND (t.sample_id IS NULL OR NOT EXISTS (
+                SELECT 1 FROM battery_data d
+                WHERE d.device_id = $1 AND d.component = t.component AND d.sample_id = t.sample_id AND d.recorded_at >= $10
+            ))
+            ON CONFLICT DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(device_id)
+        .bind(&batch_ids)
+        .bind(&levels)
+        .bind(&charging)
+        .bind(&modes)
+        .bind(&temperatures)
+        .bind(&voltages)
+        .bind(&batch_recorded)
+        .bind(&sample_ids)
+        .bind(dedupe_since)
+        .bind(&metrics)
+        .bind(&components)
+        .bind(&clock_flags)
+        .bind(&device_recorded)
+        .fetch_all(self.pool.pool())
+        .await;
+
+        match result {
+            Ok(inserted) => {
+                let inserted_ids: HashSet<Uuid> = inserted.iter().map(|d| d.id).collect();
+                let rejected_indexes: HashSet<usize> = rejected.iter().map(|r| r.index).collect();
+                let duplicates = ids
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, id)| {
+                        !inserted_ids.contains(id) && !rejected_indexes.contains(index)
+                    })
+                    .map(|(index, _)| index)
+                    .collect();
+
+                Ok(BatchInsertResult {
+                    inserted,
+                    rejected,
+                    duplicates,
+                })
+            }
+            Err(e) if is_check_violation(&e) => {
+                // 预校验未覆盖的约束（如后续迁移新增）才会走到这里
+                tracing::warn!(device_id = %device_id, error = %e, "批量写入违反未预校验的约束，改为逐条写入");
+                self.insert_each(device_id, requests, &ids, &recorded, dedupe_since, rejected)
+                    .await
+            }
+            Err(e) => Err(e.into()),
         }
+    }
 
-        // 使用事务进行批量插入
+    /// 逐条写入（保存点隔离单条失败）
+    ///
+    /// 仅在整批写入违反预校验未覆盖的约束时作为最后手段使用，`rejected` 为预校验已拒绝的记录
+    async fn insert_each(
+        &self,
+        device_id: Uuid,
+        requests: &[BatteryReportRequest],
+        ids: &[Uuid],
+        recorded: &[DateTime<Utc>],
+        dedupe_since: DateTime<Utc>,
+        rejected: Vec<RejectedBatteryRecord>,
+    ) -> Result<BatchInsertResult, AppError> {
+        let skipped: HashSet<usize> = rejected.iter().map(|r| r.index).collect();
         let mut tx = self.pool.pool().begin().await?;
-        let mut count = 0;
+        let mut result = BatchInsertResult {
+            rejected,
+            ..Default::default()
+        };
 
-        for request in requests {
-            let id = Uuid::new_v4();
-            let recorded_at = request.recorded_at.unwrap_or_else(Utc::now);
+        for (index, request) in requests.iter().enumerate() {
+            if skipped.contains(&index) {
+                continue;
+            }
+            let mut savepoint = (&mut tx).begin().await?;
 
-            sqlx::query(
+            let inserted = sqlx::query_as::<_, BatteryData>(
                 r#"
-                INSERT INTO battery_data (id, device_id, battery_level, is_charging, power_saving_mode, temperature, voltage, recorded_at, created_at)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+                INSERT INTO battery_data (id, device_id, component, battery_level, is_charging, power_saving_mode, temperature, voltage, recorded_at, sample_id, metrics, clock_flag, device_recorded_at, created_at)
+                SELECT $1, $2, $12, $3, $4, $5, $6, $7, $8, $9, $11, $13, $14, NOW()
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM battery_data
+                    WHERE device_id = $2 AND component = $12 AND recorded_at = $8
+                )
+                AND ($9::text IS NULL OR NOT EXISTS (
+                    SELECT 1 FROM battery_data
+                    WHERE device_id = $2 AND component = $12 AND sample_id = $9 AND recorded_at >= $10
+                ))
+                ON CONFLICT DO NOTHING
+                RETURNING *
                 "#,
             )
-            .bind(id)
+            .bind(ids[index])
             .bind(device_id)
             .bind(request.battery_level)
             .bind(request.is_charging)
             .bind(&request.power_saving_mode)
             .bind(request.temperature)
             .bind(request.voltage)
-            .bind(recorded_at)
-            .execute(&mut *tx)
-            .await?;
+            .bind(recorded[index])
+            .bind(&request.sample_id)
+            .bind(dedupe_since)
+            .bind(request.stored_metrics())
+            .bind(request.stored_component())
+            .bind(request.clock.map(|c| c.flag))
+            .bind(request.clock.map(|c| c.device_recorded_at))
+            .fetch_optional(&mut *savepoint)
+            .await;
 
-            count += 1;
+            match inserted {
+                Ok(Some(data)) => {
+                    savepoint.commit().await?;
+                    result.inserted.push(data);
+                }
+                Ok(None) => {
+                    savepoint.commit().await?;
+                    result.duplicates.push(index);
+                }
+                Err(e) if is_check_violation(&e) => {
+                    savepoint.rollback().await?;
+                    let constraint = match &e {
+                        sqlx::Error::Database(db) => {
+                            db.constraint().unwrap_or_default().to_string()
+                        }
+                        _ => String::new(),
+                    };
+                    result.rejected.push(RejectedBatteryRecord {
+                        index,
+                        error: format!("违反数据约束: {}", constraint),
+                    });
+                }
+                Err(e) => return Err(e.into()),
+            }
         }
 
         tx.commit().await?;
-        Ok(count)
+        result.rejected.sort_by_key(|r| r.index);
+        Ok(result)
     }
 
-    /// 查询时间范围内的电量数据
+    /// 查询时间范围内的电量数据（指定组件时只返回该组件的数据）
     pub async fn query_by_time_range(
         &self,
         device_id: Uuid,
@@ -116,6 +361,7 @@ impl BatteryRepository {
             r#"
             SELECT * FROM battery_data
             WHERE device_id = $1 AND recorded_at >= $2 AND recorded_at <= $3
+              AND ($6::text IS NULL OR component = $6)
             ORDER BY recorded_at DESC
             LIMIT $4 OFFSET $5
             "#,
@@ -125,67 +371,336 @@ impl BatteryRepository {
         .bind(request.end_time)
         .bind(request.limit)
         .bind(request.offset)
+        .bind(&request.component)
         .fetch_all(self.pool.pool())
         .await?;
 
         Ok(data)
     }
 
-    /// 查询最新电量数据
-    pub async fn query_latest(&self, device_id: Uuid) -> Result<Option<BatteryData>, AppError> {
+    /// 按 (recorded_at, id) 游标查询时间范围内的电量数据
+    ///
+    /// `after` 为上一批最后一条记录，`ascending` 决定排序方向（游标沿排序方向推进），
+    /// 指定组件时只返回该组件的数据
+    #[allow(clippy::too_many_arguments)]
+    pub async fn query_keyset(
+        &self,
+        device_id: Uuid,
+        component: Option<&str>,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        after: Option<&HistoryCursor>,
+        limit: i64,
+        ascending: bool,
+    ) -> Result<Vec<BatteryData>, AppError> {
+        let (op, dir) = if ascending {
+            (">", "ASC")
+        } else {
+            ("<", "DESC")
+        };
+
+        // 游标时间同时收紧时间范围，使索引扫描直接从游标位置开始
+        let (start_time, end_time) = match after {
+            Some(c) if ascending => (start_time.max(c.recorded_at), end_time),
+            Some(c) => (start_time, end_time.min(c.recorded_at)),
+            None => (start_time, end_time),
+        };
+
+        let data = sqlx::query_as::<_, BatteryData>(&format!(
+            r#"
+            SELECT * FROM battery_data
+            WHERE device_id = $1 AND recorded_at >= $2 AND recorded_at <= $3
+              AND ($4::timestamptz IS NULL OR (recorded_at, id) {op} ($4, $5))
+              AND ($7::text IS NULL OR component = $7)
+            ORDER BY recorded_at {dir}, id {dir}
+            LIMIT $6
+            "#,
+            op = op,
+            dir = dir
+        ))
+        .bind(device_id)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(after.map(|c| c.recorded_at))
+        .bind(after.map(|c| c.id))
+        .bind(limit)
+        .bind(component)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(data)
+    }
+
+    /// 查询最新电量数据（不指定组件时为所有组件中最近的一条）
+    pub async fn query_latest(
+        &self,
+        device_id: Uuid,
+        component: Option<&str>,
+    ) -> Result<Option<BatteryData>, AppError> {
         let data = sqlx::query_as::<_, BatteryData>(
             r#"
             SELECT * FROM battery_data
-            WHERE device_id = $1
+            WHERE device_id = $1 AND ($2::text IS NULL OR component = $2)
             ORDER BY recorded_at DESC
             LIMIT 1
             "#,
         )
         .bind(device_id)
+        .bind(component)
         .fetch_optional(self.pool.pool())
         .await?;
 
         Ok(data)
     }
 
-    /// 时间聚合查询（利用 TimescaleDB 的 time_bucket）
+    /// 查询各组件的最新电量数据（没有数据的组件不返回）
+    pub async fn query_latest_components(
+        &self,
+        device_id: Uuid,
+        components: &[String],
+    ) -> Result<Vec<BatteryData>, AppError> {
+        let data = sqlx::query_as::<_, BatteryData>(
+            r#"
+            SELECT d.* FROM UNNEST($2::text[]) WITH ORDINALITY AS c(component, ord)
+            CROSS JOIN LATERAL (
+                SELECT * FROM battery_data
+                WHERE device_id = $1 AND component = c.component
+                ORDER BY recorded_at DESC
+                LIMIT 1
+            ) d
+            ORDER BY c.ord
+            "#,
+        )
+        .bind(device_id)
+        .bind(components)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(data)
+    }
+
+    /// 查询时间窗口内的电量数据（按记录时间升序，指定组件时只返回该组件的数据）
+    pub async fn query_window(
+        &self,
+        device_id: Uuid,
+        component: Option<&str>,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<BatteryData>, AppError> {
+        let data = sqlx::query_as::<_, BatteryData>(
+            r#"
+            SELECT * FROM battery_data
+            WHERE device_id = $1 AND recorded_at >= $2 AND recorded_at <= $3
+              AND ($4::text IS NULL OR component = $4)
+            ORDER BY recorded_at ASC
+            "#,
+        )
+        .bind(device_id)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(component)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(data)
+    }
+
+    /// 时间聚合查询
+    ///
+    /// 间隔为整小时 / 整天 / 自然月时，完整的时间桶从连续聚合视图上卷（视图开启实时聚合，
+    /// 未物化的部分由 TimescaleDB 从原始数据补齐），首尾不完整的时间桶和其余间隔从原始数据聚合。
+    /// 平均值按样本数加权，结果与直接聚合原始数据一致；不指定组件时合并所有组件。
+    /// 时间范围由调用方按 [`AggregateInterval::validate_time_range`] 校验
     pub async fn aggregate_by_interval(
         &self,
         device_id: Uuid,
+        component: Option<&str>,
         start_time: DateTime<Utc>,
         end_time: DateTime<Utc>,
         interval: &AggregateInterval,
+        fill: GapFill,
     ) -> Result<Vec<BatteryAggregatePoint>, AppError> {
+        // 原始数据与视图统一为 (t, 求和, 最值, 样本数) 的形式，便于上卷
+        const RAW_COLUMNS: &str = r#"
+            recorded_at AS t,
+            battery_level::float8 AS level_sum,
+            battery_level AS min_level,
+            battery_level AS max_level,
+            1::int8 AS sample_count,
+            temperature AS temperature_sum,
+            (temperature IS NOT NULL)::int::int8 AS temperature_samples,
+            voltage AS voltage_sum,
+            (voltage IS NOT NULL)::int::int8 AS voltage_samples
+        "#;
+
+        let full_range = interval.continuous_aggregate().and_then(|(view, bucket)| {
+            bucket
+                .full_bucket_range(start_time, end_time)
+                .map(|range| (view, range))
+        });
+        let (source, (full_from, full_to)) = match full_range {
+            Some((view, range)) => (
+                format!(
+                    r#"
+                    SELECT
+                        bucket AS t,
+                        (avg_level * sample_count)::float8 AS level_sum,
+                        min_level,
+                        max_level,
+                        sample_count,
+                        avg_temperature * temperature_samples AS temperature_sum,
+                        temperature_samples,
+                        avg_voltage * voltage_samples AS voltage_sum,
+                        voltage_samples
+                    FROM {view}
+                    WHERE device_id = $1 AND bucket >= $4 AND bucket < $5
+                      AND ($6::text IS NULL OR component = $6)
+                    UNION ALL
+                    SELECT {columns} FROM battery_data
+                    WHERE device_id = $1 AND recorded_at >= $2 AND recorded_at < $4
+                      AND ($6::text IS NULL OR component = $6)
+                    UNION ALL
+                    SELECT {columns} FROM battery_data
+                    WHERE device_id = $1 AND recorded_at >= $5 AND recorded_at <= $3
+                      AND ($6::text IS NULL OR component = $6)
+                    "#,
+                    view = view,
+                    columns = RAW_COLUMNS
+                ),
+                range,
+            ),
+            None => (
+                format!(
+                    r#"
+                    SELECT {columns} FROM battery_data
+                    WHERE device_id = $1 AND recorded_at >= $2 AND recorded_at <= $3
+                      AND ($6::text IS NULL OR component = $6)
+                    "#,
+                    columns = RAW_COLUMNS
+                ),
+                (start_time, start_time),
+            ),
+        };
+
         let interval_str = interval.to_timescaledb_interval();
+        let (bucket, fill_fn) = match fill {
+            GapFill::None => (format!("time_bucket('{}', t)", interval_str), ""),
+            // gapfill 的结束时间不包含在内，需要覆盖恰好位于 end_time 的样本
+            _ => (
+                format!(
+                    "time_bucket_gapfill('{}', t, $2, $3 + INTERVAL '1 microsecond')",
+                    interval_str
+                ),
+                match fill {
+                    GapFill::Locf => "locf",
+                    GapFill::Interpolate => "interpolate",
+                    _ => "",
+                },
+            ),
+        };
 
         let data = sqlx::query_as::<_, BatteryAggregatePoint>(&format!(
             r#"
-                SELECT 
-                    time_bucket('{}', recorded_at) AS bucket,
-                    AVG(battery_level)::float8 AS avg_level,
-                    MIN(battery_level) AS min_level,
-                    MAX(battery_level) AS max_level,
-                    COUNT(*) AS count
-                FROM battery_data
-                WHERE device_id = $1 AND recorded_at >= $2 AND recorded_at <= $3
-                GROUP BY bucket
-                ORDER BY bucket DESC
-                "#,
-            interval_str
+            SELECT
+                {bucket} AS bucket,
+                {fill}((SUM(level_sum) / NULLIF(SUM(sample_count), 0))::float8) AS avg_level,
+                {fill}(MIN(min_level)) AS min_level,
+                {fill}(MAX(max_level)) AS max_level,
+                COALESCE(SUM(sample_count), 0)::int8 AS count,
+                {fill}((SUM(temperature_sum) / NULLIF(SUM(temperature_samples), 0))::float8) AS avg_temperature,
+                {fill}((SUM(voltage_sum) / NULLIF(SUM(voltage_samples), 0))::float8) AS avg_voltage
+            FROM ({source}) src
+            GROUP BY 1
+            ORDER BY 1 DESC
+            "#,
+            bucket = bucket,
+            fill = fill_fn,
+            source = source
         ))
         .bind(device_id)
         .bind(start_time)
         .bind(end_time)
+        .bind(full_from)
+        .bind(full_to)
+        .bind(component)
         .fetch_all(self.pool.pool())
         .await?;
 
         Ok(data)
     }
 
+    /// 按时间桶聚合自定义指标（直接扫描原始数据，时间桶与 [`Self::aggregate_by_interval`] 一致）
+    ///
+    /// 数值和布尔值（按 0 / 1）参与聚合，返回 (时间桶, 指标键, 聚合值)，没有数据的组合不返回
+    pub async fn aggregate_metrics(
+        &self,
+        device_id: Uuid,
+        component: Option<&str>,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        interval: &AggregateInterval,
+        keys: &[String],
+    ) -> Result<Vec<(DateTime<Utc>, String, MetricAggregate)>, AppError> {
+        let rows: Vec<(DateTime<Utc>, String, f64, f64, f64, i64)> = sqlx::query_as(&format!(
+            r#"
+            SELECT
+                time_bucket('{interval}', recorded_at) AS bucket,
+                key,
+                AVG(value)::float8,
+                MIN(value)::float8,
+                MAX(value)::float8,
+                COUNT(*)::int8
+            FROM (
+                SELECT
+                    d.recorded_at,
+                    k.key,
+                    CASE jsonb_typeof(d.metrics -> k.key)
+                        WHEN 'number' THEN (d.metrics ->> k.key)::float8
+                        WHEN 'boolean' THEN (d.metrics ->> k.key)::boolean::int::float8
+                    END AS value
+                FROM battery_data d
+                CROSS JOIN UNNEST($4::text[]) AS k(key)
+                WHERE d.device_id = $1 AND d.recorded_at >= $2 AND d.recorded_at <= $3
+                  AND d.metrics IS NOT NULL
+                  AND ($5::text IS NULL OR d.component = $5)
+            ) src
+            WHERE value IS NOT NULL
+            GROUP BY 1, 2
+            "#,
+            interval = interval.to_timescaledb_interval()
+        ))
+        .bind(device_id)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(keys)
+        .bind(component)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(bucket, key, avg, min, max, count)| {
+                (
+                    bucket,
+                    key,
+                    MetricAggregate {
+                        avg,
+                        min,
+                        max,
+                        count,
+                    },
+                )
+            })
+            .collect())
+    }
+
     /// 获取电量统计
+    ///
+    /// 充电时长按充电会话与统计区间的重叠部分计算；不指定组件时合并所有组件
     pub async fn get_stats(
         &self,
         device_id: Uuid,
+        component: Option<&str>,
         start_time: DateTime<Utc>,
         end_time: DateTime<Utc>,
     ) -> Result<BatteryStatsResponse, AppError> {
@@ -199,21 +714,146 @@ impl BatteryRepository {
                 COALESCE(MIN(battery_level), 0) AS min_battery_level,
                 COALESCE(MAX(battery_level), 100) AS max_battery_level,
                 COUNT(*) AS total_records,
-                COALESCE(SUM(CASE WHEN is_charging THEN 1 ELSE 0 END), 0) AS charging_duration_minutes,
+                (
+                    SELECT COALESCE(SUM(EXTRACT(EPOCH FROM
+                        LEAST(COALESCE(ended_at, last_sample_at), $3) - GREATEST(started_at, $2)
+                    )) / 60, 0)::bigint
+                    FROM charging_sessions
+                    WHERE device_id = $1
+                      AND ($4::text IS NULL OR component = $4)
+                      AND started_at < $3
+                      AND COALESCE(ended_at, last_sample_at) > $2
+                ) AS charging_duration_minutes,
                 COALESCE(SUM(CASE WHEN battery_level < 20 THEN 1 ELSE 0 END), 0) AS low_battery_count
             FROM battery_data
             WHERE device_id = $1 AND recorded_at >= $2 AND recorded_at <= $3
+              AND ($4::text IS NULL OR component = $4)
             "#,
         )
         .bind(device_id)
         .bind(start_time)
         .bind(end_time)
+        .bind(component)
         .fetch_one(self.pool.pool())
         .await?;
 
         Ok(stats)
     }
 
+    /// 统计放电总量（百分点，相邻两点均未充电时的电量下降之和）
+    pub async fn discharged_percent(
+        &self,
+        device_id: Uuid,
+        component: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<f64, AppError> {
+        let (total,): (f64,) = sqlx::query_as(
+            r#"
+            SELECT COALESCE(SUM(GREATEST(prev_level - battery_level, 0)), 0)::float8
+            FROM (
+                SELECT
+                    battery_level,
+                    is_charging,
+                    LAG(battery_level) OVER w AS prev_level,
+                    LAG(is_charging) OVER w AS prev_charging
+                FROM battery_data
+                WHERE device_id = $1 AND component = $4 AND recorded_at >= $2 AND recorded_at <= $3
+                WINDOW w AS (ORDER BY recorded_at)
+            ) t
+            WHERE NOT is_charging AND NOT prev_charging
+            "#,
+        )
+        .bind(device_id)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(component)
+        .fetch_one(self.pool.pool())
+        .await?;
+
+        Ok(total)
+    }
+
+    /// 按周、按省电模式统计放电速率
+    ///
+    /// 只统计相邻两点均未充电、省电模式相同且间隔不超过 1 小时的区间
+    pub async fn weekly_discharge_rates(
+        &self,
+        device_id: Uuid,
+        component: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<ModeDischargeRate>, AppError> {
+        let data = sqlx::query_as::<_, ModeDischargeRate>(
+            r#"
+            SELECT
+                date_trunc('week', recorded_at) AS bucket,
+                power_saving_mode,
+                (SUM(prev_level - battery_level) * 3600 / SUM(EXTRACT(EPOCH FROM recorded_at - prev_at)))::float8 AS value,
+                (SUM(EXTRACT(EPOCH FROM recorded_at - prev_at)) / 3600)::float8 AS hours
+            FROM (
+                SELECT
+                    recorded_at,
+                    battery_level,
+                    is_charging,
+                    power_saving_mode,
+                    LAG(recorded_at) OVER w AS prev_at,
+                    LAG(battery_level) OVER w AS prev_level,
+                    LAG(is_charging) OVER w AS prev_charging,
+                    LAG(power_saving_mode) OVER w AS prev_mode
+                FROM battery_data
+                WHERE device_id = $1 AND component = $4 AND recorded_at >= $2 AND recorded_at <= $3
+                WINDOW w AS (ORDER BY recorded_at)
+            ) t
+            WHERE NOT is_charging
+              AND NOT prev_charging
+              AND power_saving_mode = prev_mode
+              AND recorded_at > prev_at
+              AND recorded_at - prev_at <= INTERVAL '1 hour'
+            GROUP BY bucket, power_saving_mode
+            ORDER BY bucket
+            "#,
+        )
+        .bind(device_id)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(component)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(data)
+    }
+
+    /// 按周统计参考电量（45%-55%，未充电）下的平均电压
+    pub async fn weekly_reference_voltage(
+        &self,
+        device_id: Uuid,
+        component: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<HealthTrendPoint>, AppError> {
+        let data = sqlx::query_as::<_, HealthTrendPoint>(
+            r#"
+            SELECT date_trunc('week', recorded_at) AS bucket, AVG(voltage)::float8 AS value
+            FROM battery_data
+            WHERE device_id = $1 AND component = $4 AND recorded_at >= $2 AND recorded_at <= $3
+              AND voltage IS NOT NULL
+              AND NOT is_charging
+              AND battery_level BETWEEN 45 AND 55
+            GROUP BY bucket
+            ORDER BY bucket
+            "#,
+        )
+        .bind(device_id)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(component)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(data)
+    }
+
     /// 删除过期数据（用于数据保留策略）
     pub async fn delete_expired(&self, retention_days: i32) -> Result<u64, AppError> {
         let result = sqlx::query(
@@ -229,3 +869,8 @@ impl BatteryRepository {
         Ok(result.rows_affected())
     }
 }
+
+/// 是否为 CHECK 约束冲突（SQLSTATE 23514）
+fn is_check_violation(error: &sqlx::Error) -> bool {
+    matches!(error, sqlx::Error::Database(db) if db.code().as_deref() == Some("23514"))
+}
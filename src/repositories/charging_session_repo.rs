@@ -0,0 +1,181 @@
+//! 充电会话仓库
+
+use crate::db::PostgresPool;
+use crate::errors::AppError;
+use crate::models::{ChargingSession, ChargingSessionListQuery, HealthTrendPoint};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// 充电会话仓库
+#[derive(Clone)]
+pub struct ChargingSessionRepository {
+    pool: PostgresPool,
+}
+
+impl ChargingSessionRepository {
+    pub fn new(pool: PostgresPool) -> Self {
+        Self { pool }
+    }
+
+    /// 获取设备（组件）最近一次充电会话
+    pub async fn find_latest(
+        &self,
+        device_id: Uuid,
+        component: &str,
+    ) -> Result<Option<ChargingSession>, AppError> {
+        let session = sqlx::query_as::<_, ChargingSession>(
+            r#"
+            SELECT * FROM charging_sessions
+            WHERE device_id = $1 AND component = $2
+            ORDER BY started_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(device_id)
+        .bind(component)
+        .fetch_optional(self.pool.pool())
+        .await?;
+
+        Ok(session)
+    }
+
+    /// 保存充电会话（不存在则创建）
+    pub async fn save(&self, session: &ChargingSession) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO charging_sessions (
+                id, device_id, started_at, ended_at, last_sample_at, start_level, end_level,
+                peak_temperature, sample_count, duration_minutes, avg_charge_rate, created_at, updated_at,
+                component
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, NOW(), $13)
+            ON CONFLICT (id) DO UPDATE SET
+                ended_at = EXCLUDED.ended_at,
+                last_sample_at = EXCLUDED.last_sample_at,
+                end_level = EXCLUDED.end_level,
+                peak_temperature = EXCLUDED.peak_temperature,
+                sample_count = EXCLUDED.sample_count,
+                duration_minutes = EXCLUDED.duration_minutes,
+                avg_charge_rate = EXCLUDED.avg_charge_rate,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(session.id)
+        .bind(session.device_id)
+        .bind(session.started_at)
+        .bind(session.ended_at)
+        .bind(session.last_sample_at)
+        .bind(session.start_level)
+        .bind(session.end_level)
+        .bind(session.peak_temperature)
+        .bind(session.sample_count)
+        .bind(session.duration_minutes)
+        .bind(session.avg_charge_rate)
+        .bind(session.created_at)
+        .bind(&session.component)
+        .execute(self.pool.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// 分页查询设备的充电会话（按开始时间倒序）
+    pub async fn list_by_device(
+        &self,
+        device_id: Uuid,
+        query: &ChargingSessionListQuery,
+    ) -> Result<(Vec<ChargingSession>, i64), AppError> {
+        let offset = (query.page - 1) * query.page_size;
+
+        let (total,): (i64,) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*) FROM charging_sessions
+            WHERE device_id = $1
+              AND ($2::timestamptz IS NULL OR started_at >= $2)
+              AND ($3::timestamptz IS NULL OR started_at <= $3)
+              AND ($4::text IS NULL OR component = $4)
+            "#,
+        )
+        .bind(device_id)
+        .bind(query.start_time)
+        .bind(query.end_time)
+        .bind(&query.component)
+        .fetch_one(self.pool.pool())
+        .await?;
+
+        let sessions = sqlx::query_as::<_, ChargingSession>(
+            r#"
+            SELECT * FROM charging_sessions
+            WHERE device_id = $1
+              AND ($2::timestamptz IS NULL OR started_at >= $2)
+              AND ($3::timestamptz IS NULL OR started_at <= $3)
+              AND ($6::text IS NULL OR component = $6)
+            ORDER BY started_at DESC
+            LIMIT $4 OFFSET $5
+            "#,
+        )
+        .bind(device_id)
+        .bind(query.start_time)
+        .bind(query.end_time)
+        .bind(query.page_size)
+        .bind(offset)
+        .bind(&query.component)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok((sessions, total))
+    }
+
+    /// 统计时间段内开始的会话数和充入电量（百分点）
+    pub async fn charge_summary(
+        &self,
+        device_id: Uuid,
+        component: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<(i64, f64), AppError> {
+        let summary = sqlx::query_as::<_, (i64, f64)>(
+            r#"
+            SELECT COUNT(*), COALESCE(SUM(GREATEST(end_level - start_level, 0)), 0)::float8
+            FROM charging_sessions
+            WHERE device_id = $1 AND component = $4 AND started_at >= $2 AND started_at <= $3
+            "#,
+        )
+        .bind(device_id)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(component)
+        .fetch_one(self.pool.pool())
+        .await?;
+
+        Ok(summary)
+    }
+
+    /// 按周统计已结束会话达到的最高电量
+    pub async fn weekly_full_charge_level(
+        &self,
+        device_id: Uuid,
+        component: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<HealthTrendPoint>, AppError> {
+        let data = sqlx::query_as::<_, HealthTrendPoint>(
+            r#"
+            SELECT date_trunc('week', started_at) AS bucket, MAX(end_level)::float8 AS value
+            FROM charging_sessions
+            WHERE device_id = $1 AND component = $4 AND started_at >= $2 AND started_at <= $3
+              AND ended_at IS NOT NULL
+            GROUP BY bucket
+            ORDER BY bucket
+            "#,
+        )
+        .bind(device_id)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(component)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(data)
+    }
+}
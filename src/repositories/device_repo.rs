@@ -6,7 +6,7 @@ use crate::models::{
     CreateDeviceRequest, Device, DeviceConfig, DeviceListQuery, DeviceStatus,
     UpdateDeviceConfigRequest, UpdateDeviceRequest,
 };
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
 use uuid::Uuid;
 
 /// 设备数据仓库
@@ -33,8 +33,8 @@ impl DeviceRepository {
 
         let device = sqlx::query_as::<_, Device>(
             r#"
-            INSERT INTO devices (id, owner_id, name, device_type, status, api_key_hash, api_key_prefix, created_at, updated_at, metadata)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            INSERT INTO devices (id, owner_id, name, device_type, status, api_key_hash, api_key_prefix, created_at, updated_at, metadata, components, gateway_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
             RETURNING *
             "#,
         )
@@ -48,6 +48,8 @@ impl DeviceRepository {
         .bind(now)
         .bind(now)
         .bind(&request.metadata)
+        .bind(&request.components)
+        .bind(request.gateway_id)
         .fetch_one(self.pool.pool())
         .await?;
 
@@ -63,8 +65,8 @@ impl DeviceRepository {
 
         sqlx::query(
             r#"
-            INSERT INTO device_configs (device_id, low_battery_threshold, critical_battery_threshold, report_interval_seconds, high_temperature_threshold, updated_at)
-            VALUES ($1, $2, $3, $4, $5, NOW())
+            INSERT INTO device_configs (device_id, low_battery_threshold, critical_battery_threshold, report_interval_seconds, high_temperature_threshold, rapid_drain_threshold, rapid_drain_window_minutes, battery_hysteresis, temperature_hysteresis, clock_correction, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, NOW())
             "#,
         )
         .bind(device_id)
@@ -72,6 +74,11 @@ impl DeviceRepository {
         .bind(config.critical_battery_threshold)
         .bind(config.report_interval_seconds)
         .bind(config.high_temperature_threshold)
+        .bind(config.rapid_drain_threshold)
+        .bind(config.rapid_drain_window_minutes)
+        .bind(config.battery_hysteresis)
+        .bind(config.temperature_hysteresis)
+        .bind(config.clock_correction)
         .execute(self.pool.pool())
         .await?;
 
@@ -110,6 +117,8 @@ impl DeviceRepository {
             SET name = COALESCE($2, name),
                 status = COALESCE($3, status),
                 metadata = COALESCE($4, metadata),
+                components = COALESCE($5, components),
+                gateway_id = CASE WHEN $6 THEN $7 ELSE gateway_id END,
                 updated_at = NOW()
             WHERE id = $1
             RETURNING *
@@ -119,20 +128,155 @@ impl DeviceRepository {
         .bind(&request.name)
         .bind(&request.status)
         .bind(&request.metadata)
+        .bind(&request.components)
+        .bind(request.gateway_id.is_some())
+        .bind(request.gateway_id.flatten())
         .fetch_one(self.pool.pool())
         .await?;
 
         Ok(device)
     }
 
+    /// 查找网关的指定子设备（不属于该网关的设备不返回）
+    pub async fn find_children(
+        &self,
+        gateway_id: Uuid,
+        device_ids: &[Uuid],
+    ) -> Result<Vec<Device>, AppError> {
+        let devices = sqlx::query_as::<_, Device>(
+            "SELECT * FROM devices WHERE gateway_id = $1 AND id = ANY($2)",
+        )
+        .bind(gateway_id)
+        .bind(device_ids)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(devices)
+    }
+
+    /// 检查设备是否有子设备
+    pub async fn has_children(&self, id: Uuid) -> Result<bool, AppError> {
+        let result: Option<(i32,)> =
+            sqlx::query_as("SELECT 1 FROM devices WHERE gateway_id = $1 LIMIT 1")
+                .bind(id)
+                .fetch_optional(self.pool.pool())
+                .await?;
+
+        Ok(result.is_some())
+    }
+
     /// 更新设备最后在线时间
-    pub async fn update_last_seen(&self, id: Uuid) -> Result<(), AppError> {
-        sqlx::query("UPDATE devices SET last_seen_at = NOW(), status = 'online' WHERE id = $1")
-            .bind(id)
-            .execute(self.pool.pool())
-            .await?;
+    ///
+    /// 返回设备是否由离线恢复为在线
+    pub async fn update_last_seen(&self, id: Uuid) -> Result<bool, AppError> {
+        let previous: Option<(DeviceStatus,)> = sqlx::query_as(
+            r#"
+            UPDATE devices d SET last_seen_at = NOW(), status = 'online'
+            FROM (SELECT status FROM devices WHERE id = $1 FOR UPDATE) prev
+            WHERE d.id = $1
+            RETURNING prev.status
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.pool.pool())
+        .await?;
 
-        Ok(())
+        Ok(matches!(previous, Some((DeviceStatus::Offline,))))
+    }
+
+    /// 记录一次时钟偏差测量，返回设备当前的时钟偏差
+    ///
+    /// 网络延迟和重试只会使测量值偏大，因此取窗口内的最小值：测量值更小，
+    /// 或已超过 `window` 没有更新（设备时钟被调整或重置）时才替换
+    pub async fn record_clock_offset(
+        &self,
+        id: Uuid,
+        offset_seconds: i64,
+        window: Duration,
+    ) -> Result<Option<i64>, AppError> {
+        let offset: Option<(Option<i64>,)> = sqlx::query_as(
+            r#"
+            WITH updated AS (
+                UPDATE devices
+                SET clock_offset_seconds = $2, clock_offset_measured_at = NOW()
+                WHERE id = $1
+                  AND (clock_offset_seconds IS NULL
+                       OR $2 < clock_offset_seconds
+                       OR clock_offset_measured_at < NOW() - INTERVAL '1 second' * $3)
+                RETURNING clock_offset_seconds
+            )
+            SELECT COALESCE(
+                (SELECT clock_offset_seconds FROM updated),
+                (SELECT clock_offset_seconds FROM devices WHERE id = $1)
+            )
+            "#,
+        )
+        .bind(id)
+        .bind(offset_seconds)
+        .bind(window.num_seconds())
+        .fetch_optional(self.pool.pool())
+        .await?;
+
+        Ok(offset.and_then(|(offset,)| offset))
+    }
+
+    /// 查询超时未上报的在线设备
+    ///
+    /// 截止时间与 [`offline_cutoff`](crate::models::offline_cutoff) 的公式一致
+    /// （上报间隔 × 宽限倍数，按数据库时间计算），未配置的设备使用默认上报间隔。
+    /// 返回 (设备 ID, 离线截止时间)
+    pub async fn find_stale_online(
+        &self,
+        grace_multiplier: f64,
+    ) -> Result<Vec<(Uuid, DateTime<Utc>)>, AppError> {
+        let rows = sqlx::query_as(
+            r#"
+            SELECT d.id, cutoff.at
+            FROM devices d
+            LEFT JOIN device_configs c ON c.device_id = d.id
+            CROSS JOIN LATERAL (
+                SELECT NOW() - make_interval(secs => round(
+                    GREATEST(COALESCE(c.report_interval_seconds, $1), 1) * GREATEST($2, 1.0) * 1000
+                ) / 1000) AS at
+            ) cutoff
+            WHERE d.status = 'online'
+              AND d.last_seen_at IS NOT NULL
+              AND d.last_seen_at < cutoff.at
+            "#,
+        )
+        .bind(DeviceConfig::default().report_interval_seconds)
+        .bind(grace_multiplier)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// 将超时未上报的在线设备标记为离线
+    ///
+    /// `stale` 为 (设备 ID, 离线截止时间)；仍在线且最后上报早于截止时间的设备才会被标记，
+    /// 多实例并发检测时每台设备只会被标记一次。返回本次被标记的设备
+    pub async fn mark_offline(
+        &self,
+        stale: &[(Uuid, DateTime<Utc>)],
+    ) -> Result<Vec<Device>, AppError> {
+        let (ids, cutoffs): (Vec<Uuid>, Vec<DateTime<Utc>>) = stale.iter().cloned().unzip();
+        let devices = sqlx::query_as::<_, Device>(
+            r#"
+            UPDATE devices d SET status = 'offline', updated_at = NOW()
+            FROM UNNEST($1::uuid[], $2::timestamptz[]) AS s(id, cutoff)
+            WHERE d.id = s.id
+              AND d.status = 'online'
+              AND d.last_seen_at < s.cutoff
+            RETURNING d.*
+            "#,
+        )
+        .bind(&ids)
+        .bind(&cutoffs)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(devices)
     }
 
     /// 轮换 API Key
@@ -179,6 +323,10 @@ impl DeviceRepository {
             conditions.push(format!("device_type = '{}'", device_type));
         }
 
+        if let Some(gateway_id) = query.gateway_id {
+            conditions.push(format!("gateway_id = '{}'", gateway_id));
+        }
+
         // 按所有者筛选
         if let Some(owner_id) = query.owner_id {
             if query.include_shared {
@@ -239,6 +387,11 @@ impl DeviceRepository {
                 critical_battery_threshold = COALESCE($3, critical_battery_threshold),
                 report_interval_seconds = COALESCE($4, report_interval_seconds),
                 high_temperature_threshold = COALESCE($5, high_temperature_threshold),
+                rapid_drain_threshold = COALESCE($6, rapid_drain_threshold),
+                rapid_drain_window_minutes = COALESCE($7, rapid_drain_window_minutes),
+                battery_hysteresis = COALESCE($8, battery_hysteresis),
+                temperature_hysteresis = COALESCE($9, temperature_hysteresis),
+                clock_correction = COALESCE($10, clock_correction),
                 updated_at = NOW()
             WHERE device_id = $1
             RETURNING *
@@ -249,6 +402,11 @@ impl DeviceRepository {
         .bind(request.critical_battery_threshold)
         .bind(request.report_interval_seconds)
         .bind(request.high_temperature_threshold)
+        .bind(request.rapid_drain_threshold)
+        .bind(request.rapid_drain_window_minutes)
+        .bind(request.battery_hysteresis)
+        .bind(request.temperature_hysteresis)
+        .bind(request.clock_correction)
         .fetch_one(self.pool.pool())
         .await?;
 
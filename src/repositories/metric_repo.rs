@@ -0,0 +1,134 @@
+//! 自定义指标定义仓库
+
+use crate::db::PostgresPool;
+use crate::errors::AppError;
+use crate::models::{CreateMetricDefinitionRequest, MetricDefinition};
+use uuid::Uuid;
+
+/// 自定义指标定义仓库
+#[derive(Clone)]
+pub struct MetricRepository {
+    pool: PostgresPool,
+}
+
+impl MetricRepository {
+    pub fn new(pool: PostgresPool) -> Self {
+        Self { pool }
+    }
+
+    /// 查询指标定义，指定设备类型时只返回该类型的定义
+    pub async fn list(&self, device_type: Option<&str>) -> Result<Vec<MetricDefinition>, AppError> {
+        let definitions = sqlx::query_as::<_, MetricDefinition>(
+            r#"
+            SELECT * FROM metric_definitions
+            WHERE $1::text IS NULL OR device_type = $1
+            ORDER BY device_type, key
+            "#,
+        )
+        .bind(device_type)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(definitions)
+    }
+
+    /// 根据 ID 获取指标定义
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<MetricDefinition>, AppError> {
+        let definition =
+            sqlx::query_as::<_, MetricDefinition>("SELECT * FROM metric_definitions WHERE id = $1")
+                .bind(id)
+                .fetch_optional(self.pool.pool())
+                .await?;
+
+        Ok(definition)
+    }
+
+    /// 设备类型下是否已存在该指标键
+    pub async fn exists(&self, device_type: &str, key: &str) -> Result<bool, AppError> {
+        let result: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM metric_definitions WHERE device_type = $1 AND key = $2",
+        )
+        .bind(device_type)
+        .bind(key)
+        .fetch_one(self.pool.pool())
+        .await?;
+
+        Ok(result.0 > 0)
+    }
+
+    /// 创建指标定义
+    pub async fn create(
+        &self,
+        request: &CreateMetricDefinitionRequest,
+    ) -> Result<MetricDefinition, AppError> {
+        let definition = sqlx::query_as::<_, MetricDefinition>(
+            r#"
+            INSERT INTO metric_definitions (
+                id, device_type, key, value_type, unit, description, min_value, max_value, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW(), NOW())
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(&request.device_type)
+        .bind(&request.key)
+        .bind(request.value_type)
+        .bind(&request.unit)
+        .bind(&request.description)
+        .bind(request.min_value)
+        .bind(request.max_value)
+        .fetch_one(self.pool.pool())
+        .await?;
+
+        Ok(definition)
+    }
+
+    /// 保存指标定义
+    ///
+    /// 由服务层合并部分更新后写入可修改的字段
+    pub async fn update(
+        &self,
+        definition: &MetricDefinition,
+    ) -> Result<MetricDefinition, AppError> {
+        let definition = sqlx::query_as::<_, MetricDefinition>(
+            r#"
+            UPDATE metric_definitions SET
+                unit = $2,
+                description = $3,
+                min_value = $4,
+                max_value = $5,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(definition.id)
+        .bind(&definition.unit)
+        .bind(&definition.description)
+        .bind(definition.min_value)
+        .bind(definition.max_value)
+        .fetch_one(self.pool.pool())
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => AppError::NotFound("指标定义不存在".to_string()),
+            _ => e.into(),
+        })?;
+
+        Ok(definition)
+    }
+
+    /// 删除指标定义（已上报的数据保留）
+    pub async fn delete(&self, id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query("DELETE FROM metric_definitions WHERE id = $1")
+            .bind(id)
+            .execute(self.pool.pool())
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("指标定义不存在".to_string()));
+        }
+
+        Ok(())
+    }
+}
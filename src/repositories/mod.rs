@@ -3,15 +3,19 @@
 mod alert_repo;
 mod audit_repo;
 mod battery_repo;
+mod charging_session_repo;
 mod device_repo;
 mod device_token_repo;
+mod metric_repo;
 mod notification_repo;
 mod user_repo;
 
 pub use alert_repo::AlertRepository;
 pub use audit_repo::AuditRepository;
-pub use battery_repo::BatteryRepository;
+pub use battery_repo::{BatchInsertResult, BatteryRepository};
+pub use charging_session_repo::ChargingSessionRepository;
 pub use device_repo::DeviceRepository;
 pub use device_token_repo::{CreateTokenParams, DeviceAccessTokenRepository};
+pub use metric_repo::MetricRepository;
 pub use notification_repo::NotificationRepository;
 pub use user_repo::UserRepository;
@@ -3,10 +3,10 @@
 use crate::db::PostgresPool;
 use crate::errors::AppError;
 use crate::models::{
-    NotificationChannel, NotificationHistory, SubscribeWebPushRequest,
+    NotificationChannel, NotificationHistory, NotificationOutboxQuery, SubscribeWebPushRequest,
     UpdateNotificationPreferenceRequest, UserNotificationPreference, WebPushSubscription,
 };
-use chrono::{NaiveTime, Utc};
+use chrono::{DateTime, NaiveTime, Utc};
 use uuid::Uuid;
 
 /// 通知偏好数据仓库
@@ -169,7 +169,7 @@ impl NotificationRepository {
         sqlx::query(
             r#"
             UPDATE notification_history
-            SET status = $2, error_message = $3, sent_at = $4
+            SET status = $2, error_message = $3, sent_at = $4, locked_until = NULL
             WHERE id = $1
             "#,
         )
@@ -222,7 +222,8 @@ impl NotificationRepository {
         let result: Option<(chrono::DateTime<Utc>,)> = sqlx::query_as(
             r#"
             SELECT created_at FROM notification_history
-            WHERE user_id = $1 AND channel = $2 AND status = 'sent'
+            WHERE user_id = $1 AND channel = $2 AND status IN ('pending', 'failed', 'sent')
+              AND event_type = 'alert_triggered'
             ORDER BY created_at DESC
             LIMIT 1
             "#,
@@ -235,6 +236,171 @@ impl NotificationRepository {
         Ok(result.map(|r| r.0))
     }
 
+    // ========== 通知发件箱 ==========
+
+    /// 写入发件箱（待投递记录）
+    pub async fn enqueue_notification(
+        &self,
+        alert_event_id: Uuid,
+        user_id: Uuid,
+        channel: NotificationChannel,
+        recipient: &str,
+        event_type: &str,
+        payload: &serde_json::Value,
+    ) -> Result<NotificationHistory, AppError> {
+        let history = sqlx::query_as::<_, NotificationHistory>(
+            r#"
+            INSERT INTO notification_history (
+                id, alert_event_id, user_id, channel, recipient, event_type,
+                status, payload, attempts, next_attempt_at, created_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, 'pending', $7, 0, NOW(), NOW())
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(alert_event_id)
+        .bind(user_id)
+        .bind(channel)
+        .bind(recipient)
+        .bind(event_type)
+        .bind(payload)
+        .fetch_one(self.pool.pool())
+        .await?;
+
+        Ok(history)
+    }
+
+    /// 领取到期的待投递记录
+    ///
+    /// 领取时累加尝试次数并设置租约，租约期内其他实例不会重复领取
+    pub async fn claim_due_notifications(
+        &self,
+        limit: i64,
+        lease_seconds: i64,
+    ) -> Result<Vec<NotificationHistory>, AppError> {
+        let histories = sqlx::query_as::<_, NotificationHistory>(
+            r#"
+            UPDATE notification_history
+            SET attempts = attempts + 1,
+                last_attempt_at = NOW(),
+                locked_until = NOW() + INTERVAL '1 second' * $2
+            WHERE id IN (
+                SELECT id FROM notification_history
+                WHERE status IN ('pending', 'failed')
+                  AND next_attempt_at <= NOW()
+                  AND (locked_until IS NULL OR locked_until < NOW())
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_seconds)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        Ok(histories)
+    }
+
+    /// 记录投递失败
+    ///
+    /// `retry_at` 为空表示不再重试，记录转为死信（dead）
+    pub async fn record_notification_failure(
+        &self,
+        history_id: Uuid,
+        error_message: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE notification_history
+            SET status = CASE WHEN $3::timestamptz IS NULL THEN 'dead' ELSE 'failed' END,
+                error_message = $2,
+                next_attempt_at = $3,
+                locked_until = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(history_id)
+        .bind(error_message)
+        .bind(retry_at)
+        .execute(self.pool.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// 查询发件箱记录（管理员）
+    pub async fn list_outbox(
+        &self,
+        query: &NotificationOutboxQuery,
+    ) -> Result<(Vec<NotificationHistory>, i64), AppError> {
+        let offset = (query.page - 1) * query.page_size;
+
+        let histories = sqlx::query_as::<_, NotificationHistory>(
+            r#"
+            SELECT * FROM notification_history
+            WHERE ($1::varchar IS NULL OR status = $1)
+              AND ($2::notification_channel IS NULL OR channel = $2)
+              AND ($3::uuid IS NULL OR user_id = $3)
+            ORDER BY created_at DESC
+            LIMIT $4 OFFSET $5
+            "#,
+        )
+        .bind(&query.status)
+        .bind(&query.channel)
+        .bind(query.user_id)
+        .bind(query.page_size)
+        .bind(offset)
+        .fetch_all(self.pool.pool())
+        .await?;
+
+        let total: (i64,) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*) FROM notification_history
+            WHERE ($1::varchar IS NULL OR status = $1)
+              AND ($2::notification_channel IS NULL OR channel = $2)
+              AND ($3::uuid IS NULL OR user_id = $3)
+            "#,
+        )
+        .bind(&query.status)
+        .bind(&query.channel)
+        .bind(query.user_id)
+        .fetch_one(self.pool.pool())
+        .await?;
+
+        Ok((histories, total.0))
+    }
+
+    /// 重放失败或死信记录（重置尝试次数并立即投递）
+    pub async fn replay_notification(
+        &self,
+        history_id: Uuid,
+    ) -> Result<NotificationHistory, AppError> {
+        let history = sqlx::query_as::<_, NotificationHistory>(
+            r#"
+            UPDATE notification_history
+            SET status = 'pending',
+                attempts = 0,
+                error_message = NULL,
+                next_attempt_at = NOW(),
+                locked_until = NULL
+            WHERE id = $1
+              AND status IN ('failed', 'dead')
+              AND payload IS NOT NULL
+            RETURNING *
+            "#,
+        )
+        .bind(history_id)
+        .fetch_optional(self.pool.pool())
+        .await?;
+
+        history
+            .ok_or_else(|| AppError::NotFound(format!("通知记录不存在或不可重放: {}", history_id)))
+    }
+
     // ========== Web Push 订阅管理 ==========
 
     /// 创建或更新 Web Push 订阅
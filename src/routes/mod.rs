@@ -108,10 +108,35 @@ pub fn configure(
                             "/batch-report",
                             web::post().to(handlers::batch_report_battery),
                         )
+                        .route(
+                            "/gateway-report",
+                            web::post().to(handlers::gateway_report_battery),
+                        )
                         .route(
                             "/latest/{device_id}",
                             web::get().to(handlers::get_latest_battery),
                         )
+                        .route(
+                            "/estimate/{device_id}",
+                            web::get().to(handlers::get_battery_estimate),
+                        )
+                        .route(
+                            "/charging-sessions/{device_id}",
+                            web::get().to(handlers::list_charging_sessions),
+                        )
+                        .route(
+                            "/health/{device_id}",
+                            web::get().to(handlers::get_battery_health),
+                        )
+                        .route("/export", web::get().to(handlers::export_battery_history))
+                        .route(
+                            "/import/{device_id}",
+                            web::post().to(handlers::import_battery_history),
+                        )
+                        .route(
+                            "/history/{device_id}/stream",
+                            web::get().to(handlers::stream_battery_history),
+                        )
                         .route(
                             "/history/{device_id}",
                             web::get().to(handlers::get_battery_history),
@@ -205,6 +230,27 @@ pub fn configure(
                             web::get().to(handlers::count_active_alerts),
                         ),
                 )
+                // 自定义指标路由（需要认证，修改需要管理员权限）
+                .service(
+                    web::scope("/metrics")
+                        .wrap(jwt_auth.clone())
+                        .route(
+                            "/definitions",
+                            web::get().to(handlers::list_metric_definitions),
+                        )
+                        .route(
+                            "/definitions",
+                            web::post().to(handlers::create_metric_definition),
+                        )
+                        .route(
+                            "/definitions/{id}",
+                            web::put().to(handlers::update_metric_definition),
+                        )
+                        .route(
+                            "/definitions/{id}",
+                            web::delete().to(handlers::delete_metric_definition),
+                        ),
+                )
                 // 通知偏好路由（需要认证）
                 .service(
                     web::scope("/notifications")
@@ -233,6 +279,12 @@ pub fn configure(
                         .route(
                             "/web-push/subscriptions/{id}",
                             web::delete().to(handlers::unsubscribe_web_push),
+                        )
+                        // 通知发件箱（管理员）
+                        .route("/outbox", web::get().to(handlers::list_notification_outbox))
+                        .route(
+                            "/outbox/{id}/replay",
+                            web::post().to(handlers::replay_notification),
                         ),
                 ),
         );
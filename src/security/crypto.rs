@@ -3,6 +3,7 @@
 use crate::errors::AppError;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use ring::aead::{self, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
+use ring::hmac;
 use ring::rand::{SecureRandom, SystemRandom};
 
 /// 加密上下文
@@ -111,6 +112,16 @@ pub fn generate_encryption_key() -> Result<String, AppError> {
     Ok(BASE64.encode(bytes))
 }
 
+/// 计算 HMAC-SHA256 签名（小写十六进制）
+pub fn hmac_sha256_hex(key: &[u8], message: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    hmac::sign(&key, message)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -127,6 +138,16 @@ mod tests {
         assert_eq!(plaintext.to_vec(), decrypted);
     }
 
+    #[test]
+    fn test_hmac_sha256_hex() {
+        // RFC 4231 测试用例 2
+        let signature = hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            signature,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
     #[test]
     fn test_encrypt_decrypt_base64() {
         let key = generate_encryption_key().unwrap();
@@ -2,10 +2,12 @@
 
 use crate::errors::AppError;
 use crate::models::{
-    AlertEvent, AlertListQuery, AlertRule, AlertStatus, AlertType, CreateAlertRuleRequest,
-    PaginatedResponse, Pagination, UpdateAlertRuleRequest, UpdateAlertStatusRequest,
+    AlertEvent, AlertListQuery, AlertRule, AlertRuleScope, AlertStatus, AlertType,
+    CreateAlertRuleRequest, Device, DeviceAlertRules, DeviceConfig, PaginatedResponse, Pagination,
+    UpdateAlertRuleRequest, UpdateAlertStatusRequest,
 };
 use crate::repositories::AlertRepository;
+use chrono::{DateTime, Utc};
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -13,6 +15,37 @@ use uuid::Uuid;
 pub struct AlertService {
     alert_repo: AlertRepository,
     notification_service: Option<Arc<dyn NotificationSender>>,
+    push_sender: Option<Arc<dyn AlertPushSender>>,
+}
+
+/// 预警检查结果
+#[derive(Debug)]
+pub enum AlertOutcome {
+    /// 没有对设备生效的规则
+    NoRule,
+    /// 指标未越过规则阈值
+    NotBreached,
+    /// 越过阈值但处于冷却期内
+    Cooldown,
+    /// 已触发预警
+    Triggered(AlertEvent),
+}
+
+/// 单条数据的预警检查上下文
+pub struct AlertCheck<'a> {
+    /// 对设备生效的规则（同一批数据只查询一次）
+    pub rules: &'a DeviceAlertRules,
+    /// 数据的记录时间（作为预警的触发和解决时间）
+    pub at: DateTime<Utc>,
+    /// 为 false 时仍记录事件和推送，但不发送通知（用于补传的历史数据）
+    pub notify: bool,
+}
+
+impl AlertOutcome {
+    /// 指标是否越过阈值（无论是否真正触发）
+    pub fn is_breached(&self) -> bool {
+        matches!(self, AlertOutcome::Cooldown | AlertOutcome::Triggered(_))
+    }
 }
 
 /// 通知发送器trait（用于依赖注入）
@@ -23,6 +56,18 @@ pub trait NotificationSender: Send + Sync {
         alert_event: &AlertEvent,
         user_id: Uuid,
     ) -> Result<(), AppError>;
+
+    async fn send_alert_resolved_notification(
+        &self,
+        alert_event: &AlertEvent,
+        user_id: Uuid,
+    ) -> Result<(), AppError>;
+}
+
+/// 预警实时推送器 trait（用于依赖注入，避免与 websocket 模块循环依赖）
+#[async_trait::async_trait]
+pub trait AlertPushSender: Send + Sync {
+    async fn push_alert(&self, alert_event: &AlertEvent, user_ids: &[Uuid]);
 }
 
 impl AlertService {
@@ -30,6 +75,7 @@ impl AlertService {
         Self {
             alert_repo,
             notification_service: None,
+            push_sender: None,
         }
     }
 
@@ -38,12 +84,36 @@ impl AlertService {
         self.notification_service = Some(notification_service);
     }
 
+    /// 设置实时推送器（延迟注入，避免循环依赖）
+    pub fn set_push_sender(&mut self, push_sender: Arc<dyn AlertPushSender>) {
+        self.push_sender = Some(push_sender);
+    }
+
     /// 创建预警规则（用户独立）
     pub async fn create_rule(
         &self,
         user_id: Uuid,
         request: CreateAlertRuleRequest,
     ) -> Result<AlertRule, AppError> {
+        let mut request = request;
+        request.device_type = self
+            .validate_scope(
+                user_id,
+                request.scope,
+                &mut request.device_ids,
+                request.device_type.take(),
+                request.threshold,
+            )
+            .await?;
+        request.metric_key = self
+            .validate_metric(
+                &request.alert_type,
+                request.metric_key.take(),
+                request.threshold,
+                request.device_type.as_deref(),
+            )
+            .await?;
+
         self.alert_repo.create_rule(user_id, &request).await
     }
 
@@ -67,9 +137,173 @@ impl AlertService {
         user_id: Uuid,
         request: UpdateAlertRuleRequest,
     ) -> Result<AlertRule, AppError> {
-        self.alert_repo
-            .update_rule(rule_id, user_id, &request)
-            .await
+        let mut rule = self.get_rule(rule_id, user_id).await?;
+
+        // 修改作用范围时设备列表与设备类型只取自本次请求
+        let (device_ids, device_type) = match request.scope {
+            Some(scope) => {
+                rule.scope = scope;
+                (request.device_ids.unwrap_or_default(), request.device_type)
+            }
+            None => (
+                request.device_ids.unwrap_or(rule.device_ids),
+                request.device_type.or(rule.device_type),
+            ),
+        };
+        rule.device_ids = device_ids;
+
+        if let Some(name) = request.name {
+            rule.name = name;
+        }
+        if let Some(alert_type) = request.alert_type {
+            rule.alert_type = alert_type;
+        }
+        if let Some(level) = request.level {
+            rule.level = level;
+        }
+        if let Some(cooldown_minutes) = request.cooldown_minutes {
+            rule.cooldown_minutes = cooldown_minutes;
+        }
+        if let Some(enabled) = request.enabled {
+            rule.enabled = enabled;
+        }
+        if let Some(threshold) = request.threshold {
+            rule.threshold = threshold;
+        }
+        if let Some(comparison) = request.comparison {
+            rule.comparison = comparison;
+        }
+        if let Some(metric_key) = request.metric_key {
+            rule.metric_key = metric_key;
+        }
+
+        rule.device_type = self
+            .validate_scope(
+                user_id,
+                rule.scope,
+                &mut rule.device_ids,
+                device_type,
+                rule.threshold,
+            )
+            .await?;
+        rule.metric_key = self
+            .validate_metric(
+                &rule.alert_type,
+                rule.metric_key.take(),
+                rule.threshold,
+                rule.device_type.as_deref(),
+            )
+            .await?;
+
+        self.alert_repo.update_rule(&rule).await
+    }
+
+    /// 校验规则引用的自定义指标，返回规范化后的指标键
+    ///
+    /// custom_metric 规则必须指定已注册的指标键和阈值（作用于设备类型时须为该类型注册的指标），
+    /// 其余类型不能指定指标键
+    async fn validate_metric(
+        &self,
+        alert_type: &AlertType,
+        metric_key: Option<String>,
+        threshold: Option<f64>,
+        device_type: Option<&str>,
+    ) -> Result<Option<String>, AppError> {
+        let metric_key = metric_key
+            .map(|k| k.trim().to_string())
+            .filter(|k| !k.is_empty());
+
+        if *alert_type != AlertType::CustomMetric {
+            if metric_key.is_some() {
+                return Err(AppError::ValidationError(
+                    "只有自定义指标预警可以指定 metric_key".to_string(),
+                ));
+            }
+            return Ok(None);
+        }
+
+        let key = metric_key.ok_or_else(|| {
+            AppError::ValidationError("自定义指标预警必须指定 metric_key".to_string())
+        })?;
+        if threshold.is_none() {
+            return Err(AppError::ValidationError(
+                "自定义指标预警必须设置阈值".to_string(),
+            ));
+        }
+        if !self.alert_repo.metric_registered(&key, device_type).await? {
+            return Err(AppError::ValidationError(format!("未注册的指标: {}", key)));
+        }
+
+        Ok(Some(key))
+    }
+
+    /// 校验规则作用范围，返回规范化后的设备类型
+    ///
+    /// 设备列表会去重，且必须全部属于当前用户
+    async fn validate_scope(
+        &self,
+        user_id: Uuid,
+        scope: AlertRuleScope,
+        device_ids: &mut Vec<Uuid>,
+        device_type: Option<String>,
+        threshold: Option<f64>,
+    ) -> Result<Option<String>, AppError> {
+        if threshold.is_some_and(|t| !t.is_finite()) {
+            return Err(AppError::ValidationError("阈值必须为有效数值".to_string()));
+        }
+
+        let device_type = device_type
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty());
+
+        match scope {
+            AlertRuleScope::All => {
+                if !device_ids.is_empty() || device_type.is_some() {
+                    return Err(AppError::ValidationError(
+                        "作用范围为全部设备时不能指定 device_ids 或 device_type".to_string(),
+                    ));
+                }
+            }
+            AlertRuleScope::Devices => {
+                if device_type.is_some() {
+                    return Err(AppError::ValidationError(
+                        "作用范围为指定设备时不能指定 device_type".to_string(),
+                    ));
+                }
+
+                device_ids.sort();
+                device_ids.dedup();
+                if device_ids.is_empty() {
+                    return Err(AppError::ValidationError(
+                        "作用范围为指定设备时 device_ids 不能为空".to_string(),
+                    ));
+                }
+
+                let owned = self
+                    .alert_repo
+                    .count_owned_devices(user_id, device_ids)
+                    .await?;
+                if owned != device_ids.len() as i64 {
+                    return Err(AppError::ValidationError(
+                        "device_ids 包含不存在或无权访问的设备".to_string(),
+                    ));
+                }
+            }
+            AlertRuleScope::DeviceType => {
+                if !device_ids.is_empty() {
+                    return Err(AppError::ValidationError(
+                        "作用范围为设备类型时不能指定 device_ids".to_string(),
+                    ));
+                }
+                if device_type.is_none() {
+                    return Err(AppError::ValidationError(
+                        "作用范围为设备类型时 device_type 不能为空".to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(device_type)
     }
 
     /// 删除预警规则（仅限用户自己的规则）
@@ -77,96 +311,274 @@ impl AlertService {
         self.alert_repo.delete_rule(rule_id, user_id).await
     }
 
-    /// 触发低电量预警
+    /// 检查低电量预警
     pub async fn trigger_low_battery(
         &self,
-        device_id: Uuid,
-        user_id: Uuid,
+        device: &Device,
+        component: Option<&str>,
         level: f64,
-        threshold: f64,
-    ) -> Result<Option<AlertEvent>, AppError> {
+        default_threshold: f64,
+        check: &AlertCheck<'_>,
+    ) -> Result<AlertOutcome, AppError> {
         self.trigger_alert(
-            device_id,
-            user_id,
+            device,
             AlertType::LowBattery,
             level,
-            threshold,
-            &format!("设备电量低: {}%", level as i32),
+            default_threshold,
+            &format!("{}电量低: {}%", subject(component), level as i32),
+            None,
+            component,
+            check,
         )
         .await
     }
 
-    /// 触发临界电量预警
+    /// 检查临界电量预警
     pub async fn trigger_critical_battery(
         &self,
-        device_id: Uuid,
-        user_id: Uuid,
+        device: &Device,
+        component: Option<&str>,
         level: f64,
-        threshold: f64,
-    ) -> Result<Option<AlertEvent>, AppError> {
+        default_threshold: f64,
+        check: &AlertCheck<'_>,
+    ) -> Result<AlertOutcome, AppError> {
         self.trigger_alert(
-            device_id,
-            user_id,
+            device,
             AlertType::CriticalBattery,
             level,
-            threshold,
-            &format!("设备电量临界: {}%", level as i32),
+            default_threshold,
+            &format!("{}电量临界: {}%", subject(component), level as i32),
+            None,
+            component,
+            check,
         )
         .await
     }
 
-    /// 触发高温预警
+    /// 检查高温预警
     pub async fn trigger_high_temperature(
         &self,
-        device_id: Uuid,
-        user_id: Uuid,
+        device: &Device,
+        component: Option<&str>,
         temperature: f64,
-        threshold: f64,
-    ) -> Result<Option<AlertEvent>, AppError> {
+        default_threshold: f64,
+        check: &AlertCheck<'_>,
+    ) -> Result<AlertOutcome, AppError> {
         self.trigger_alert(
-            device_id,
-            user_id,
+            device,
             AlertType::HighTemperature,
             temperature,
-            threshold,
-            &format!("设备温度过高: {:.1}°C", temperature),
+            default_threshold,
+            &format!("{}温度过高: {:.1}°C", subject(component), temperature),
+            None,
+            component,
+            check,
         )
         .await
     }
 
-    /// 触发设备离线预警
-    pub async fn trigger_device_offline(
+    /// 检查快速耗电预警
+    pub async fn trigger_rapid_drain(
         &self,
-        device_id: Uuid,
-        user_id: Uuid,
-    ) -> Result<Option<AlertEvent>, AppError> {
+        device: &Device,
+        component: Option<&str>,
+        rate_per_hour: f64,
+        default_threshold: f64,
+        check: &AlertCheck<'_>,
+    ) -> Result<AlertOutcome, AppError> {
         self.trigger_alert(
-            device_id,
-            user_id,
+            device,
+            AlertType::RapidDrain,
+            rate_per_hour,
+            default_threshold,
+            &format!(
+                "{}电量下降过快: {:.1}%/小时",
+                subject(component),
+                rate_per_hour
+            ),
+            None,
+            component,
+            check,
+        )
+        .await
+    }
+
+    /// 触发设备离线预警（离线判定由检测任务完成）
+    pub async fn trigger_device_offline(&self, device: &Device) -> Result<AlertOutcome, AppError> {
+        let rules = self.device_rules(device).await?;
+        self.trigger_alert(
+            device,
             AlertType::DeviceOffline,
             0.0,
             0.0,
             "设备已离线",
+            None,
+            None,
+            &AlertCheck {
+                rules: &rules,
+                at: Utc::now(),
+                notify: true,
+            },
         )
         .await
     }
 
-    /// 触发预警
-    async fn trigger_alert(
+    /// 检查自定义指标预警（阈值只取自规则）
+    pub async fn trigger_custom_metric(
+        &self,
+        device: &Device,
+        component: Option<&str>,
+        metric_key: &str,
+        value: f64,
+        check: &AlertCheck<'_>,
+    ) -> Result<AlertOutcome, AppError> {
+        // 自定义指标规则必须设置阈值，NaN 保证缺少阈值时不会触发
+        self.trigger_alert(
+            device,
+            AlertType::CustomMetric,
+            value,
+            f64::NAN,
+            &format!("{}指标 {} 异常: {}", subject(component), metric_key, value),
+            Some(metric_key),
+            component,
+            check,
+        )
+        .await
+    }
+
+    /// 对设备生效的启用规则（设备无所有者时为空）
+    pub async fn device_rules(&self, device: &Device) -> Result<DeviceAlertRules, AppError> {
+        match device.owner_id {
+            Some(user_id) => self
+                .alert_repo
+                .find_device_rules(user_id, device.id, &device.device_type)
+                .await
+                .map(DeviceAlertRules::new),
+            None => Ok(DeviceAlertRules::default()),
+        }
+    }
+
+    /// 自动解决已恢复正常的预警（含离线预警）
+    ///
+    /// 回差避免指标在阈值附近波动时反复触发和解决
+    #[allow(clippy::too_many_arguments)]
+    pub async fn resolve_recovered(
         &self,
         device_id: Uuid,
+        component: Option<&str>,
         user_id: Uuid,
+        battery_level: f64,
+        temperature: Option<f64>,
+        config: &DeviceConfig,
+        check: &AlertCheck<'_>,
+    ) -> Result<Vec<AlertEvent>, AppError> {
+        let recovered: Vec<Uuid> = self
+            .alert_repo
+            .find_unresolved_events(device_id)
+            .await?
+            .into_iter()
+            .filter(|e| e.is_recovered(component, battery_level, temperature, config, check.at))
+            .map(|e| e.id)
+            .collect();
+        if recovered.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let events = self
+            .alert_repo
+            .resolve_events(device_id, &recovered, check.at)
+            .await?;
+
+        self.notify_resolved(device_id, user_id, &events, check.notify)
+            .await;
+
+        Ok(events)
+    }
+
+    /// 自动解决已恢复正常的自定义指标预警（`metrics` 为本次上报的指标键和数值）
+    pub async fn resolve_recovered_metrics(
+        &self,
+        device_id: Uuid,
+        component: Option<&str>,
+        user_id: Uuid,
+        metrics: &[(String, f64)],
+        check: &AlertCheck<'_>,
+    ) -> Result<Vec<AlertEvent>, AppError> {
+        let (keys, values): (Vec<String>, Vec<f64>) = metrics.iter().cloned().unzip();
+        let events = self
+            .alert_repo
+            .resolve_recovered_metric_events(device_id, component, &keys, &values, check.at)
+            .await?;
+
+        self.notify_resolved(device_id, user_id, &events, check.notify)
+            .await;
+
+        Ok(events)
+    }
+
+    /// 推送已自动解决的预警，`notify` 为 true 时发送解决通知
+    async fn notify_resolved(
+        &self,
+        device_id: Uuid,
+        user_id: Uuid,
+        events: &[AlertEvent],
+        notify: bool,
+    ) {
+        for event in events {
+            tracing::info!(
+                device_id = %device_id,
+                alert_id = %event.id,
+                alert_type = ?event.alert_type,
+                "设备指标已恢复，预警已自动解决"
+            );
+
+            self.push_event(event).await;
+
+            if !notify {
+                continue;
+            }
+            if let Some(ref notification_service) = self.notification_service {
+                if let Err(e) = notification_service
+                    .send_alert_resolved_notification(event, user_id)
+                    .await
+                {
+                    tracing::error!(
+                        error = %e,
+                        alert_id = %event.id,
+                        user_id = %user_id,
+                        "解决通知发送失败"
+                    );
+                }
+            }
+        }
+    }
+
+    /// 按最具体的匹配规则判断并触发预警
+    ///
+    /// 规则未设置阈值时使用 `default_threshold`（设备配置）；
+    /// `metric_key` 仅用于自定义指标预警；`component` 为触发预警的电池组件（按组件分别冷却）；
+    /// 冷却期和触发时间以 `check.at`（数据的记录时间）为准
+    #[allow(clippy::too_many_arguments)]
+    async fn trigger_alert(
+        &self,
+        device: &Device,
         alert_type: AlertType,
         value: f64,
-        threshold: f64,
+        default_threshold: f64,
         message: &str,
-    ) -> Result<Option<AlertEvent>, AppError> {
-        // 获取对应的预警规则（用于级别和冷却时间）
-        let rule = match self
-            .alert_repo
-            .get_rule_by_type(user_id, &alert_type)
-            .await?
-        {
+        metric_key: Option<&str>,
+        component: Option<&str>,
+        check: &AlertCheck<'_>,
+    ) -> Result<AlertOutcome, AppError> {
+        let device_id = device.id;
+
+        // 规则和通知归属于设备所有者
+        let user_id = match device.owner_id {
+            Some(uid) => uid,
+            None => return Ok(AlertOutcome::NoRule),
+        };
+
+        let rule = match check.rules.matching(&alert_type, metric_key) {
             Some(r) => r,
             None => {
                 tracing::debug!(
@@ -174,14 +586,31 @@ impl AlertService {
                     alert_type = ?alert_type,
                     "未找到对应的预警规则"
                 );
-                return Ok(None);
+                return Ok(AlertOutcome::NoRule);
+            }
+        };
+
+        // 离线预警没有数值阈值
+        let threshold = if alert_type == AlertType::DeviceOffline {
+            default_threshold
+        } else {
+            match rule.breached(value, default_threshold) {
+                Some(t) => t,
+                None => return Ok(AlertOutcome::NotBreached),
             }
         };
 
         // 检查是否在冷却期内
         if self
             .alert_repo
-            .is_in_cooldown(device_id, &alert_type, rule.cooldown_minutes)
+            .is_in_cooldown(
+                device_id,
+                &alert_type,
+                metric_key,
+                component,
+                rule.cooldown_minutes,
+                check.at,
+            )
             .await?
         {
             tracing::debug!(
@@ -189,27 +618,34 @@ impl AlertService {
                 alert_type = ?alert_type,
                 "预警处于冷却期内"
             );
-            return Ok(None);
+            return Ok(AlertOutcome::Cooldown);
         }
 
-        // 创建预警事件（使用设备实际阈值）
+        // 创建预警事件（记录实际使用的阈值）
         let event = self
             .alert_repo
-            .create_event(device_id, &rule, value, threshold, message)
+            .create_event(
+                device_id, component, rule, value, threshold, message, check.at,
+            )
             .await?;
 
         tracing::info!(
             device_id = %device_id,
             alert_type = ?alert_type,
             level = ?rule.level,
+            rule_id = %rule.id,
             value = value,
             threshold = threshold,
             "触发预警"
         );
 
-        // TODO: 发送通知（webhook、邮件等）
+        // 推送给 WebSocket 会话
+        self.push_event(&event).await;
+
         // 发送通知
-        if let Some(ref notification_service) = self.notification_service {
+        if !check.notify {
+            tracing::debug!(alert_id = %event.id, "历史数据触发的预警，跳过通知");
+        } else if let Some(ref notification_service) = self.notification_service {
             // 获取设备所属用户ID
             if let Err(e) = notification_service
                 .send_alert_notification(&event, user_id)
@@ -225,7 +661,7 @@ impl AlertService {
             }
         }
 
-        Ok(Some(event))
+        Ok(AlertOutcome::Triggered(event))
     }
 
     /// 更新预警状态（仅限用户设备的预警）
@@ -235,9 +671,37 @@ impl AlertService {
         user_id: Uuid,
         request: UpdateAlertStatusRequest,
     ) -> Result<AlertEvent, AppError> {
-        self.alert_repo
+        let event = self
+            .alert_repo
             .update_event_status(event_id, user_id, &request)
-            .await
+            .await?;
+
+        // 推送状态变更
+        self.push_event(&event).await;
+
+        Ok(event)
+    }
+
+    /// 推送预警事件给设备所有者及共享用户
+    async fn push_event(&self, event: &AlertEvent) {
+        let push_sender = match self.push_sender {
+            Some(ref p) => p,
+            None => return,
+        };
+
+        match self.alert_repo.get_device_audience(event.device_id).await {
+            Ok(user_ids) if !user_ids.is_empty() => {
+                push_sender.push_alert(event, &user_ids).await;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!(
+                    error = %e,
+                    alert_id = %event.id,
+                    "获取预警推送对象失败"
+                );
+            }
+        }
     }
 
     /// 确认预警
@@ -282,3 +746,11 @@ impl AlertService {
         self.alert_repo.count_active_alerts(device_id).await
     }
 }
+
+/// 预警消息的主语（多电池设备指明组件）
+fn subject(component: Option<&str>) -> String {
+    match component {
+        Some(c) => format!("设备组件 {} ", c),
+        None => "设备".to_string(),
+    }
+}
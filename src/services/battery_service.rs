@@ -1,121 +1,493 @@
 //! 电量业务服务
 
+use crate::config::Settings;
 use crate::db::RedisPool;
 use crate::errors::AppError;
 use crate::models::{
-    AggregateInterval, BatteryAggregatePoint, BatteryData, BatteryQueryRequest,
-    BatteryReportRequest, BatteryStatsResponse, LatestBatteryResponse,
+    alert_evaluation_order, clock_offset_seconds, degradation_score, discharge_rate_per_hour,
+    downsample_lttb, estimate_battery, estimate_battery_rate, latest_by_component, metric_value,
+    notify_cutoff, parse_import, track_charging_sessions, validate_metrics, window_ending_at,
+    AggregateInterval, AlertType, BatchReportResponse, BatteryAggregatePoint, BatteryData,
+    BatteryEstimate, BatteryEstimateResponse, BatteryHealthReport, BatteryQueryRequest,
+    BatteryReportRequest, BatteryStatsResponse, BatteryStreamRequest, ChargingSession,
+    ChargingSessionListQuery, ComponentBattery, CursorPage, Device, DeviceAlertRules, DeviceClock,
+    DeviceConfig, GapFill, GatewayDeviceResult, GatewayReportRequest, GatewayReportResponse,
+    HealthStatus, HealthTrend, HealthTrendPoint, HistoryCursor, ImportFormat, ImportLineError,
+    ImportRecord, ImportReport, LatestBatteryResponse, MetricAggregate, MetricDefinition,
+    PaginatedResponse, Pagination, PowerSavingMode, RateEstimate, RejectedBatteryRecord,
 };
-use crate::repositories::{BatteryRepository, DeviceRepository};
-use crate::services::AlertService;
-use chrono::{DateTime, Utc};
+use crate::repositories::{
+    BatteryRepository, ChargingSessionRepository, DeviceRepository, MetricRepository,
+};
+use crate::services::{AlertCheck, AlertService};
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 use uuid::Uuid;
+use validator::Validate;
+
+/// 快速耗电检测的最短放电时长（分钟）
+const RAPID_DRAIN_MIN_MINUTES: i64 = 5;
+
+/// 续航估算的最短有效数据时长（分钟）
+const ESTIMATE_MIN_MINUTES: i64 = 10;
+
+/// 健康报告中单周放电速率的最短放电时长（小时）
+const HEALTH_MIN_DISCHARGE_HOURS: f64 = 1.0;
+
+/// 流式查询历史数据时每批读取的记录数
+const HISTORY_STREAM_BATCH_SIZE: i64 = 1000;
 
 /// 电量业务服务
 pub struct BatteryService {
     battery_repo: BatteryRepository,
+    charging_repo: ChargingSessionRepository,
     device_repo: DeviceRepository,
+    metric_repo: MetricRepository,
     alert_service: Arc<AlertService>,
     redis_pool: Arc<RedisPool>,
+    push_sender: Option<Arc<dyn BatteryPushSender>>,
+    max_batch_size: usize,
+    sample_dedupe_window: Duration,
+    estimate_window: Duration,
+    estimate_refresh_seconds: u64,
+    import_max_bytes: usize,
+    clock_skew_tolerance_seconds: i64,
+    clock_offset_window: Duration,
+    max_aggregate_buckets: i64,
+}
+
+/// 电量实时推送器 trait（用于依赖注入，避免与 websocket 模块循环依赖）
+#[async_trait::async_trait]
+pub trait BatteryPushSender: Send + Sync {
+    async fn push_battery(&self, device_id: Uuid, data: &LatestBatteryResponse);
 }
 
 impl BatteryService {
     pub fn new(
+        settings: &Settings,
         battery_repo: BatteryRepository,
+        charging_repo: ChargingSessionRepository,
         device_repo: DeviceRepository,
+        metric_repo: MetricRepository,
         alert_service: Arc<AlertService>,
         redis_pool: Arc<RedisPool>,
     ) -> Self {
         Self {
             battery_repo,
+            charging_repo,
             device_repo,
+            metric_repo,
             alert_service,
             redis_pool,
+            push_sender: None,
+            max_batch_size: settings.battery.max_batch_size,
+            sample_dedupe_window: Duration::hours(settings.battery.sample_dedupe_window_hours),
+            estimate_window: Duration::hours(settings.battery.estimate_window_hours),
+            estimate_refresh_seconds: settings.battery.estimate_refresh_seconds.max(1),
+            import_max_bytes: settings.battery.import_max_bytes,
+            clock_skew_tolerance_seconds: settings.battery.clock_skew_tolerance_seconds,
+            clock_offset_window: Duration::minutes(settings.battery.clock_offset_window_minutes),
+            max_aggregate_buckets: settings.battery.max_aggregate_buckets,
         }
     }
 
+    /// 设置实时推送器（延迟注入，避免循环依赖）
+    pub fn set_push_sender(&mut self, push_sender: Arc<dyn BatteryPushSender>) {
+        self.push_sender = Some(push_sender);
+    }
+
     /// 上报电量数据
+    ///
+    /// 重复上报的样本（相同记录时间或样本 ID）返回已存在的数据，不再写入、推送和检查预警。
+    /// 以记录时间测量设备时钟偏差，并按设备时钟状态调整记录时间
     pub async fn report(
         &self,
         device_id: Uuid,
-        request: BatteryReportRequest,
+        mut request: BatteryReportRequest,
     ) -> Result<BatteryData, AppError> {
-        // 验证电量值范围
-        if request.battery_level < 0 || request.battery_level > 100 {
-            return Err(AppError::ValidationError(
-                "电量值必须在 0-100 之间".to_string(),
-            ));
-        }
+        // 设备和配置整个请求只查询一次
+        let device = self.find_device(device_id).await?;
+        let config = self.device_config(device_id).await?;
 
-        // 验证时间戳（不能是未来时间）
-        if let Some(recorded_at) = request.recorded_at {
-            if recorded_at > Utc::now() {
-                return Err(AppError::ValidationError(
-                    "记录时间不能是未来时间".to_string(),
-                ));
-            }
-        }
+        let received_at = Utc::now();
+        self.device_clock(&device, &config, request.recorded_at, received_at)
+            .await?
+            .apply(&mut request, received_at, true)
+            .map_err(AppError::ValidationError)?;
+
+        // 验证数据范围、时间戳（不能是未来时间）、电池组件和自定义指标
+        let schema = self
+            .report_schema(&device, std::iter::once(&request))
+            .await?;
+        validate_report(&request, &schema).map_err(AppError::ValidationError)?;
 
         // 插入数据
-        let data = self.battery_repo.insert(device_id, &request).await?;
+        let (data, inserted) = self
+            .battery_repo
+            .insert(device_id, &request, Utc::now() - self.sample_dedupe_window)
+            .await?;
 
         // 更新设备最后在线时间
-        self.device_repo.update_last_seen(device_id).await?;
+        self.mark_online(device_id).await?;
 
-        // 更新缓存
-        self.update_latest_cache(device_id, &data).await?;
+        if !inserted {
+            tracing::debug!(device_id = %device_id, data_id = %data.id, "重复上报，已忽略");
+            return Ok(data);
+        }
+
+        // 划分充电会话
+        self.track_charging(device_id, std::slice::from_ref(&data))
+            .await;
+
+        // 更新缓存并推送给订阅者（迟到的乱序样本不更新）
+        if let Some(latest) = self.update_latest_cache(device_id, &data, &config).await? {
+            self.push_latest(device_id, &latest).await;
+        }
 
         // 检查预警
-        self.check_alerts(device_id, &data).await?;
+        self.check_alerts(&device, &config, std::slice::from_ref(&data), None)
+            .await?;
 
         Ok(data)
     }
 
     /// 批量上报电量数据
+    ///
+    /// 不合法的记录单独拒绝并在结果中返回下标和原因，其余记录照常写入；
+    /// 重复样本（含同一批次内样本 ID 重复的记录）只计数，不写入也不检查预警。
+    /// 按记录时间顺序逐条检查预警；记录时间早于 `notify_max_age_seconds` 的样本
+    /// 仍会记录预警事件，但不发送通知（避免补传离线缓存时打扰用户）。
+    /// 提供设备发送时间 `sent_at` 时以其测量设备时钟偏差；记录时间按设备时钟状态调整，
+    /// 明显错误的记录时间单独拒绝
     pub async fn batch_report(
         &self,
         device_id: Uuid,
         requests: Vec<BatteryReportRequest>,
-    ) -> Result<usize, AppError> {
-        // 验证所有数据
-        for request in &requests {
-            if request.battery_level < 0 || request.battery_level > 100 {
-                return Err(AppError::ValidationError(
-                    "电量值必须在 0-100 之间".to_string(),
-                ));
+        notify_max_age_seconds: Option<i64>,
+        sent_at: Option<DateTime<Utc>>,
+    ) -> Result<BatchReportResponse, AppError> {
+        let device = self.find_device(device_id).await?;
+        self.batch_report_device(&device, requests, notify_max_age_seconds, sent_at)
+            .await
+    }
+
+    /// 批量上报已查询到的设备的电量数据（设备配置整批只查询一次）
+    async fn batch_report_device(
+        &self,
+        device: &Device,
+        requests: Vec<BatteryReportRequest>,
+        notify_max_age_seconds: Option<i64>,
+        sent_at: Option<DateTime<Utc>>,
+    ) -> Result<BatchReportResponse, AppError> {
+        let device_id = device.id;
+        if requests.is_empty() {
+            return Err(AppError::ValidationError("批量数据不能为空".to_string()));
+        }
+        if requests.len() > self.max_batch_size {
+            return Err(AppError::ValidationError(format!(
+                "批量数据条数不能超过 {}",
+                self.max_batch_size
+            )));
+        }
+
+        let notify_cutoff =
+            notify_cutoff(notify_max_age_seconds, Utc::now()).map_err(AppError::ValidationError)?;
+
+        let config = self.device_config(device_id).await?;
+        let received_at = Utc::now();
+        let clock = self
+            .device_clock(device, &config, sent_at, received_at)
+            .await?;
+
+        // 逐条校验，记录原始下标（样本 ID 在同一组件内去重）
+        let schema = self.report_schema(device, requests.iter()).await?;
+        let mut rejected = Vec::new();
+        let mut duplicate_count = 0;
+        let mut sample_ids = HashSet::new();
+        let mut accepted = Vec::with_capacity(requests.len());
+        let mut accepted_index = Vec::with_capacity(requests.len());
+        for (index, mut request) in requests.into_iter().enumerate() {
+            if let Err(error) = clock
+                .apply(&mut request, received_at, false)
+                .and_then(|_| validate_report(&request, &schema))
+            {
+                rejected.push(RejectedBatteryRecord { index, error });
+                continue;
+            }
+
+            if let Some(ref sample_id) = request.sample_id {
+                let key = (request.stored_component().to_string(), sample_id.clone());
+                if !sample_ids.insert(key) {
+                    duplicate_count += 1;
+                    continue;
+                }
             }
+
+            accepted_index.push(index);
+            accepted.push(request);
+        }
+
+        // 批量插入（数据库约束拒绝的记录下标需映射回原始下标）
+        let result = self
+            .battery_repo
+            .batch_insert(device_id, &accepted, Utc::now() - self.sample_dedupe_window)
+            .await?;
+        let mut samples = result.inserted;
+        duplicate_count += result.duplicates.len();
+        rejected.extend(result.rejected.into_iter().map(|r| RejectedBatteryRecord {
+            index: accepted_index[r.index],
+            error: r.error,
+        }));
+        rejected.sort_by_key(|r| r.index);
+
+        if duplicate_count > 0 {
+            tracing::debug!(device_id = %device_id, duplicates = duplicate_count, "批量上报包含重复样本");
         }
 
-        // 批量插入
-        let count = self.battery_repo.batch_insert(device_id, &requests).await?;
+        // 更新设备最后在线时间（全部为重复样本时同样说明设备在线）
+        if !samples.is_empty() || duplicate_count > 0 {
+            self.mark_online(device_id).await?;
+        }
 
-        // 更新设备最后在线时间
-        self.device_repo.update_last_seen(device_id).await?;
+        if !samples.is_empty() {
+            samples.sort_by_key(|d| d.recorded_at);
 
-        // 检查最新数据的预警
-        if let Some(latest) = requests.last() {
-            let data = BatteryData {
-                id: Uuid::new_v4(),
-                device_id,
-                battery_level: latest.battery_level,
-                is_charging: latest.is_charging,
-                power_saving_mode: latest.power_saving_mode.clone(),
-                temperature: latest.temperature,
-                voltage: latest.voltage,
-                recorded_at: latest.recorded_at.unwrap_or_else(Utc::now),
-                created_at: Utc::now(),
+            // 划分充电会话
+            self.track_charging(device_id, &samples).await;
+
+            // 以每个组件记录时间最新的数据更新缓存并推送给订阅者
+            for data in latest_by_component(&samples) {
+                if let Some(latest) = self.update_latest_cache(device_id, data, &config).await? {
+                    self.push_latest(device_id, &latest).await;
+                }
+            }
+
+            self.check_alerts(device, &config, &samples, notify_cutoff)
+                .await?;
+        }
+
+        Ok(BatchReportResponse {
+            inserted_count: samples.len(),
+            duplicate_count,
+            rejected_count: rejected.len(),
+            rejected,
+        })
+    }
+
+    /// 网关代子设备批量上报
+    ///
+    /// 每台子设备按批量上报的规则单独处理，结果按请求顺序返回；
+    /// 不属于该网关的子设备或整体无法处理的数据（如条数为空、组件不合法）只拒绝该子设备，
+    /// 不影响其他子设备。所有子设备的记录总数同样受批量上报条数上限限制
+    pub async fn gateway_report(
+        &self,
+        gateway_id: Uuid,
+        request: GatewayReportRequest,
+    ) -> Result<GatewayReportResponse, AppError> {
+        let total: usize = request.devices.iter().map(|d| d.data.len()).sum();
+        if total > self.max_batch_size {
+            return Err(AppError::ValidationError(format!(
+                "网关上报的记录总数不能超过 {}",
+                self.max_batch_size
+            )));
+        }
+
+        let mut device_ids = Vec::with_capacity(request.devices.len());
+        for entry in &request.devices {
+            if device_ids.contains(&entry.device_id) {
+                return Err(AppError::ValidationError(format!(
+                    "子设备 {} 重复出现",
+                    entry.device_id
+                )));
+            }
+            device_ids.push(entry.device_id);
+        }
+
+        let children: HashMap<Uuid, Device> = self
+            .device_repo
+            .find_children(gateway_id, &device_ids)
+            .await?
+            .into_iter()
+            .map(|d| (d.id, d))
+            .collect();
+
+        // 网关能代为上报说明网关在线（先于子设备处理，子设备失败不影响网关状态）
+        self.mark_online(gateway_id).await?;
+
+        let mut results = Vec::with_capacity(request.devices.len());
+        for entry in request.devices {
+            let outcome = match children.get(&entry.device_id) {
+                Some(child) => {
+                    self.batch_report_device(
+                        child,
+                        entry.data,
+                        request.notify_max_age_seconds,
+                        None,
+                    )
+                    .await
+                }
+                None => Err(AppError::Forbidden("设备不属于此网关".to_string())),
             };
-            self.check_alerts(device_id, &data).await?;
+
+            results.push(match outcome {
+                Ok(result) => GatewayDeviceResult {
+                    device_id: entry.device_id,
+                    result: Some(result),
+                    error: None,
+                },
+                Err(AppError::ValidationError(error))
+                | Err(AppError::Forbidden(error))
+                | Err(AppError::NotFound(error)) => GatewayDeviceResult {
+                    device_id: entry.device_id,
+                    result: None,
+                    error: Some(error),
+                },
+                // 服务端错误只记录到该子设备，已写入的其他子设备结果照常返回，避免网关整体重试导致重复写入
+                Err(e) => {
+                    tracing::error!(
+                        error = ?e,
+                        gateway_id = %gateway_id,
+                        device_id = %entry.device_id,
+                        "网关子设备上报失败"
+                    );
+                    GatewayDeviceResult {
+                        device_id: entry.device_id,
+                        result: None,
+                        error: Some(e.to_string()),
+                    }
+                }
+            });
+        }
+
+        let accepted_devices = results.iter().filter(|r| r.result.is_some()).count();
+        Ok(GatewayReportResponse {
+            accepted_devices,
+            failed_devices: results.len() - accepted_devices,
+            results,
+        })
+    }
+
+    /// 导入文件允许的最大字节数
+    pub fn import_max_bytes(&self) -> usize {
+        self.import_max_bytes
+    }
+
+    /// 导入历史电量数据（CSV / NDJSON）
+    ///
+    /// 每行按与上报相同的规则校验，不合法的行在报告中返回行号和原因；
+    /// 与已有数据重复的样本（同一组件相同记录时间或样本 ID）以及文件内重复的样本 ID 只计数不写入。
+    /// 导入的是历史数据，不检查预警、不划分充电会话，也不更新最新电量缓存和设备在线状态
+    pub async fn import_history(
+        &self,
+        device_id: Uuid,
+        format: ImportFormat,
+        content: &str,
+    ) -> Result<ImportReport, AppError> {
+        let device = self.find_device(device_id).await?;
+
+        let parsed = parse_import(format, content).map_err(AppError::ValidationError)?;
+        let total_rows = parsed.records.len() + parsed.errors.len();
+        if total_rows == 0 {
+            return Err(AppError::ValidationError("导入文件没有数据".to_string()));
+        }
+
+        let schema = self
+            .report_schema(&device, parsed.records.iter().map(|r| &r.request))
+            .await?;
+
+        let mut errors = parsed.errors;
+        let mut duplicate_count = 0;
+        let mut sample_ids = HashSet::new();
+        let mut accepted: Vec<ImportRecord> = Vec::with_capacity(parsed.records.len());
+        for record in parsed.records {
+            if let Err(error) = validate_report(&record.request, &schema) {
+                errors.push(ImportLineError {
+                    line: record.line,
+                    error,
+                });
+                continue;
+            }
+
+            if let Some(ref sample_id) = record.request.sample_id {
+                let key = (
+                    record.request.stored_component().to_string(),
+                    sample_id.clone(),
+                );
+                if !sample_ids.insert(key) {
+                    duplicate_count += 1;
+                    continue;
+                }
+            }
+
+            accepted.push(record);
         }
 
-        Ok(count)
+        // 按批量上报的条数上限分批写入
+        let mut inserted_count = 0;
+        for chunk in accepted.chunks(self.max_batch_size.max(1)) {
+            let requests: Vec<BatteryReportRequest> =
+                chunk.iter().map(|r| r.request.clone()).collect();
+            // 样本 ID 去重窗口从本批最早的记录时间算起
+            let dedupe_since = requests
+                .iter()
+                .filter_map(|r| r.recorded_at)
+                .min()
+                .unwrap_or_else(Utc::now)
+                - self.sample_dedupe_window;
+
+            let result = self
+                .battery_repo
+                .batch_insert(device_id, &requests, dedupe_since)
+                .await?;
+            inserted_count += result.inserted.len();
+            duplicate_count += result.duplicates.len();
+            errors.extend(result.rejected.into_iter().map(|r| ImportLineError {
+                line: chunk[r.index].line,
+                error: r.error,
+            }));
+        }
+
+        errors.sort_by_key(|e| e.line);
+        let rejected_count = errors.len();
+        let errors_truncated = rejected_count > ImportReport::MAX_ERRORS;
+        errors.truncate(ImportReport::MAX_ERRORS);
+
+        tracing::info!(
+            device_id = %device_id,
+            total = total_rows,
+            inserted = inserted_count,
+            duplicates = duplicate_count,
+            rejected = rejected_count,
+            "导入历史电量数据"
+        );
+
+        Ok(ImportReport {
+            device_id,
+            total_rows,
+            inserted_count,
+            duplicate_count,
+            rejected_count,
+            errors,
+            errors_truncated,
+        })
     }
 
     /// 获取最新电量
-    pub async fn get_latest(&self, device_id: Uuid) -> Result<LatestBatteryResponse, AppError> {
+    ///
+    /// 指定组件时返回该组件的最新电量；多电池设备不指定组件时返回最近上报的一条，
+    /// 并附带各组件的最新电量
+    pub async fn get_latest(
+        &self,
+        device_id: Uuid,
+        component: Option<String>,
+    ) -> Result<LatestBatteryResponse, AppError> {
+        let component = normalize_component(component);
+
         // 先尝试从缓存获取
-        let cache_key = format!("battery:latest:{}", device_id);
+        let cache_key = latest_cache_key(device_id, component.as_deref());
         if let Some(cached) = self
             .redis_pool
             .get::<LatestBatteryResponse>(&cache_key)
@@ -124,34 +496,160 @@ impl BatteryService {
             return Ok(cached);
         }
 
+        let device = self.find_device(device_id).await?;
+        if component.is_some() {
+            device
+                .check_component(component.as_deref())
+                .map_err(AppError::ValidationError)?;
+        }
+
         // 从数据库查询
         let data = self
             .battery_repo
-            .query_latest(device_id)
+            .query_latest(device_id, component.as_deref())
             .await?
             .ok_or_else(|| AppError::NotFound("暂无电量数据".to_string()))?;
 
-        // 获取设备配置以判断低电量阈值
-        let config = self
-            .device_repo
-            .get_config(device_id)
+        let config = self.device_config(device_id).await?;
+        let mut response = self.build_latest(device_id, &data, &config).await?;
+        if component.is_none() && device.has_components() {
+            response.components = Some(self.component_batteries(&device, &config).await?);
+        }
+
+        // 更新缓存
+        self.redis_pool.set_ex(&cache_key, &response, 60).await?;
+
+        Ok(response)
+    }
+
+    /// 估算续航 / 充满时间（多电池设备需指定组件）
+    pub async fn get_estimate(
+        &self,
+        device_id: Uuid,
+        component: Option<String>,
+    ) -> Result<BatteryEstimateResponse, AppError> {
+        let component = self.battery_component(device_id, component).await?;
+
+        let data = self
+            .battery_repo
+            .query_latest(device_id, Some(component.as_str()))
             .await?
-            .unwrap_or_default();
+            .ok_or_else(|| AppError::NotFound("暂无电量数据".to_string()))?;
+
+        let config = self.device_config(device_id).await?;
+        let estimate = self.estimate(device_id, &data, &config).await?;
 
-        let response = LatestBatteryResponse {
+        Ok(BatteryEstimateResponse {
             device_id,
+            component: data.component_key().map(str::to_string),
             battery_level: data.battery_level,
             is_charging: data.is_charging,
             power_saving_mode: data.power_saving_mode,
             recorded_at: data.recorded_at,
-            is_low_battery: data.battery_level < config.low_battery_threshold,
-            is_critical: data.battery_level < config.critical_battery_threshold,
-        };
+            estimate,
+        })
+    }
 
-        // 更新缓存
-        self.redis_pool.set_ex(&cache_key, &response, 60).await?;
+    /// 分页查询充电会话
+    pub async fn list_charging_sessions(
+        &self,
+        device_id: Uuid,
+        query: ChargingSessionListQuery,
+    ) -> Result<PaginatedResponse<ChargingSession>, AppError> {
+        let mut query = query;
+        query.component = self.query_component(device_id, query.component).await?;
 
-        Ok(response)
+        let (sessions, total) = self.charging_repo.list_by_device(device_id, &query).await?;
+
+        let pagination = Pagination::new(query.page, query.page_size, total);
+
+        Ok(PaginatedResponse::new(sessions, pagination))
+    }
+
+    /// 生成电池健康报告（最近 `days` 天，多电池设备需指定组件）
+    pub async fn get_health(
+        &self,
+        device_id: Uuid,
+        days: i64,
+        component: Option<String>,
+    ) -> Result<BatteryHealthReport, AppError> {
+        let component = self.battery_component(device_id, component).await?;
+        let period_end = Utc::now();
+        let period_start = period_end - Duration::days(days);
+
+        let discharged_percent = self
+            .battery_repo
+            .discharged_percent(device_id, &component, period_start, period_end)
+            .await?;
+        let (charge_session_count, charged_percent) = self
+            .charging_repo
+            .charge_summary(device_id, &component, period_start, period_end)
+            .await?;
+
+        let full_charge_level = self
+            .charging_repo
+            .weekly_full_charge_level(device_id, &component, period_start, period_end)
+            .await?;
+        let voltage_at_reference = self
+            .battery_repo
+            .weekly_reference_voltage(device_id, &component, period_start, period_end)
+            .await?;
+
+        // 放电速率受省电模式影响，只比较放电时长最多的模式
+        let rates = self
+            .battery_repo
+            .weekly_discharge_rates(device_id, &component, period_start, period_end)
+            .await?;
+        let mut hours_by_mode: Vec<(PowerSavingMode, f64)> = Vec::new();
+        for rate in &rates {
+            match hours_by_mode
+                .iter_mut()
+                .find(|(mode, _)| *mode == rate.power_saving_mode)
+            {
+                Some((_, hours)) => *hours += rate.hours,
+                None => hours_by_mode.push((rate.power_saving_mode.clone(), rate.hours)),
+            }
+        }
+        let discharge_rate_mode = hours_by_mode
+            .into_iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(mode, _)| mode);
+        let discharge_points = rates
+            .into_iter()
+            .filter(|r| {
+                discharge_rate_mode.as_ref() == Some(&r.power_saving_mode)
+                    && r.hours >= HEALTH_MIN_DISCHARGE_HOURS
+            })
+            .map(|r| HealthTrendPoint {
+                bucket: r.bucket,
+                value: r.value,
+            })
+            .collect();
+
+        let full_charge_level = HealthTrend::fit(full_charge_level);
+        let discharge_rate = HealthTrend::fit(discharge_points);
+        let voltage_at_reference = HealthTrend::fit(voltage_at_reference);
+
+        let degradation_score =
+            degradation_score(&full_charge_level, &discharge_rate, &voltage_at_reference);
+
+        Ok(BatteryHealthReport {
+            device_id,
+            component: (!component.is_empty()).then_some(component),
+            period_start,
+            period_end,
+            // 采样间隔会漏记部分电量变化，两种统计取较大值
+            equivalent_cycles: discharged_percent.max(charged_percent) / 100.0,
+            discharged_percent,
+            charged_percent,
+            charge_session_count,
+            full_charge_level,
+            discharge_rate,
+            discharge_rate_mode,
+            voltage_at_reference,
+            degradation_score,
+            status: degradation_score.map(HealthStatus::from_score),
+        })
     }
 
     /// 查询历史数据
@@ -160,125 +658,889 @@ impl BatteryService {
         device_id: Uuid,
         request: BatteryQueryRequest,
     ) -> Result<Vec<BatteryData>, AppError> {
-        self.battery_repo
-            .query_by_time_range(device_id, &request)
-            .await
+        let mut request = request;
+        request.component = self.query_component(device_id, request.component).await?;
+
+        let Some(max_points) = request.max_points else {
+            return self
+                .battery_repo
+                .query_by_time_range(device_id, &request)
+                .await;
+        };
+
+        request
+            .validate_time_range()
+            .map_err(AppError::ValidationError)?;
+
+        let data = self
+            .battery_repo
+            .query_window(
+                device_id,
+                request.component.as_deref(),
+                request.start_time,
+                request.end_time,
+            )
+            .await?;
+
+        // 与分页查询保持一致，按记录时间倒序返回
+        let mut data = downsample_lttb(data, max_points as usize);
+        data.reverse();
+        Ok(data)
+    }
+
+    /// 游标分页查询历史数据（按记录时间倒序）
+    pub async fn get_history_page(
+        &self,
+        device_id: Uuid,
+        request: BatteryQueryRequest,
+    ) -> Result<CursorPage<BatteryData>, AppError> {
+        request
+            .validate_time_range()
+            .map_err(AppError::ValidationError)?;
+
+        if request.max_points.is_some() {
+            return Err(AppError::ValidationError(
+                "max_points 不能与游标分页同时使用".to_string(),
+            ));
+        }
+
+        let component = self.query_component(device_id, request.component).await?;
+
+        let after = request
+            .cursor
+            .as_deref()
+            .map(HistoryCursor::decode)
+            .transpose()
+            .map_err(AppError::ValidationError)?;
+
+        // 多取一条判断是否还有下一页
+        let mut items = self
+            .battery_repo
+            .query_keyset(
+                device_id,
+                component.as_deref(),
+                request.start_time,
+                request.end_time,
+                after.as_ref(),
+                request.limit + 1,
+                false,
+            )
+            .await?;
+
+        let next_cursor = if items.len() as i64 > request.limit {
+            items.truncate(request.limit as usize);
+            items.last().map(|d| HistoryCursor::from_data(d).encode())
+        } else {
+            None
+        };
+
+        Ok(CursorPage::new(items, next_cursor))
+    }
+
+    /// 流式查询历史数据（按记录时间升序，逐批返回）
+    ///
+    /// 每批通过游标查询读取，不占用长事务，也不受分页查询 30 天的限制
+    pub async fn stream_history(
+        &self,
+        device_id: Uuid,
+        request: BatteryStreamRequest,
+    ) -> Result<BoxStream<'static, Result<Vec<BatteryData>, AppError>>, AppError> {
+        let mut request = request;
+        request.component = self.query_component(device_id, request.component).await?;
+
+        self.export_history(vec![device_id], request)
+    }
+
+    /// 依次流式查询多台设备的历史数据（每台设备内按记录时间升序，指定组件时只返回该组件的数据）
+    pub fn export_history(
+        &self,
+        device_ids: Vec<Uuid>,
+        request: BatteryStreamRequest,
+    ) -> Result<BoxStream<'static, Result<Vec<BatteryData>, AppError>>, AppError> {
+        request
+            .validate_time_range()
+            .map_err(AppError::ValidationError)?;
+
+        let repo = self.battery_repo.clone();
+        let (start_time, end_time) = (request.start_time, request.end_time);
+        let component = request.component;
+
+        let stream = stream::iter(device_ids).flat_map(move |device_id| {
+            let repo = repo.clone();
+            let component = component.clone();
+
+            // 状态为下一批的起始游标，None 表示已读取完毕
+            stream::try_unfold(Some(None), move |after: Option<Option<HistoryCursor>>| {
+                let repo = repo.clone();
+                let component = component.clone();
+                async move {
+                    let Some(after) = after else {
+                        return Ok(None);
+                    };
+
+                    let batch = repo
+                        .query_keyset(
+                            device_id,
+                            component.as_deref(),
+                            start_time,
+                            end_time,
+                            after.as_ref(),
+                            HISTORY_STREAM_BATCH_SIZE,
+                            true,
+                        )
+                        .await?;
+                    if batch.is_empty() {
+                        return Ok(None);
+                    }
+
+                    let next = if batch.len() as i64 == HISTORY_STREAM_BATCH_SIZE {
+                        batch.last().map(|d| Some(HistoryCursor::from_data(d)))
+                    } else {
+                        None
+                    };
+                    Ok(Some((batch, next)))
+                }
+            })
+        });
+
+        Ok(stream.boxed())
     }
 
     /// 获取聚合统计
+    ///
+    /// `metric_keys` 不为空时同时按时间桶聚合这些自定义指标（须为设备类型已注册的指标）。
+    /// 自定义指标没有连续聚合视图，直接聚合原始数据，时间范围受同样的限制。
+    /// 不指定组件时合并所有组件的数据
+    #[allow(clippy::too_many_arguments)]
     pub async fn get_aggregated(
         &self,
         device_id: Uuid,
+        component: Option<String>,
         start_time: DateTime<Utc>,
         end_time: DateTime<Utc>,
         interval: AggregateInterval,
+        fill: GapFill,
+        metric_keys: &[String],
     ) -> Result<Vec<BatteryAggregatePoint>, AppError> {
-        self.battery_repo
-            .aggregate_by_interval(device_id, start_time, end_time, &interval)
-            .await
+        interval
+            .validate_time_range(start_time, end_time, self.max_aggregate_buckets)
+            .map_err(AppError::ValidationError)?;
+        let component = self.query_component(device_id, component).await?;
+
+        if !metric_keys.is_empty() {
+            if (end_time - start_time).num_days() > AggregateInterval::MAX_RAW_RANGE_DAYS {
+                return Err(AppError::ValidationError(format!(
+                    "聚合自定义指标时查询时间范围不能超过 {} 天",
+                    AggregateInterval::MAX_RAW_RANGE_DAYS
+                )));
+            }
+
+            let device = self.find_device(device_id).await?;
+            let definitions = self.metric_repo.list(Some(&device.device_type)).await?;
+            if let Some(key) = metric_keys
+                .iter()
+                .find(|key| !definitions.iter().any(|d| d.key == **key))
+            {
+                return Err(AppError::ValidationError(format!("未注册的指标: {}", key)));
+            }
+        }
+
+        let mut points = self
+            .battery_repo
+            .aggregate_by_interval(
+                device_id,
+                component.as_deref(),
+                start_time,
+                end_time,
+                &interval,
+                fill,
+            )
+            .await?;
+        if metric_keys.is_empty() {
+            return Ok(points);
+        }
+
+        let mut buckets: HashMap<DateTime<Utc>, BTreeMap<String, MetricAggregate>> = HashMap::new();
+        for (bucket, key, aggregate) in self
+            .battery_repo
+            .aggregate_metrics(
+                device_id,
+                component.as_deref(),
+                start_time,
+                end_time,
+                &interval,
+                metric_keys,
+            )
+            .await?
+        {
+            buckets.entry(bucket).or_default().insert(key, aggregate);
+        }
+        for point in &mut points {
+            point.metrics = Some(buckets.remove(&point.bucket).unwrap_or_default());
+        }
+
+        Ok(points)
     }
 
-    /// 获取统计信息
+    /// 获取统计信息（不指定组件时合并所有组件）
     pub async fn get_stats(
         &self,
         device_id: Uuid,
+        component: Option<String>,
         start_time: DateTime<Utc>,
         end_time: DateTime<Utc>,
     ) -> Result<BatteryStatsResponse, AppError> {
+        let component = self.query_component(device_id, component).await?;
+
         self.battery_repo
-            .get_stats(device_id, start_time, end_time)
+            .get_stats(device_id, component.as_deref(), start_time, end_time)
             .await
     }
 
     /// 更新最新电量缓存
+    ///
+    /// 多电池设备更新组件的缓存，并清除设备的缓存（其中各组件的最新电量在下次查询时重新生成）。
+    /// 已有记录时间更新的数据时（迟到的乱序样本）不更新缓存，返回 `None`
     async fn update_latest_cache(
         &self,
         device_id: Uuid,
         data: &BatteryData,
-    ) -> Result<(), AppError> {
-        let config = self
-            .device_repo
-            .get_config(device_id)
+        config: &DeviceConfig,
+    ) -> Result<Option<LatestBatteryResponse>, AppError> {
+        let cache_key = latest_cache_key(device_id, None);
+        let component_key = data
+            .component_key()
+            .map(|component| latest_cache_key(device_id, Some(component)));
+
+        // 先比较缓存，缓存不存在时查询该组件记录时间最新的数据
+        let latest_key = component_key.as_deref().unwrap_or(&cache_key);
+        let superseded = match self
+            .redis_pool
+            .get::<LatestBatteryResponse>(latest_key)
             .await?
-            .unwrap_or_default();
+        {
+            Some(cached) => cached.recorded_at > data.recorded_at,
+            None => self
+                .battery_repo
+                .query_latest(device_id, Some(data.component.as_str()))
+                .await?
+                .is_some_and(|latest| latest.recorded_at > data.recorded_at),
+        };
+        if superseded {
+            tracing::debug!(device_id = %device_id, data_id = %data.id, "乱序样本，不更新最新电量");
+            return Ok(None);
+        }
+
+        let response = self.build_latest(device_id, data, config).await?;
+        match component_key {
+            Some(component_key) => {
+                self.redis_pool
+                    .set_ex(&component_key, &response, 300)
+                    .await?;
+                self.redis_pool.del(&cache_key).await?;
+            }
+            None => self.redis_pool.set_ex(&cache_key, &response, 300).await?,
+        }
+
+        Ok(Some(response))
+    }
+
+    /// 多电池设备各组件的最新电量（阈值取设备配置）
+    async fn component_batteries(
+        &self,
+        device: &Device,
+        config: &DeviceConfig,
+    ) -> Result<Vec<ComponentBattery>, AppError> {
+        let latest = self
+            .battery_repo
+            .query_latest_components(device.id, &device.components)
+            .await?;
+
+        Ok(latest
+            .into_iter()
+            .map(|data| ComponentBattery {
+                is_low_battery: data.battery_level < config.low_battery_threshold,
+                is_critical: data.battery_level < config.critical_battery_threshold,
+                component: data.component,
+                battery_level: data.battery_level,
+                is_charging: data.is_charging,
+                recorded_at: data.recorded_at,
+            })
+            .collect())
+    }
 
-        let response = LatestBatteryResponse {
+    /// 构建最新电量响应（阈值判断与续航估算）
+    async fn build_latest(
+        &self,
+        device_id: Uuid,
+        data: &BatteryData,
+        config: &DeviceConfig,
+    ) -> Result<LatestBatteryResponse, AppError> {
+        let estimate = self.cached_estimate(device_id, data, config).await?;
+
+        Ok(LatestBatteryResponse {
             device_id,
+            component: data.component_key().map(str::to_string),
             battery_level: data.battery_level,
             is_charging: data.is_charging,
             power_saving_mode: data.power_saving_mode.clone(),
             recorded_at: data.recorded_at,
             is_low_battery: data.battery_level < config.low_battery_threshold,
             is_critical: data.battery_level < config.critical_battery_threshold,
+            estimate,
+            metrics: data.metrics.clone(),
+            components: None,
+        })
+    }
+
+    /// 基于估算窗口内同一组件的历史数据估算续航 / 充满时间
+    async fn estimate(
+        &self,
+        device_id: Uuid,
+        latest: &BatteryData,
+        config: &DeviceConfig,
+    ) -> Result<Option<BatteryEstimate>, AppError> {
+        let samples = self
+            .battery_repo
+            .query_window(
+                device_id,
+                Some(latest.component.as_str()),
+                latest.recorded_at - self.estimate_window,
+                latest.recorded_at,
+            )
+            .await?;
+
+        Ok(estimate_battery(
+            &samples,
+            latest,
+            config.critical_battery_threshold,
+            ESTIMATE_MIN_MINUTES,
+        ))
+    }
+
+    /// 最新电量附带的续航估算
+    ///
+    /// 估算速率按设备组件缓存 `estimate_refresh_seconds` 秒（充电状态或省电模式变化时重新估算），
+    /// 缓存期间只按最新电量重新推算时间，避免每次上报都扫描估算窗口
+    async fn cached_estimate(
+        &self,
+        device_id: Uuid,
+        latest: &BatteryData,
+        config: &DeviceConfig,
+    ) -> Result<Option<BatteryEstimate>, AppError> {
+        let cache_key = format!("battery:estimate_rate:{}:{}", device_id, latest.component);
+        let cached = self
+            .redis_pool
+            .get::<CachedEstimateRate>(&cache_key)
+            .await?
+            .filter(|c| {
+                c.is_charging == latest.is_charging
+                    && c.power_saving_mode == latest.power_saving_mode
+            });
+
+        let rate = match cached {
+            Some(cached) => cached.rate,
+            None => {
+                let samples = self
+                    .battery_repo
+                    .query_window(
+                        device_id,
+                        Some(latest.component.as_str()),
+                        latest.recorded_at - self.estimate_window,
+                        latest.recorded_at,
+                    )
+                    .await?;
+                let rate = estimate_battery_rate(&samples, latest, ESTIMATE_MIN_MINUTES);
+
+                // 数据不足（速率为空）同样缓存，避免反复扫描
+                let entry = CachedEstimateRate {
+                    is_charging: latest.is_charging,
+                    power_saving_mode: latest.power_saving_mode.clone(),
+                    rate,
+                };
+                self.redis_pool
+                    .set_ex(&cache_key, &entry, self.estimate_refresh_seconds)
+                    .await?;
+                entry.rate
+            }
         };
 
-        let cache_key = format!("battery:latest:{}", device_id);
-        self.redis_pool.set_ex(&cache_key, &response, 300).await?;
+        Ok(rate.map(|(rate, mode_matched)| {
+            BatteryEstimate::from_rate(
+                rate,
+                mode_matched,
+                latest,
+                config.critical_battery_threshold,
+            )
+        }))
+    }
+
+    /// 根据新写入的数据划分充电会话（`samples` 需按记录时间升序，多电池设备按组件分别划分）
+    ///
+    /// 会话仅用于统计，失败时记录日志，不影响上报
+    async fn track_charging(&self, device_id: Uuid, samples: &[BatteryData]) {
+        let mut by_component: BTreeMap<&str, Vec<BatteryData>> = BTreeMap::new();
+        for data in samples {
+            by_component
+                .entry(data.component.as_str())
+                .or_default()
+                .push(data.clone());
+        }
+
+        let result = async {
+            for (component, samples) in &by_component {
+                let last = self.charging_repo.find_latest(device_id, component).await?;
+                for session in track_charging_sessions(last, samples) {
+                    self.charging_repo.save(&session).await?;
+                }
+            }
+            Ok::<_, AppError>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!(device_id = %device_id, error = %e, "充电会话更新失败");
+        }
+    }
+
+    /// 更新设备在线状态（离线预警在预警检查中自动解决）
+    async fn mark_online(&self, device_id: Uuid) -> Result<(), AppError> {
+        if self.device_repo.update_last_seen(device_id).await? {
+            tracing::info!(device_id = %device_id, "设备恢复在线");
+        }
 
         Ok(())
     }
 
-    /// 检查预警
-    async fn check_alerts(&self, device_id: Uuid, data: &BatteryData) -> Result<(), AppError> {
-        // 获取设备信息（需要 owner_id 来触发预警）
-        let device = match self.device_repo.find_by_id(device_id).await? {
-            Some(d) => d,
-            None => {
-                tracing::warn!(device_id = %device_id, "设备不存在，跳过预警检查");
-                return Ok(());
+    /// 设备时钟状态
+    ///
+    /// 提供设备时间时先记录本次测量的偏差，否则沿用最近测量的偏差
+    async fn device_clock(
+        &self,
+        device: &Device,
+        config: &DeviceConfig,
+        device_time: Option<DateTime<Utc>>,
+        received_at: DateTime<Utc>,
+    ) -> Result<DeviceClock, AppError> {
+        let offset_seconds = match device_time {
+            Some(device_time) => {
+                self.device_repo
+                    .record_clock_offset(
+                        device.id,
+                        clock_offset_seconds(device_time, received_at),
+                        self.clock_offset_window,
+                    )
+                    .await?
             }
+            None => device.clock_offset_seconds,
+        };
+
+        Ok(DeviceClock {
+            offset_seconds,
+            correction: config.clock_correction,
+            tolerance_seconds: self.clock_skew_tolerance_seconds,
+        })
+    }
+
+    /// 获取校验上报数据所需的设备组件和指标定义（均未携带自定义指标时不查询指标定义）
+    async fn report_schema<'a, 'd>(
+        &self,
+        device: &'d Device,
+        mut requests: impl Iterator<Item = &'a BatteryReportRequest>,
+    ) -> Result<ReportSchema<'d>, AppError> {
+        let definitions = if requests.any(BatteryReportRequest::has_metrics) {
+            self.metric_repo.list(Some(&device.device_type)).await?
+        } else {
+            Vec::new()
         };
 
+        Ok(ReportSchema {
+            device,
+            definitions,
+        })
+    }
+
+    /// 获取设备
+    async fn find_device(&self, device_id: Uuid) -> Result<Device, AppError> {
+        self.device_repo
+            .find_by_id(device_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("设备不存在".to_string()))
+    }
+
+    /// 获取设备配置（未配置时使用默认配置）
+    async fn device_config(&self, device_id: Uuid) -> Result<DeviceConfig, AppError> {
+        Ok(self
+            .device_repo
+            .get_config(device_id)
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// 校验查询参数中的组件（须为设备声明的组件），`None` 表示所有组件
+    async fn query_component(
+        &self,
+        device_id: Uuid,
+        component: Option<String>,
+    ) -> Result<Option<String>, AppError> {
+        let component = normalize_component(component);
+        if component.is_some() {
+            self.find_device(device_id)
+                .await?
+                .check_component(component.as_deref())
+                .map_err(AppError::ValidationError)?;
+        }
+
+        Ok(component)
+    }
+
+    /// 确定针对单个电池的查询所用的组件（多电池设备必须指定，单电池设备为空字符串）
+    async fn battery_component(
+        &self,
+        device_id: Uuid,
+        component: Option<String>,
+    ) -> Result<String, AppError> {
+        let component = normalize_component(component);
+        self.find_device(device_id)
+            .await?
+            .check_component(component.as_deref())
+            .map_err(AppError::ValidationError)?;
+
+        Ok(component.unwrap_or_default())
+    }
+
+    /// 推送最新电量给订阅者
+    async fn push_latest(&self, device_id: Uuid, latest: &LatestBatteryResponse) {
+        if let Some(ref push_sender) = self.push_sender {
+            push_sender.push_battery(device_id, latest).await;
+        }
+    }
+
+    /// 检查预警
+    ///
+    /// 数据按记录时间升序逐条触发预警；生效规则和快速耗电的滑动窗口数据整批只查询一次，
+    /// 自动解决按每个组件的最终状态（记录时间最新的数据）整批只执行一次
+    async fn check_alerts(
+        &self,
+        device: &Device,
+        config: &DeviceConfig,
+        samples: &[BatteryData],
+        notify_cutoff: Option<DateTime<Utc>>,
+    ) -> Result<(), AppError> {
+        // 需要 owner_id 来触发预警
         let user_id = match device.owner_id {
             Some(uid) => uid,
             None => {
-                tracing::debug!(device_id = %device_id, "设备无所有者，跳过预警检查");
+                tracing::debug!(device_id = %device.id, "设备无所有者，跳过预警检查");
                 return Ok(());
             }
         };
 
-        // 获取设备配置
-        let config = self
-            .device_repo
-            .get_config(device_id)
-            .await?
-            .unwrap_or_default();
+        // 生效规则整批只查询一次，只检查有生效规则的自定义指标
+        let rules = self.alert_service.device_rules(device).await?;
+        let metric_keys = rules.metric_keys();
 
-        // 检查低电量预警
-        if data.battery_level < config.critical_battery_threshold && !data.is_charging {
-            self.alert_service
+        let ordered = alert_evaluation_order(samples, notify_cutoff);
+        let drain_history = self
+            .rapid_drain_history(device, &rules, config, &ordered)
+            .await?;
+
+        for &(data, notify) in &ordered {
+            let check = AlertCheck {
+                rules: &rules,
+                at: data.recorded_at,
+                notify,
+            };
+            let history = drain_history
+                .as_ref()
+                .map(|h| h.get(&data.component).map_or(&[][..], Vec::as_slice));
+            self.check_sample_alerts(device, config, data, history, &check)
+                .await?;
+            self.check_metric_alerts(device, data, &metric_keys, &check)
+                .await?;
+        }
+
+        // 自动解决已恢复正常的预警（以每个组件的最终状态判断）
+        let sorted: Vec<&BatteryData> = ordered.iter().map(|&(d, _)| d).collect();
+        for data in latest_by_component(sorted.iter().copied()) {
+            let check = AlertCheck {
+                rules: &rules,
+                at: data.recorded_at,
+                notify: notify_cutoff.is_none_or(|cutoff| data.recorded_at >= cutoff),
+            };
+            self.resolve_component_alerts(
+                device,
+                user_id,
+                config,
+                &sorted,
+                data,
+                &metric_keys,
+                &check,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// 快速耗电检查所需的滑动窗口数据，按组件分组（升序）
+    ///
+    /// 没有生效的快速耗电规则或整批都在充电时不查询，返回 `None`
+    async fn rapid_drain_history(
+        &self,
+        device: &Device,
+        rules: &DeviceAlertRules,
+        config: &DeviceConfig,
+        ordered: &[(&BatteryData, bool)],
+    ) -> Result<Option<HashMap<String, Vec<BatteryData>>>, AppError> {
+        if rules.matching(&AlertType::RapidDrain, None).is_none()
+            || ordered.iter().all(|(d, _)| d.is_charging)
+        {
+            return Ok(None);
+        }
+        let (Some(&(first, _)), Some(&(last, _))) = (ordered.first(), ordered.last()) else {
+            return Ok(None);
+        };
+
+        let window = Duration::minutes(config.rapid_drain_window_minutes as i64);
+        let samples = self
+            .battery_repo
+            .query_window(
+                device.id,
+                None,
+                first.recorded_at - window,
+                last.recorded_at,
+            )
+            .await?;
+
+        let mut history: HashMap<String, Vec<BatteryData>> = HashMap::new();
+        for data in samples {
+            history
+                .entry(data.component.clone())
+                .or_default()
+                .push(data);
+        }
+
+        Ok(Some(history))
+    }
+
+    /// 检查单条数据的预警（`drain_history` 为该组件的快速耗电窗口数据，`None` 表示不检查）
+    async fn check_sample_alerts(
+        &self,
+        device: &Device,
+        config: &DeviceConfig,
+        data: &BatteryData,
+        drain_history: Option<&[BatteryData]>,
+        check: &AlertCheck<'_>,
+    ) -> Result<(), AppError> {
+        let component = data.component_key();
+
+        // 检查电量预警（阈值由匹配的规则或设备配置决定，临界优先于低电量）
+        if !data.is_charging {
+            let level = data.battery_level as f64;
+            let critical = self
+                .alert_service
                 .trigger_critical_battery(
-                    device_id,
-                    user_id,
-                    data.battery_level as f64,
+                    device,
+                    component,
+                    level,
                     config.critical_battery_threshold as f64,
+                    check,
                 )
                 .await?;
-        } else if data.battery_level < config.low_battery_threshold && !data.is_charging {
+
+            if !critical.is_breached() {
+                self.alert_service
+                    .trigger_low_battery(
+                        device,
+                        component,
+                        level,
+                        config.low_battery_threshold as f64,
+                        check,
+                    )
+                    .await?;
+            }
+
+            // 检查快速耗电预警
+            if let Some(history) = drain_history {
+                self.check_rapid_drain(device, data, history, config, check)
+                    .await?;
+            }
+        }
+
+        // 检查温度预警
+        if let Some(temp) = data.temperature {
             self.alert_service
-                .trigger_low_battery(
-                    device_id,
-                    user_id,
-                    data.battery_level as f64,
-                    config.low_battery_threshold as f64,
+                .trigger_high_temperature(
+                    device,
+                    component,
+                    temp,
+                    config.high_temperature_threshold,
+                    check,
                 )
                 .await?;
         }
 
-        // 检查温度预警
-        if let Some(temp) = data.temperature {
-            if temp > config.high_temperature_threshold {
+        Ok(())
+    }
+
+    /// 检查单条数据的自定义指标预警
+    async fn check_metric_alerts(
+        &self,
+        device: &Device,
+        data: &BatteryData,
+        metric_keys: &[String],
+        check: &AlertCheck<'_>,
+    ) -> Result<(), AppError> {
+        let Some(ref metrics) = data.metrics else {
+            return Ok(());
+        };
+
+        let component = data.component_key();
+        for key in metric_keys {
+            if let Some(value) = metric_value(metrics, key) {
                 self.alert_service
-                    .trigger_high_temperature(
-                        device_id,
-                        user_id,
-                        temp,
-                        config.high_temperature_threshold,
-                    )
+                    .trigger_custom_metric(device, component, key, value, check)
                     .await?;
             }
         }
 
         Ok(())
     }
+
+    /// 按组件的最终状态自动解决已恢复正常的预警
+    ///
+    /// `latest` 为该组件记录时间最新的数据；自定义指标取整批中每个指标最后一次上报的值
+    #[allow(clippy::too_many_arguments)]
+    async fn resolve_component_alerts(
+        &self,
+        device: &Device,
+        user_id: Uuid,
+        config: &DeviceConfig,
+        sorted: &[&BatteryData],
+        latest: &BatteryData,
+        metric_keys: &[String],
+        check: &AlertCheck<'_>,
+    ) -> Result<(), AppError> {
+        let component = latest.component_key();
+        self.alert_service
+            .resolve_recovered(
+                device.id,
+                component,
+                user_id,
+                latest.battery_level as f64,
+                latest.temperature,
+                config,
+                check,
+            )
+            .await?;
+
+        let values: Vec<(String, f64)> = metric_keys
+            .iter()
+            .filter_map(|key| {
+                sorted
+                    .iter()
+                    .rev()
+                    .filter(|d| d.component == latest.component)
+                    .find_map(|d| d.metrics.as_ref().and_then(|m| metric_value(m, key)))
+                    .map(|v| (key.clone(), v))
+            })
+            .collect();
+        if !values.is_empty() {
+            self.alert_service
+                .resolve_recovered_metrics(device.id, component, user_id, &values, check)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// 检查快速耗电（同一组件滑动窗口内的放电速率，忽略充电区间）
+    ///
+    /// `history` 为该组件整批共用的窗口数据（升序），按本条数据的记录时间截取滑动窗口
+    async fn check_rapid_drain(
+        &self,
+        device: &Device,
+        data: &BatteryData,
+        history: &[BatteryData],
+        config: &DeviceConfig,
+        check: &AlertCheck<'_>,
+    ) -> Result<(), AppError> {
+        let window = Duration::minutes(config.rapid_drain_window_minutes as i64);
+        let samples = window_ending_at(history, data.recorded_at, window);
+
+        // 放电时长至少覆盖窗口的一半，避免少量样本的抖动误报
+        let min_minutes =
+            (config.rapid_drain_window_minutes as i64 / 2).max(RAPID_DRAIN_MIN_MINUTES);
+        let rate = match discharge_rate_per_hour(samples, min_minutes) {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+
+        self.alert_service
+            .trigger_rapid_drain(
+                device,
+                data.component_key(),
+                rate,
+                config.rapid_drain_threshold,
+                check,
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// 缓存的续航估算速率（只适用于相同的充电状态和省电模式）
+#[derive(Serialize, Deserialize)]
+struct CachedEstimateRate {
+    is_charging: bool,
+    power_saving_mode: PowerSavingMode,
+    /// 估算速率及是否按省电模式匹配，数据不足时为空
+    rate: Option<(RateEstimate, bool)>,
+}
+
+/// 校验上报数据所需的设备信息
+struct ReportSchema<'a> {
+    device: &'a Device,
+    /// 设备类型的指标定义
+    definitions: Vec<MetricDefinition>,
+}
+
+/// 校验单条上报数据，返回错误描述
+///
+/// 电池组件须为设备声明的组件，自定义指标按设备类型的指标定义校验
+fn validate_report(request: &BatteryReportRequest, schema: &ReportSchema) -> Result<(), String> {
+    request.validate().map_err(|e| e.to_string())?;
+
+    schema
+        .device
+        .check_component(request.component.as_deref())?;
+
+    if let Some(recorded_at) = request.recorded_at {
+        if recorded_at > Utc::now() {
+            return Err("记录时间不能是未来时间".to_string());
+        }
+    }
+
+    if let Some(ref metrics) = request.metrics {
+        validate_metrics(&schema.definitions, metrics)?;
+    }
+
+    Ok(())
+}
+
+/// 去除查询参数中组件标识的空白，空字符串视为未指定
+fn normalize_component(component: Option<String>) -> Option<String> {
+    component
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+}
+
+/// 最新电量缓存键（多电池设备的组件单独缓存）
+fn latest_cache_key(device_id: Uuid, component: Option<&str>) -> String {
+    match component {
+        Some(component) => format!("battery:latest:{}:{}", device_id, component),
+        None => format!("battery:latest:{}", device_id),
+    }
 }
@@ -0,0 +1,87 @@
+//! 设备在线状态检测服务
+//!
+//! 后台定时扫描超过「上报间隔 × 宽限倍数」未上报的在线设备，
+//! 将其标记为离线并触发离线预警。设备重新上报时由电量服务恢复在线状态。
+
+use crate::config::Settings;
+use crate::errors::AppError;
+use crate::repositories::DeviceRepository;
+use crate::services::AlertService;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 设备在线状态检测服务
+pub struct DeviceMonitorService {
+    device_repo: DeviceRepository,
+    alert_service: Arc<AlertService>,
+    check_interval: Duration,
+    grace_multiplier: f64,
+}
+
+impl DeviceMonitorService {
+    pub fn new(
+        settings: &Settings,
+        device_repo: DeviceRepository,
+        alert_service: Arc<AlertService>,
+    ) -> Self {
+        let monitor = &settings.device_monitor;
+
+        Self {
+            device_repo,
+            alert_service,
+            check_interval: Duration::from_secs(monitor.offline_check_interval_seconds.max(1)),
+            grace_multiplier: monitor.offline_grace_multiplier,
+        }
+    }
+
+    /// 启动后台检测任务
+    pub fn start(self: &Arc<Self>) {
+        let monitor = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(monitor.check_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+                if let Err(e) = monitor.check_offline_devices().await {
+                    tracing::error!(error = %e, "设备离线检测失败");
+                }
+            }
+        });
+    }
+
+    /// 执行一次离线检测
+    ///
+    /// 超时判断在查询中完成，只返回超时的设备；状态切换在单条 UPDATE 中完成，
+    /// 多实例并发检测时每台设备只会被标记一次。返回本次被标记为离线的设备数
+    pub async fn check_offline_devices(&self) -> Result<usize, AppError> {
+        let stale = self
+            .device_repo
+            .find_stale_online(self.grace_multiplier)
+            .await?;
+        if stale.is_empty() {
+            return Ok(0);
+        }
+
+        let devices = self.device_repo.mark_offline(&stale).await?;
+
+        for device in &devices {
+            tracing::info!(
+                device_id = %device.id,
+                last_seen_at = ?device.last_seen_at,
+                "设备超时未上报，已标记为离线"
+            );
+
+            if device.owner_id.is_none() {
+                continue;
+            }
+
+            // 单台设备预警失败不影响其他设备
+            if let Err(e) = self.alert_service.trigger_device_offline(device).await {
+                tracing::error!(error = %e, device_id = %device.id, "离线预警触发失败");
+            }
+        }
+
+        Ok(devices.len())
+    }
+}
@@ -31,6 +31,10 @@ impl DeviceService {
         request: CreateDeviceRequest,
         owner_id: Option<Uuid>,
     ) -> Result<CreateDeviceResponse, AppError> {
+        if let Some(gateway_id) = request.gateway_id {
+            self.check_gateway(None, owner_id, gateway_id).await?;
+        }
+
         // 生成 API Key（使用统一的 token 模块）
         let token_result = generate_token(TokenType::DeviceApiKeyLive)?;
 
@@ -94,7 +98,12 @@ impl DeviceService {
     /// 更新设备
     pub async fn update(&self, id: Uuid, request: UpdateDeviceRequest) -> Result<Device, AppError> {
         // 确保设备存在
-        self.get_by_id(id).await?;
+        let device = self.get_by_id(id).await?;
+
+        if let Some(Some(gateway_id)) = request.gateway_id {
+            self.check_gateway(Some(id), device.owner_id, gateway_id)
+                .await?;
+        }
 
         // 更新设备
         let device = self.device_repo.update(id, &request).await?;
@@ -204,6 +213,48 @@ impl DeviceService {
         Ok(token_result.token)
     }
 
+    /// 校验网关关联
+    ///
+    /// 网关须为同一用户的设备，且只有一层：网关不能是子设备，已有子设备的设备不能挂到网关下
+    async fn check_gateway(
+        &self,
+        device_id: Option<Uuid>,
+        owner_id: Option<Uuid>,
+        gateway_id: Uuid,
+    ) -> Result<(), AppError> {
+        if device_id == Some(gateway_id) {
+            return Err(AppError::ValidationError(
+                "设备不能作为自己的网关".to_string(),
+            ));
+        }
+
+        let gateway = self
+            .device_repo
+            .find_by_id(gateway_id)
+            .await?
+            .ok_or_else(|| AppError::ValidationError("网关设备不存在".to_string()))?;
+
+        if gateway.owner_id != owner_id {
+            return Err(AppError::ValidationError(
+                "网关与子设备必须属于同一用户".to_string(),
+            ));
+        }
+        if gateway.gateway_id.is_some() {
+            return Err(AppError::ValidationError(
+                "网关设备不能是其他网关的子设备".to_string(),
+            ));
+        }
+        if let Some(device_id) = device_id {
+            if self.device_repo.has_children(device_id).await? {
+                return Err(AppError::ValidationError(
+                    "已有子设备的网关不能再关联到其他网关".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     /// 清除设备相关缓存
     async fn invalidate_cache(&self, device_id: Uuid) -> Result<(), AppError> {
         let keys = vec![
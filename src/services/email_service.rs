@@ -305,6 +305,78 @@ impl EmailService {
     }
 }
 
+/// 发送预警解决通知邮件的参数
+#[derive(Debug, Clone, Copy)]
+pub struct AlertResolvedParams<'a> {
+    pub to_email: &'a str,
+    pub alert_type: &'a str,
+    pub message: &'a str,
+    pub device_name: &'a str,
+    pub triggered_at: &'a str,
+    pub resolved_at: &'a str,
+}
+
+impl EmailService {
+    /// 发送预警解决通知邮件
+    pub async fn send_alert_resolved_notification(
+        &self,
+        params: AlertResolvedParams<'_>,
+    ) -> Result<(), AppError> {
+        let mailer = self
+            .mailer
+            .as_ref()
+            .ok_or_else(|| AppError::ConfigError("邮件服务未启用".to_string()))?;
+
+        let from = format!("{} <{}>", self.settings.from_name, self.settings.from_email);
+        let subject = format!("【Zinnia】✅ 预警已解决 - {}", params.alert_type);
+
+        let body = format!(
+            r#"您好！
+
+您设备的预警已自动解决：
+
+📱 设备名称：{}
+⚠️  预警类型：{}
+📝 原预警信息：{}
+
+• 触发时间：{}
+• 解决时间：{}
+
+设备指标已恢复正常，无需处理。
+
+此邮件由系统自动发送，请勿直接回复。
+
+——Zinnia 团队"#,
+            params.device_name,
+            params.alert_type,
+            params.message,
+            params.triggered_at,
+            params.resolved_at,
+        );
+
+        let email = Message::builder()
+            .from(
+                from.parse()
+                    .map_err(|e| AppError::ConfigError(format!("发件人地址无效: {}", e)))?,
+            )
+            .to(params
+                .to_email
+                .parse()
+                .map_err(|_| AppError::ValidationError("收件人邮箱格式无效".to_string()))?)
+            .subject(subject)
+            .body(body)
+            .map_err(|e| AppError::InternalError(format!("邮件构建失败: {}", e)))?;
+
+        mailer.send(email).await.map_err(|e| {
+            tracing::error!(error = %e, to = %params.to_email, "预警解决邮件发送失败");
+            AppError::InternalError("邮件发送失败，请稍后重试".to_string())
+        })?;
+
+        tracing::info!(to = %params.to_email, alert_type = %params.alert_type, "预警解决邮件已发送");
+        Ok(())
+    }
+}
+
 /// 根据预警类型和级别获取建议
 fn get_alert_suggestion(alert_type: &str, level: &str) -> &'static str {
     match (alert_type, level) {
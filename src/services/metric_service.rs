@@ -0,0 +1,122 @@
+//! 自定义指标业务服务
+
+use crate::errors::AppError;
+use crate::models::{
+    CreateMetricDefinitionRequest, MetricDefinition, MetricDefinitionQuery,
+    UpdateMetricDefinitionRequest,
+};
+use crate::repositories::MetricRepository;
+use uuid::Uuid;
+
+/// 自定义指标业务服务
+pub struct MetricService {
+    metric_repo: MetricRepository,
+}
+
+impl MetricService {
+    pub fn new(metric_repo: MetricRepository) -> Self {
+        Self { metric_repo }
+    }
+
+    /// 查询指标定义
+    pub async fn list(
+        &self,
+        query: MetricDefinitionQuery,
+    ) -> Result<Vec<MetricDefinition>, AppError> {
+        let device_type = query
+            .device_type
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty());
+
+        self.metric_repo.list(device_type).await
+    }
+
+    /// 注册指标定义（同一设备类型下指标键唯一）
+    pub async fn create(
+        &self,
+        request: CreateMetricDefinitionRequest,
+    ) -> Result<MetricDefinition, AppError> {
+        let mut request = request;
+        request.device_type = request.device_type.trim().to_string();
+        if request.device_type.is_empty() {
+            return Err(AppError::ValidationError("设备类型不能为空".to_string()));
+        }
+        validate_range(request.min_value, request.max_value)?;
+
+        if self
+            .metric_repo
+            .exists(&request.device_type, &request.key)
+            .await?
+        {
+            return Err(AppError::Conflict(format!(
+                "设备类型 {} 已注册指标 {}",
+                request.device_type, request.key
+            )));
+        }
+
+        let definition = self.metric_repo.create(&request).await?;
+
+        tracing::info!(
+            device_type = %definition.device_type,
+            key = %definition.key,
+            "注册自定义指标"
+        );
+
+        Ok(definition)
+    }
+
+    /// 更新指标定义（只修改单位、描述和取值范围，不影响已上报的数据）
+    pub async fn update(
+        &self,
+        id: Uuid,
+        request: UpdateMetricDefinitionRequest,
+    ) -> Result<MetricDefinition, AppError> {
+        let mut definition = self
+            .metric_repo
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("指标定义不存在".to_string()))?;
+
+        if let Some(unit) = request.unit {
+            definition.unit = unit;
+        }
+        if let Some(description) = request.description {
+            definition.description = description;
+        }
+        if let Some(min_value) = request.min_value {
+            definition.min_value = min_value;
+        }
+        if let Some(max_value) = request.max_value {
+            definition.max_value = max_value;
+        }
+        validate_range(definition.min_value, definition.max_value)?;
+
+        self.metric_repo.update(&definition).await
+    }
+
+    /// 删除指标定义
+    ///
+    /// 已上报的数据保留；删除后该指标不能再上报，引用它的预警规则不再触发新的预警
+    pub async fn delete(&self, id: Uuid) -> Result<(), AppError> {
+        self.metric_repo.delete(id).await
+    }
+}
+
+/// 校验取值范围
+fn validate_range(min_value: Option<f64>, max_value: Option<f64>) -> Result<(), AppError> {
+    if min_value.is_some_and(|v| !v.is_finite()) || max_value.is_some_and(|v| !v.is_finite()) {
+        return Err(AppError::ValidationError(
+            "取值范围必须为有效数值".to_string(),
+        ));
+    }
+    if let (Some(min), Some(max)) = (min_value, max_value) {
+        if min > max {
+            return Err(AppError::ValidationError(
+                "最小值不能大于最大值".to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
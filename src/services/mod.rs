@@ -4,26 +4,34 @@ mod alert_service;
 mod auth_service;
 mod battery_service;
 mod cache_service;
+mod device_monitor_service;
 mod device_service;
 mod device_token_service;
 mod email_service;
+mod metric_service;
+mod notification_outbox;
 mod notification_service;
 mod recaptcha_service;
 mod registration_security_service;
 mod user_service;
 mod verification_service;
 mod web_push_service;
+mod webhook_service;
 
-pub use alert_service::AlertService;
+pub use alert_service::{AlertCheck, AlertOutcome, AlertPushSender, AlertService};
 pub use auth_service::AuthService;
-pub use battery_service::BatteryService;
+pub use battery_service::{BatteryPushSender, BatteryService};
 pub use cache_service::CacheService;
+pub use device_monitor_service::DeviceMonitorService;
 pub use device_service::DeviceService;
 pub use device_token_service::DeviceAccessTokenService;
 pub use email_service::EmailService;
-pub use notification_service::NotificationService;
+pub use metric_service::MetricService;
+pub use notification_outbox::{retry_delay, NotificationOutboxWorker};
+pub use notification_service::{DeliveryOutcome, NotificationService};
 pub use recaptcha_service::{RecaptchaService, RecaptchaVerifyResult};
 pub use registration_security_service::{RegistrationCheckResult, RegistrationSecurityService};
 pub use user_service::UserService;
 pub use verification_service::{VerificationCodeType, VerificationService};
 pub use web_push_service::WebPushService;
+pub use webhook_service::{sign_webhook_payload, WebhookService};
@@ -0,0 +1,291 @@
+//! 通知发件箱投递任务
+//!
+//! 后台定时领取到期的 pending / failed 记录并投递，失败后按指数退避重试，
+//! 超过最大尝试次数或遇到重试也不会成功的错误（请求或配置错误）时转为死信（dead），
+//! 可由管理员重放。
+
+use crate::config::Settings;
+use crate::errors::AppError;
+use crate::models::NotificationHistory;
+use crate::repositories::NotificationRepository;
+use crate::services::notification_service::DeliveryOutcome;
+use crate::services::NotificationService;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 单批次内并发投递数
+const DELIVERY_CONCURRENCY: usize = 8;
+
+/// 通知发件箱投递任务
+pub struct NotificationOutboxWorker {
+    notification_service: Arc<NotificationService>,
+    notification_repo: NotificationRepository,
+    poll_interval: Duration,
+    batch_size: i64,
+    lease_seconds: i64,
+    max_attempts: i32,
+    retry_base: Duration,
+    retry_max: Duration,
+}
+
+impl NotificationOutboxWorker {
+    pub fn new(
+        settings: &Settings,
+        notification_service: Arc<NotificationService>,
+        notification_repo: NotificationRepository,
+    ) -> Self {
+        let config = &settings.notification;
+
+        Self {
+            notification_service,
+            notification_repo,
+            poll_interval: Duration::from_secs(config.outbox_poll_interval_seconds.max(1)),
+            batch_size: config.outbox_batch_size.max(1),
+            lease_seconds: config.outbox_lease_seconds.max(1),
+            max_attempts: config.max_attempts.max(1),
+            retry_base: Duration::from_secs(config.retry_base_seconds),
+            retry_max: Duration::from_secs(config.retry_max_seconds),
+        }
+    }
+
+    /// 启动后台投递任务
+    pub fn start(self: &Arc<Self>) {
+        let worker = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(worker.poll_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+                if let Err(e) = worker.drain().await {
+                    tracing::error!(error = %e, "通知发件箱投递失败");
+                }
+            }
+        });
+    }
+
+    /// 投递发件箱中到期的记录，直到没有可领取的记录为止
+    ///
+    /// 返回本次处理的记录数
+    pub async fn drain(&self) -> Result<usize, AppError> {
+        let mut processed = 0;
+
+        loop {
+            let batch = self
+                .notification_repo
+                .claim_due_notifications(self.batch_size, self.lease_seconds)
+                .await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            processed += batch.len();
+            let full = batch.len() as i64 >= self.batch_size;
+
+            futures::stream::iter(batch)
+                .for_each_concurrent(DELIVERY_CONCURRENCY, |history| async move {
+                    if let Err(e) = self.process(&history).await {
+                        tracing::error!(error = %e, history_id = %history.id, "投递结果记录失败");
+                    }
+                })
+                .await;
+
+            if !full {
+                break;
+            }
+        }
+
+        Ok(processed)
+    }
+
+    /// 投递单条记录并保存结果
+    async fn process(&self, history: &NotificationHistory) -> Result<(), AppError> {
+        match self.notification_service.deliver(history).await {
+            Ok(DeliveryOutcome::Sent) => {
+                self.notification_repo
+                    .update_notification_status(history.id, "sent", None)
+                    .await?;
+            }
+            Ok(DeliveryOutcome::Skipped(reason)) => {
+                self.notification_repo
+                    .update_notification_status(history.id, "skipped", Some(&reason))
+                    .await?;
+            }
+            Err(e) => {
+                let reason = failure_reason(&e);
+                let retry_at = next_retry_at(
+                    history.attempts,
+                    self.max_attempts,
+                    &e,
+                    self.retry_base,
+                    self.retry_max,
+                    Utc::now(),
+                );
+
+                match retry_at {
+                    Some(at) => tracing::warn!(
+                        history_id = %history.id,
+                        channel = %history.channel,
+                        attempts = history.attempts,
+                        retry_at = %at,
+                        error = %reason,
+                        "通知投递失败，稍后重试"
+                    ),
+                    None if is_permanent_error(&e) => tracing::error!(
+                        history_id = %history.id,
+                        channel = %history.channel,
+                        attempts = history.attempts,
+                        error = %reason,
+                        "通知投递失败且重试无效，转为死信"
+                    ),
+                    None => tracing::error!(
+                        history_id = %history.id,
+                        channel = %history.channel,
+                        attempts = history.attempts,
+                        error = %reason,
+                        "通知投递失败次数已达上限，转为死信"
+                    ),
+                }
+
+                self.notification_repo
+                    .record_notification_failure(history.id, &reason, retry_at)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 计算第 `attempts` 次失败后的重试间隔（指数退避，封顶 `max`）
+pub fn retry_delay(attempts: i32, base: Duration, max: Duration) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 31) as u32;
+    base.saturating_mul(1u32 << exponent).min(max)
+}
+
+/// 是否为重试也不会成功的错误（请求或配置错误，如无效的 Webhook 请求头、渠道未配置）
+pub fn is_permanent_error(error: &AppError) -> bool {
+    matches!(
+        error,
+        AppError::Unauthorized(_)
+            | AppError::Forbidden(_)
+            | AppError::NotFound(_)
+            | AppError::ValidationError(_)
+            | AppError::Conflict(_)
+            | AppError::ConfigError(_)
+    )
+}
+
+/// 计算投递失败后的重试时间，`None` 表示转为死信
+///
+/// `attempts` 为包含本次在内的尝试次数（领取时已累加）；
+/// 永久错误或已达最大尝试次数时不再重试
+pub fn next_retry_at(
+    attempts: i32,
+    max_attempts: i32,
+    error: &AppError,
+    base: Duration,
+    max: Duration,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if is_permanent_error(error) || attempts >= max_attempts {
+        return None;
+    }
+
+    chrono::Duration::from_std(retry_delay(attempts, base, max))
+        .ok()
+        .map(|d| now + d)
+}
+
+/// 提取失败原因（保留内部错误的详细信息）
+fn failure_reason(error: &AppError) -> String {
+    match error {
+        AppError::InternalError(msg)
+        | AppError::ValidationError(msg)
+        | AppError::ConfigError(msg) => msg.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_exponential() {
+        let base = Duration::from_secs(30);
+        let max = Duration::from_secs(3600);
+
+        assert_eq!(retry_delay(1, base, max), Duration::from_secs(30));
+        assert_eq!(retry_delay(2, base, max), Duration::from_secs(60));
+        assert_eq!(retry_delay(4, base, max), Duration::from_secs(240));
+    }
+
+    #[test]
+    fn test_retry_delay_capped() {
+        let base = Duration::from_secs(30);
+        let max = Duration::from_secs(3600);
+
+        assert_eq!(retry_delay(8, base, max), max);
+        assert_eq!(retry_delay(100, base, max), max);
+    }
+
+    const BASE: Duration = Duration::from_secs(30);
+    const MAX: Duration = Duration::from_secs(3600);
+
+    fn transient() -> AppError {
+        AppError::InternalError("Webhook 请求失败: 连接失败".to_string())
+    }
+
+    #[test]
+    fn test_transient_error_retried_with_backoff() {
+        let now = Utc::now();
+
+        assert_eq!(
+            next_retry_at(1, 5, &transient(), BASE, MAX, now),
+            Some(now + chrono::Duration::seconds(30))
+        );
+        assert_eq!(
+            next_retry_at(3, 5, &transient(), BASE, MAX, now),
+            Some(now + chrono::Duration::seconds(120))
+        );
+    }
+
+    #[test]
+    fn test_dead_after_max_attempts() {
+        let now = Utc::now();
+
+        assert!(next_retry_at(4, 5, &transient(), BASE, MAX, now).is_some());
+        assert_eq!(next_retry_at(5, 5, &transient(), BASE, MAX, now), None);
+        assert_eq!(next_retry_at(6, 5, &transient(), BASE, MAX, now), None);
+    }
+
+    #[test]
+    fn test_permanent_error_dead_immediately() {
+        let now = Utc::now();
+
+        for error in [
+            AppError::ValidationError("请求头名称无效".to_string()),
+            AppError::ConfigError("邮件服务未启用".to_string()),
+            AppError::Forbidden("目标地址不被允许".to_string()),
+            AppError::NotFound("设备不存在".to_string()),
+        ] {
+            assert_eq!(
+                next_retry_at(1, 5, &error, BASE, MAX, now),
+                None,
+                "{} 不应重试",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_rate_limited_error_retried() {
+        let error = AppError::RateLimited("推送服务限流".to_string());
+
+        assert!(!is_permanent_error(&error));
+        assert!(next_retry_at(1, 5, &error, BASE, MAX, Utc::now()).is_some());
+    }
+}
@@ -1,25 +1,38 @@
 //! 通知服务模块
 //!
-//! 提供统一的通知接口，支持多种通知渠道（邮件、Webhook等）
+//! 提供统一的通知接口，支持多种通知渠道（邮件、Webhook等）。
+//! 预警通知先写入发件箱（notification_history），由后台任务投递。
 
 use crate::errors::AppError;
 use crate::models::{
-    AlertEvent, AlertLevel, EmailNotificationConfig, NotificationChannel, SubscribeWebPushRequest,
-    UpdateNotificationPreferenceRequest, UserNotificationPreference, WebPushNotificationConfig,
-    WebPushSubscription, WebhookNotificationConfig,
+    is_rate_limited_event, AlertEvent, AlertLevel, EmailNotificationConfig, NotificationChannel,
+    NotificationHistory, NotificationOutboxQuery, NotificationPayload, PaginatedResponse,
+    Pagination, SubscribeWebPushRequest, UpdateNotificationPreferenceRequest,
+    UserNotificationPreference, WebPushNotificationConfig, WebPushSubscription,
+    WebhookNotificationConfig, EVENT_ALERT_RESOLVED, EVENT_ALERT_TRIGGERED,
 };
 use crate::repositories::{DeviceRepository, NotificationRepository};
 use crate::services::alert_service::NotificationSender;
-use crate::services::{EmailService, WebPushService};
+use crate::services::{EmailService, WebPushService, WebhookService};
 use chrono::Utc;
 use std::sync::Arc;
 use uuid::Uuid;
 
+/// 发件箱记录投递结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeliveryOutcome {
+    /// 投递成功
+    Sent,
+    /// 无需投递（渠道已停用等），附带原因
+    Skipped(String),
+}
+
 /// 通知服务
 pub struct NotificationService {
     notification_repo: NotificationRepository,
     device_repo: DeviceRepository,
     email_service: Arc<EmailService>,
+    webhook_service: Arc<WebhookService>,
     web_push_service: Option<Arc<WebPushService>>,
 }
 
@@ -32,6 +45,15 @@ impl NotificationSender for NotificationService {
     ) -> Result<(), AppError> {
         self.send_alert_notification(alert_event, user_id).await
     }
+
+    async fn send_alert_resolved_notification(
+        &self,
+        alert_event: &AlertEvent,
+        user_id: Uuid,
+    ) -> Result<(), AppError> {
+        self.send_alert_resolved_notification(alert_event, user_id)
+            .await
+    }
 }
 
 impl NotificationService {
@@ -39,11 +61,13 @@ impl NotificationService {
         notification_repo: NotificationRepository,
         device_repo: DeviceRepository,
         email_service: Arc<EmailService>,
+        webhook_service: Arc<WebhookService>,
     ) -> Self {
         Self {
             notification_repo,
             device_repo,
             email_service,
+            webhook_service,
             web_push_service: None,
         }
     }
@@ -119,10 +143,33 @@ impl NotificationService {
     // ========== 预警通知发送 ==========
 
     /// 发送预警通知（根据用户偏好选择渠道）
+    ///
+    /// 仅写入通知发件箱，由后台任务异步投递并在失败时重试
     pub async fn send_alert_notification(
         &self,
         alert_event: &AlertEvent,
         user_id: Uuid,
+    ) -> Result<(), AppError> {
+        self.notify(alert_event, user_id, EVENT_ALERT_TRIGGERED)
+            .await
+    }
+
+    /// 发送预警解决通知（不受频率限制，也不计入频率限制）
+    pub async fn send_alert_resolved_notification(
+        &self,
+        alert_event: &AlertEvent,
+        user_id: Uuid,
+    ) -> Result<(), AppError> {
+        self.notify(alert_event, user_id, EVENT_ALERT_RESOLVED)
+            .await
+    }
+
+    /// 按用户偏好将预警事件写入发件箱
+    async fn notify(
+        &self,
+        alert_event: &AlertEvent,
+        user_id: Uuid,
+        event_type: &str,
     ) -> Result<(), AppError> {
         // 获取用户通知偏好
         let preference = match self.notification_repo.get_user_preference(user_id).await? {
@@ -162,94 +209,104 @@ impl NotificationService {
             .await?
             .ok_or_else(|| AppError::NotFound("设备不存在".to_string()))?;
 
-        // 发送各渠道通知
-        let mut sent_any = false;
+        let payload = serde_json::to_value(NotificationPayload {
+            alert_event: alert_event.clone(),
+            device_name: device.name,
+        })
+        .map_err(|e| AppError::InternalError(format!("通知内容序列化失败: {}", e)))?;
+
+        // 各渠道写入发件箱
+        let mut queued = 0;
+        for (channel, recipient) in self.enabled_channels(&preference)? {
+            if self
+                .enqueue(
+                    &preference,
+                    alert_event,
+                    channel,
+                    &recipient,
+                    event_type,
+                    &payload,
+                )
+                .await?
+            {
+                queued += 1;
+            }
+        }
 
-        // 1. 邮件通知
-        if let Err(e) = self
-            .send_email_notification(&preference, alert_event, &device.name)
-            .await
-        {
-            tracing::error!(
-                error = %e,
+        if queued > 0 {
+            tracing::info!(
                 user_id = %user_id,
                 alert_id = %alert_event.id,
-                "邮件通知发送失败"
+                event_type = event_type,
+                channels = queued,
+                "预警通知已加入发件箱"
             );
-        } else {
-            sent_any = true;
         }
 
-        // 2. Webhook 通知（预留）
-        if let Err(e) = self
-            .send_webhook_notification(&preference, alert_event, &device.name)
-            .await
-        {
-            tracing::error!(
-                error = %e,
-                user_id = %user_id,
-                alert_id = %alert_event.id,
-                "Webhook通知发送失败"
-            );
-        } else if self.is_webhook_enabled(&preference) {
-            sent_any = true;
+        Ok(())
+    }
+
+    /// 获取用户已启用的渠道及接收地址
+    fn enabled_channels(
+        &self,
+        preference: &UserNotificationPreference,
+    ) -> Result<Vec<(NotificationChannel, String)>, AppError> {
+        let mut channels = Vec::new();
+
+        // 1. 邮件通知
+        if let Some(config) = &preference.email_config {
+            let email_config: EmailNotificationConfig = serde_json::from_value(config.clone())
+                .map_err(|e| AppError::InternalError(format!("邮件配置解析失败: {}", e)))?;
+            if email_config.enabled {
+                channels.push((NotificationChannel::Email, email_config.email));
+            }
         }
 
-        // 3. Web Push 通知
-        if let Err(e) = self
-            .send_web_push_notification(&preference, alert_event, &device.name)
-            .await
-        {
-            tracing::error!(
-                error = %e,
-                user_id = %user_id,
-                alert_id = %alert_event.id,
-                "Web Push 通知发送失败"
-            );
-        } else if self.is_web_push_enabled(&preference) {
-            sent_any = true;
+        // 2. Webhook 通知
+        if let Some(config) = &preference.webhook_config {
+            let webhook_config: WebhookNotificationConfig = serde_json::from_value(config.clone())
+                .map_err(|e| AppError::InternalError(format!("Webhook配置解析失败: {}", e)))?;
+            if webhook_config.enabled {
+                channels.push((NotificationChannel::Webhook, webhook_config.url));
+            }
         }
 
-        if sent_any {
-            tracing::info!(
-                user_id = %user_id,
-                alert_id = %alert_event.id,
-                "预警通知已发送"
-            );
+        // 3. Web Push 通知（需要配置 VAPID 密钥）
+        if self.web_push_service.is_some() && self.is_web_push_enabled(preference) {
+            channels.push((NotificationChannel::Push, "web_push".to_string()));
         }
 
-        Ok(())
+        Ok(channels)
     }
 
-    /// 发送邮件通知
-    async fn send_email_notification(
+    /// 写入发件箱（频率限制内的通知记录为跳过）
+    ///
+    /// 返回是否已加入发件箱
+    async fn enqueue(
         &self,
         preference: &UserNotificationPreference,
         alert_event: &AlertEvent,
-        device_name: &str,
-    ) -> Result<(), AppError> {
-        // 解析邮件配置
-        let email_config: EmailNotificationConfig = match &preference.email_config {
-            Some(config) => serde_json::from_value(config.clone())
-                .map_err(|e| AppError::InternalError(format!("邮件配置解析失败: {}", e)))?,
-            None => return Ok(()),
+        channel: NotificationChannel,
+        recipient: &str,
+        event_type: &str,
+        payload: &serde_json::Value,
+    ) -> Result<bool, AppError> {
+        // 检查频率限制（仅限触发通知）
+        let last_time = if is_rate_limited_event(event_type) {
+            self.notification_repo
+                .get_last_notification_time(preference.user_id, channel.clone())
+                .await?
+        } else {
+            None
         };
 
-        if !email_config.enabled {
-            return Ok(());
-        }
-
-        // 检查频率限制
-        if let Some(last_time) = self
-            .notification_repo
-            .get_last_notification_time(preference.user_id, NotificationChannel::Email)
-            .await?
-        {
+        if let Some(last_time) = last_time {
             let elapsed = Utc::now().signed_duration_since(last_time);
             if elapsed.num_minutes() < preference.min_notification_interval as i64 {
                 tracing::debug!(
                     user_id = %preference.user_id,
-                    "邮件通知频率限制中"
+                    channel = %channel,
+                    "通知频率限制中"
                 );
 
                 // 记录跳过
@@ -257,37 +314,92 @@ impl NotificationService {
                     .create_notification_history(
                         alert_event.id,
                         preference.user_id,
-                        NotificationChannel::Email,
-                        &email_config.email,
+                        channel,
+                        recipient,
                         "skipped",
                         Some("频率限制"),
                     )
                     .await?;
 
-                return Ok(());
+                return Ok(false);
             }
         }
 
-        // 创建待发送记录
-        let history = self
-            .notification_repo
-            .create_notification_history(
+        self.notification_repo
+            .enqueue_notification(
                 alert_event.id,
                 preference.user_id,
-                NotificationChannel::Email,
-                &email_config.email,
-                "pending",
-                None,
+                channel,
+                recipient,
+                event_type,
+                payload,
             )
             .await?;
 
-        // 发送邮件
+        Ok(true)
+    }
+
+    /// 投递一条发件箱记录
+    ///
+    /// 返回 `Err` 表示本次投递失败，由调用方决定是否重试
+    pub async fn deliver(
+        &self,
+        history: &NotificationHistory,
+    ) -> Result<DeliveryOutcome, AppError> {
+        let payload: NotificationPayload = match &history.payload {
+            Some(p) => serde_json::from_value(p.clone())
+                .map_err(|e| AppError::InternalError(format!("通知内容解析失败: {}", e)))?,
+            None => return Ok(DeliveryOutcome::Skipped("缺少投递内容".to_string())),
+        };
+
+        match history.channel {
+            NotificationChannel::Email => self.deliver_email(history, &payload).await,
+            NotificationChannel::Webhook => self.deliver_webhook(history, &payload).await,
+            NotificationChannel::Push => self.deliver_web_push(history, &payload).await,
+            NotificationChannel::Sms => {
+                Ok(DeliveryOutcome::Skipped("短信渠道暂不支持".to_string()))
+            }
+        }
+    }
+
+    /// 投递邮件通知
+    async fn deliver_email(
+        &self,
+        history: &NotificationHistory,
+        payload: &NotificationPayload,
+    ) -> Result<DeliveryOutcome, AppError> {
+        let alert_event = &payload.alert_event;
+
+        if history.event_type == EVENT_ALERT_RESOLVED {
+            let params = crate::services::email_service::AlertResolvedParams {
+                to_email: &history.recipient,
+                alert_type: &format!("{:?}", alert_event.alert_type),
+                message: &alert_event.message,
+                device_name: &payload.device_name,
+                triggered_at: &alert_event
+                    .triggered_at
+                    .format("%Y-%m-%d %H:%M:%S UTC")
+                    .to_string(),
+                resolved_at: &alert_event
+                    .resolved_at
+                    .unwrap_or_else(Utc::now)
+                    .format("%Y-%m-%d %H:%M:%S UTC")
+                    .to_string(),
+            };
+
+            self.email_service
+                .send_alert_resolved_notification(params)
+                .await?;
+
+            return Ok(DeliveryOutcome::Sent);
+        }
+
         let params = crate::services::email_service::AlertNotificationParams {
-            to_email: &email_config.email,
+            to_email: &history.recipient,
             alert_type: &format!("{:?}", alert_event.alert_type),
             level: &format!("{:?}", alert_event.level),
             message: &alert_event.message,
-            device_name,
+            device_name: &payload.device_name,
             value: alert_event.value,
             threshold: alert_event.threshold,
             triggered_at: &alert_event
@@ -295,227 +407,125 @@ impl NotificationService {
                 .format("%Y-%m-%d %H:%M:%S UTC")
                 .to_string(),
         };
-        let result = self.email_service.send_alert_notification(params).await;
 
-        // 更新发送状态
-        match result {
-            Ok(_) => {
-                self.notification_repo
-                    .update_notification_status(history.id, "sent", None)
-                    .await?;
-            }
-            Err(e) => {
-                self.notification_repo
-                    .update_notification_status(history.id, "failed", Some(&e.to_string()))
-                    .await?;
-                return Err(e);
-            }
-        }
+        self.email_service.send_alert_notification(params).await?;
 
-        Ok(())
+        Ok(DeliveryOutcome::Sent)
     }
 
-    /// 发送Webhook通知（预留扩展）
-    async fn send_webhook_notification(
+    /// 投递Webhook通知
+    async fn deliver_webhook(
         &self,
-        preference: &UserNotificationPreference,
-        alert_event: &AlertEvent,
-        device_name: &str,
-    ) -> Result<(), AppError> {
-        // 解析Webhook配置
-        let webhook_config: WebhookNotificationConfig = match &preference.webhook_config {
-            Some(config) => serde_json::from_value(config.clone())
-                .map_err(|e| AppError::InternalError(format!("Webhook配置解析失败: {}", e)))?,
-            None => return Ok(()),
-        };
-
-        if !webhook_config.enabled {
-            return Ok(());
-        }
-
-        // 检查频率限制
-        if let Some(last_time) = self
+        history: &NotificationHistory,
+        payload: &NotificationPayload,
+    ) -> Result<DeliveryOutcome, AppError> {
+        // 使用最新配置（密钥或请求头可能已更新）
+        let preference = self
             .notification_repo
-            .get_last_notification_time(preference.user_id, NotificationChannel::Webhook)
-            .await?
+            .get_user_preference(history.user_id)
+            .await?;
+        let webhook_config: WebhookNotificationConfig = match preference
+            .and_then(|p| p.webhook_config)
+            .and_then(|v| serde_json::from_value(v).ok())
         {
-            let elapsed = Utc::now().signed_duration_since(last_time);
-            if elapsed.num_minutes() < preference.min_notification_interval as i64 {
-                self.notification_repo
-                    .create_notification_history(
-                        alert_event.id,
-                        preference.user_id,
-                        NotificationChannel::Webhook,
-                        &webhook_config.url,
-                        "skipped",
-                        Some("频率限制"),
-                    )
-                    .await?;
-                return Ok(());
-            }
+            Some(c) => c,
+            None => return Ok(DeliveryOutcome::Skipped("Webhook 已停用".to_string())),
+        };
+
+        if !webhook_config.enabled || webhook_config.url != history.recipient {
+            return Ok(DeliveryOutcome::Skipped("Webhook 已停用".to_string()));
         }
 
         // 构建Webhook负载
-        let _payload = serde_json::json!({
+        let alert_event = &payload.alert_event;
+        let data = serde_json::json!({
             "alert_id": alert_event.id,
-            "device_name": device_name,
+            "device_id": alert_event.device_id,
+            "device_name": payload.device_name,
             "alert_type": alert_event.alert_type,
             "level": alert_event.level,
+            "status": alert_event.status,
             "message": alert_event.message,
             "value": alert_event.value,
             "threshold": alert_event.threshold,
             "triggered_at": alert_event.triggered_at,
+            "resolved_at": alert_event.resolved_at,
         });
 
-        // 这里可以实现实际的HTTP请求发送
-        // 目前记录为待实现
-
-        // 记录通知历史
-        self.notification_repo
-            .create_notification_history(
-                alert_event.id,
-                preference.user_id,
-                NotificationChannel::Webhook,
-                &webhook_config.url,
-                "sent",
-                None,
-            )
-            .await?;
-
-        Ok(())
+        // 记录 ID 作为投递 ID，重试时保持不变便于接收方去重
+        match self
+            .webhook_service
+            .send(&webhook_config, &history.event_type, history.id, &data)
+            .await
+        {
+            Ok(()) => Ok(DeliveryOutcome::Sent),
+            // 目标地址不被允许，重试也不会成功
+            Err(AppError::Forbidden(reason)) => Ok(DeliveryOutcome::Skipped(reason)),
+            Err(e) => Err(e),
+        }
     }
 
-    /// 发送 Web Push 通知
-    async fn send_web_push_notification(
+    /// 投递 Web Push 通知
+    async fn deliver_web_push(
         &self,
-        preference: &UserNotificationPreference,
-        alert_event: &AlertEvent,
-        device_name: &str,
-    ) -> Result<(), AppError> {
+        history: &NotificationHistory,
+        payload: &NotificationPayload,
+    ) -> Result<DeliveryOutcome, AppError> {
         // 检查 Web Push 服务是否可用
         let web_push_service = match &self.web_push_service {
             Some(service) => service,
-            None => return Ok(()), // 未配置 Web Push 服务
+            None => return Ok(DeliveryOutcome::Skipped("Web Push 服务未配置".to_string())),
         };
 
-        // 解析配置
-        let web_push_config: WebPushNotificationConfig = match &preference.web_push_config {
-            Some(config) => serde_json::from_value(config.clone())
-                .map_err(|e| AppError::InternalError(format!("Web Push 配置解析失败: {}", e)))?,
-            None => return Ok(()),
-        };
-
-        if !web_push_config.enabled {
-            return Ok(());
-        }
-
-        // 检查频率限制
-        if let Some(last_time) = self
-            .notification_repo
-            .get_last_notification_time(preference.user_id, NotificationChannel::Push)
-            .await?
-        {
-            let elapsed = Utc::now().signed_duration_since(last_time);
-            if elapsed.num_minutes() < preference.min_notification_interval as i64 {
-                tracing::debug!(
-                    user_id = %preference.user_id,
-                    "Web Push 通知频率限制中"
-                );
-
-                // 记录跳过
-                self.notification_repo
-                    .create_notification_history(
-                        alert_event.id,
-                        preference.user_id,
-                        NotificationChannel::Push,
-                        "web_push",
-                        "skipped",
-                        Some("频率限制"),
-                    )
-                    .await?;
-
-                return Ok(());
-            }
-        }
-
-        // 创建待发送记录
-        let history = self
-            .notification_repo
-            .create_notification_history(
-                alert_event.id,
-                preference.user_id,
-                NotificationChannel::Push,
-                "web_push",
-                "pending",
-                None,
-            )
-            .await?;
-
         // 构建通知内容
-        let title = format!("{:?} - {:?}", alert_event.level, alert_event.alert_type);
-        let body = format!("{} | {}", device_name, alert_event.message);
+        let alert_event = &payload.alert_event;
+        let title = payload.title(&history.event_type);
+        let body = format!("{} | {}", payload.device_name, alert_event.message);
         let data = Some(serde_json::json!({
             "alert_id": alert_event.id,
             "device_id": alert_event.device_id,
             "alert_type": alert_event.alert_type,
             "level": alert_event.level,
+            "status": alert_event.status,
         }));
 
         // 发送到用户的所有订阅
-        let result = web_push_service
-            .send_to_user(preference.user_id, &title, &body, data)
-            .await;
+        let count = web_push_service
+            .send_to_user(history.user_id, &title, &body, data)
+            .await?;
 
-        // 更新发送状态
-        match result {
-            Ok(count) if count > 0 => {
-                self.notification_repo
-                    .update_notification_status(history.id, "sent", None)
-                    .await?;
-            }
-            Ok(_) => {
-                self.notification_repo
-                    .update_notification_status(history.id, "skipped", Some("无活跃订阅"))
-                    .await?;
-            }
-            Err(e) => {
-                self.notification_repo
-                    .update_notification_status(history.id, "failed", Some(&e.to_string()))
-                    .await?;
-                return Err(e);
-            }
+        if count == 0 {
+            return Ok(DeliveryOutcome::Skipped("无活跃订阅".to_string()));
         }
 
-        Ok(())
+        Ok(DeliveryOutcome::Sent)
     }
 
-    // ========== Webhook 通知（待实现）==========
-    /*
-    async fn send_webhook_notification(
+    // ========== 通知发件箱管理 ==========
+
+    /// 查询发件箱记录（管理员）
+    pub async fn list_outbox(
         &self,
-        preference: &UserNotificationPreference,
-        alert_event: &AlertEvent,
-        device_name: &str,
-    ) -> Result<(), AppError> {
-        let webhook_config: WebhookNotificationConfig = match &preference.webhook_config {
-            Some(v) => serde_json::from_value(v.clone())
-                .map_err(|_| AppError::ConfigError("Webhook配置无效".to_string()))?,
-            None => return Ok(()),
-        };
+        query: NotificationOutboxQuery,
+    ) -> Result<PaginatedResponse<NotificationHistory>, AppError> {
+        let (histories, total) = self.notification_repo.list_outbox(&query).await?;
 
-        if !webhook_config.enabled {
-            return Ok(());
-        }
+        let pagination = Pagination::new(query.page, query.page_size, total);
 
-        // TODO: 实现 Webhook 发送逻辑
-        tracing::info!(
-            webhook_url = %webhook_config.url,
-            "Webhook通知已准备（实现待补充）"
-        );
+        Ok(PaginatedResponse::new(histories, pagination))
+    }
 
-        Ok(())
+    /// 重放失败或死信记录（管理员）
+    pub async fn replay(&self, history_id: Uuid) -> Result<NotificationHistory, AppError> {
+        let history = self
+            .notification_repo
+            .replay_notification(history_id)
+            .await?;
+
+        tracing::info!(history_id = %history_id, channel = %history.channel, "通知已重新加入发件箱");
+
+        Ok(history)
     }
-    */
 
     // ========== 辅助方法 ==========
 
@@ -555,15 +565,6 @@ impl NotificationService {
         }
     }
 
-    /// 检查Webhook是否启用
-    fn is_webhook_enabled(&self, preference: &UserNotificationPreference) -> bool {
-        preference
-            .webhook_config
-            .as_ref()
-            .and_then(|v| serde_json::from_value::<WebhookNotificationConfig>(v.clone()).ok())
-            .is_some_and(|c| c.enabled)
-    }
-
     /// 检查Web Push是否启用
     fn is_web_push_enabled(&self, preference: &UserNotificationPreference) -> bool {
         preference
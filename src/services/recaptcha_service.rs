@@ -207,6 +207,11 @@ mod tests {
                 score_threshold: 0.5,
             },
             registration: Default::default(),
+            websocket: Default::default(),
+            device_monitor: Default::default(),
+            webhook: Default::default(),
+            notification: Default::default(),
+            battery: Default::default(),
         };
 
         let service = RecaptchaService::new(&settings);
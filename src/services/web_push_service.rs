@@ -203,6 +203,13 @@ impl WebPushService {
             "批量 Web Push 发送完成"
         );
 
+        if success_count == 0 {
+            return Err(AppError::InternalError(format!(
+                "全部 {} 个订阅推送失败",
+                subscriptions.len()
+            )));
+        }
+
         Ok(success_count)
     }
 }
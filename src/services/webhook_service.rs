@@ -0,0 +1,476 @@
+//! Webhook 投递服务
+//!
+//! 将事件以 JSON POST 到用户配置的 URL。配置了 `secret` 时附带签名头：
+//! - `X-Zinnia-Timestamp`: Unix 时间戳（秒），接收方应拒绝偏差过大的请求以防重放
+//! - `X-Zinnia-Signature`: `sha256=<hex>`，签名内容为 `{timestamp}.{body}`
+//!
+//! 目标地址只允许公网地址（见 [`WebhookTargetPolicy`]），防止借 Webhook 访问服务端内网
+
+use crate::config::{Settings, WebhookSettings};
+use crate::errors::AppError;
+use crate::models::WebhookNotificationConfig;
+use crate::security::hmac_sha256_hex;
+use chrono::Utc;
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, USER_AGENT};
+use reqwest::{redirect, Client, Url};
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// 事件类型请求头
+pub const HEADER_EVENT: &str = "X-Zinnia-Event";
+/// 投递 ID 请求头（接收方可据此去重）
+pub const HEADER_DELIVERY: &str = "X-Zinnia-Delivery";
+/// 时间戳请求头
+pub const HEADER_TIMESTAMP: &str = "X-Zinnia-Timestamp";
+/// 签名请求头
+pub const HEADER_SIGNATURE: &str = "X-Zinnia-Signature";
+
+/// 错误信息中保留的响应体最大长度
+const MAX_ERROR_BODY_LEN: usize = 200;
+
+/// Webhook 目标地址策略
+///
+/// 回环、私有网段、链路本地（含云厂商元数据地址 `169.254.169.254`）等非公网地址一律拒绝，
+/// `allowed_hosts` 中的主机不受限制。既用于投递前校验，也作为客户端的 DNS 解析器，
+/// 连接时重新解析出的地址同样经过校验（防止 DNS 重绑定绕过投递前的校验）
+#[derive(Debug, Clone, Default)]
+pub struct WebhookTargetPolicy {
+    allowed_hosts: Arc<HashSet<String>>,
+}
+
+impl WebhookTargetPolicy {
+    /// `allowed_hosts` 为逗号分隔的主机名或 IP（不区分大小写）
+    pub fn new(allowed_hosts: &str) -> Self {
+        let allowed_hosts = allowed_hosts
+            .split(',')
+            .map(|h| normalize_host(h.trim()))
+            .filter(|h| !h.is_empty())
+            .collect();
+
+        Self {
+            allowed_hosts: Arc::new(allowed_hosts),
+        }
+    }
+
+    fn is_allowed_host(&self, host: &str) -> bool {
+        self.allowed_hosts.contains(&normalize_host(host))
+    }
+
+    /// 校验目标 URL：只允许 http / https，主机须解析为公网地址
+    ///
+    /// 目标不被允许时返回 `Forbidden`（重试也不会成功），域名解析失败返回 `InternalError`
+    pub async fn check(&self, url: &str) -> Result<(), AppError> {
+        let url = Url::parse(url)
+            .map_err(|_| AppError::Forbidden(format!("无效的 Webhook URL: {}", url)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(AppError::Forbidden(format!(
+                "Webhook URL 只支持 http / https: {}",
+                url.scheme()
+            )));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| AppError::Forbidden("Webhook URL 缺少主机".to_string()))?;
+        let port = url.port_or_known_default().unwrap_or(443);
+
+        // IP 地址不经过 DNS 解析，直接校验
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return self.check_addrs(host, &[SocketAddr::new(ip, port)]);
+        }
+
+        self.resolve_host(host, port).await.map(|_| ())
+    }
+
+    /// 解析主机并校验所有地址（任一地址不是公网地址即拒绝）
+    async fn resolve_host(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, AppError> {
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| AppError::InternalError(format!("Webhook 域名解析失败: {}", e)))?
+            .collect();
+        self.check_addrs(host, &addrs)?;
+
+        Ok(addrs)
+    }
+
+    fn check_addrs(&self, host: &str, addrs: &[SocketAddr]) -> Result<(), AppError> {
+        if self.is_allowed_host(host) {
+            return Ok(());
+        }
+        match addrs.iter().find(|a| !is_public_ip(a.ip())) {
+            Some(addr) => Err(AppError::Forbidden(format!(
+                "Webhook 目标 {} 解析为非公网地址 {}",
+                host,
+                addr.ip()
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Resolve for WebhookTargetPolicy {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.clone();
+        Box::pin(async move {
+            let addrs = policy.resolve_host(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// 是否为公网地址（回环、私有网段、链路本地、运营商级 NAT、文档和保留地址等均不是）
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            !(v4.is_unspecified()
+                || v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64)
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (b & 0xfe) == 18)
+                || a >= 240)
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let [first, second, ..] = v6.segments();
+            !(v6.is_unspecified()
+                || v6.is_loopback()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && second == 0x0db8))
+        }
+    }
+}
+
+/// 主机名统一为小写，IPv6 去掉方括号
+fn normalize_host(host: &str) -> String {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase()
+}
+
+/// Webhook 投递服务
+pub struct WebhookService {
+    client: Client,
+    policy: WebhookTargetPolicy,
+}
+
+impl WebhookService {
+    pub fn new(settings: &Settings) -> Result<Self, AppError> {
+        Self::with_settings(&settings.webhook)
+    }
+
+    fn with_settings(settings: &WebhookSettings) -> Result<Self, AppError> {
+        let policy = WebhookTargetPolicy::new(&settings.allowed_hosts);
+        let client = Client::builder()
+            .timeout(Duration::from_secs(settings.timeout_seconds))
+            .connect_timeout(Duration::from_secs(settings.connect_timeout_seconds))
+            // 不跟随重定向，避免签名请求被转发到非预期地址（包括内网地址）
+            .redirect(redirect::Policy::none())
+            // 连接时解析出的地址同样按目标地址策略校验
+            .dns_resolver(Arc::new(policy.clone()))
+            .build()
+            .map_err(|e| AppError::ConfigError(format!("创建 Webhook 客户端失败: {}", e)))?;
+
+        Ok(Self { client, policy })
+    }
+
+    /// 投递事件
+    ///
+    /// 仅 2xx 响应视为成功，其余情况返回包含失败原因的 `InternalError`；
+    /// 目标地址不被允许（非公网地址）时返回 `Forbidden`，不发出请求
+    pub async fn send(
+        &self,
+        config: &WebhookNotificationConfig,
+        event_type: &str,
+        delivery_id: Uuid,
+        data: &serde_json::Value,
+    ) -> Result<(), AppError> {
+        let timestamp = Utc::now().timestamp();
+        let body = serde_json::to_vec(&serde_json::json!({
+            "event_type": event_type,
+            "delivery_id": delivery_id,
+            "timestamp": timestamp,
+            "data": data,
+        }))
+        .map_err(|e| AppError::InternalError(format!("Webhook 负载序列化失败: {}", e)))?;
+
+        self.policy.check(&config.url).await?;
+        let headers = build_headers(config, event_type, delivery_id, timestamp, &body)?;
+
+        let response = self
+            .client
+            .post(&config.url)
+            .headers(headers)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| {
+                let reason = if e.is_timeout() {
+                    "请求超时".to_string()
+                } else if e.is_connect() {
+                    "连接失败".to_string()
+                } else {
+                    e.to_string()
+                };
+                AppError::InternalError(format!("Webhook 请求失败: {}", reason))
+            })?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let mut body = response.text().await.unwrap_or_default();
+        if body.len() > MAX_ERROR_BODY_LEN {
+            let mut end = MAX_ERROR_BODY_LEN;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            body.truncate(end);
+        }
+
+        Err(AppError::InternalError(format!(
+            "Webhook 返回 HTTP {}: {}",
+            status.as_u16(),
+            body
+        )))
+    }
+}
+
+/// 计算 Webhook 签名（`sha256=<hex>`）
+pub fn sign_webhook_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut message = format!("{}.", timestamp).into_bytes();
+    message.extend_from_slice(body);
+    format!("sha256={}", hmac_sha256_hex(secret.as_bytes(), &message))
+}
+
+/// 构建请求头（自定义头在前，系统头不可被覆盖）
+fn build_headers(
+    config: &WebhookNotificationConfig,
+    event_type: &str,
+    delivery_id: Uuid,
+    timestamp: i64,
+    body: &[u8],
+) -> Result<HeaderMap, AppError> {
+    let mut headers = HeaderMap::new();
+
+    for (name, value) in &config.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| AppError::ValidationError(format!("无效的 Webhook 请求头: {}", name)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|_| AppError::ValidationError(format!("无效的 Webhook 请求头值: {}", name)))?;
+        headers.insert(name, value);
+    }
+
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_static(concat!("Zinnia-Webhook/", env!("CARGO_PKG_VERSION"))),
+    );
+    headers.insert(HEADER_EVENT, header_value(event_type)?);
+    headers.insert(HEADER_DELIVERY, header_value(&delivery_id.to_string())?);
+    headers.insert(HEADER_TIMESTAMP, header_value(&timestamp.to_string())?);
+
+    if let Some(secret) = config.secret.as_deref().filter(|s| !s.is_empty()) {
+        let signature = sign_webhook_payload(secret, timestamp, body);
+        headers.insert(HEADER_SIGNATURE, header_value(&signature)?);
+    }
+
+    Ok(headers)
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, AppError> {
+    HeaderValue::from_str(value)
+        .map_err(|e| AppError::InternalError(format!("请求头构建失败: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn test_config(secret: Option<&str>) -> WebhookNotificationConfig {
+        WebhookNotificationConfig {
+            enabled: true,
+            url: "https://example.com/webhook".to_string(),
+            secret: secret.map(String::from),
+            headers: HashMap::from([
+                ("X-Custom-Header".to_string(), "custom".to_string()),
+                (HEADER_SIGNATURE.to_string(), "forged".to_string()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_signature_covers_timestamp() {
+        let body = br#"{"event_type":"alert_triggered"}"#;
+        let a = sign_webhook_payload("secret", 1_700_000_000, body);
+        let b = sign_webhook_payload("secret", 1_700_000_001, body);
+
+        assert!(a.starts_with("sha256="));
+        assert_eq!(a.len(), "sha256=".len() + 64);
+        assert_ne!(a, b, "不同时间戳的签名应不同");
+    }
+
+    #[test]
+    fn test_build_headers_with_secret() {
+        let body = b"{}";
+        let headers = build_headers(
+            &test_config(Some("secret")),
+            "alert_triggered",
+            Uuid::nil(),
+            1,
+            body,
+        )
+        .unwrap();
+
+        assert_eq!(headers["x-custom-header"], "custom");
+        assert_eq!(headers[HEADER_EVENT], "alert_triggered");
+        assert_eq!(headers[HEADER_TIMESTAMP], "1");
+        assert_eq!(
+            headers[HEADER_SIGNATURE].to_str().unwrap(),
+            sign_webhook_payload("secret", 1, body),
+            "自定义头不能覆盖签名"
+        );
+    }
+
+    #[test]
+    fn test_build_headers_without_secret() {
+        let mut config = test_config(None);
+        config.headers.clear();
+
+        let headers = build_headers(&config, "alert_triggered", Uuid::nil(), 1, b"{}").unwrap();
+        assert!(!headers.contains_key(HEADER_SIGNATURE));
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{} 不是公网地址", ip);
+        }
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{} 是公网地址", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_rejects_internal_targets() {
+        let policy = WebhookTargetPolicy::default();
+        for url in [
+            "http://169.254.169.254/latest/meta-data",
+            "http://127.0.0.1:8080/hook",
+            "http://[::1]/hook",
+            "http://localhost/hook",
+            "ftp://8.8.8.8/hook",
+            "not a url",
+        ] {
+            assert!(
+                matches!(policy.check(url).await, Err(AppError::Forbidden(_))),
+                "{} 应被拒绝",
+                url
+            );
+        }
+        assert!(policy.check("https://8.8.8.8/hook").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_check_allowed_hosts() {
+        let policy = WebhookTargetPolicy::new(" LOCALHOST , 10.0.0.5,[::1]");
+
+        assert!(policy.check("http://localhost:9000/hook").await.is_ok());
+        assert!(policy.check("http://10.0.0.5/hook").await.is_ok());
+        assert!(policy.check("http://[::1]/hook").await.is_ok());
+        assert!(matches!(
+            policy.check("http://10.0.0.6/hook").await,
+            Err(AppError::Forbidden(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_rejects_internal_target_without_request() {
+        let service = WebhookService::with_settings(&WebhookSettings::default()).unwrap();
+        let mut config = test_config(None);
+        config.url = "http://169.254.169.254/latest/meta-data".to_string();
+
+        let result = service
+            .send(
+                &config,
+                "alert_triggered",
+                Uuid::nil(),
+                &serde_json::json!({}),
+            )
+            .await;
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+    }
+
+    #[tokio::test]
+    async fn test_send_does_not_follow_redirects() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf).await.unwrap();
+            stream
+                .write_all(
+                    b"HTTP/1.1 302 Found\r\n\
+                      Location: http://169.254.169.254/latest/meta-data\r\n\
+                      Content-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+        });
+
+        let settings = WebhookSettings {
+            allowed_hosts: "127.0.0.1".to_string(),
+            ..WebhookSettings::default()
+        };
+        let service = WebhookService::with_settings(&settings).unwrap();
+        let mut config = test_config(None);
+        config.url = format!("http://{}/hook", addr);
+
+        let result = service
+            .send(
+                &config,
+                "alert_triggered",
+                Uuid::nil(),
+                &serde_json::json!({}),
+            )
+            .await;
+        server.await.unwrap();
+
+        match result {
+            Err(AppError::InternalError(msg)) => assert!(msg.contains("302"), "{}", msg),
+            other => panic!("重定向不应被跟随: {:?}", other.err()),
+        }
+    }
+}
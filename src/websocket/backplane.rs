@@ -0,0 +1,278 @@
+//! WebSocket 跨实例推送（Redis Pub/Sub）
+//!
+//! 多副本部署时，设备上报和用户 WebSocket 连接可能落在不同实例上。
+//! 本实例先直接投递给本地会话，再将事件发布到 Redis 频道；
+//! 其他实例订阅该频道后投递给各自的本地会话（忽略自己发出的事件）。
+
+use crate::config::Settings;
+use crate::db::RedisPool;
+use crate::models::{AlertEvent, LatestBatteryResponse};
+use crate::services::{AlertPushSender, BatteryPushSender};
+use crate::websocket::hub::WsHub;
+use crate::websocket::messages::AlertPushMessage;
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// 订阅连接断开后的重连间隔
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// 跨实例推送事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BackplaneEvent {
+    /// 电量数据推送
+    Battery {
+        device_id: Uuid,
+        data: Box<LatestBatteryResponse>,
+    },
+
+    /// 预警推送
+    Alert {
+        user_ids: Vec<Uuid>,
+        message: AlertPushMessage,
+    },
+}
+
+/// 频道消息信封
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BackplaneEnvelope {
+    /// 发布实例 ID（用于忽略自身消息）
+    origin: Uuid,
+    event: BackplaneEvent,
+}
+
+/// Redis Pub/Sub 推送总线
+pub struct RedisBackplane {
+    instance_id: Uuid,
+    channel: String,
+    redis_pool: Arc<RedisPool>,
+    hub: Arc<WsHub>,
+}
+
+impl RedisBackplane {
+    pub fn new(settings: &Settings, redis_pool: Arc<RedisPool>, hub: Arc<WsHub>) -> Self {
+        Self {
+            instance_id: Uuid::new_v4(),
+            channel: settings.websocket.backplane_channel.clone(),
+            redis_pool,
+            hub,
+        }
+    }
+
+    /// 启动后台订阅任务（断线自动重连）
+    pub fn start(self: &Arc<Self>) {
+        let backplane = self.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = backplane.run_subscriber().await {
+                    tracing::error!(error = %e, "WebSocket 推送总线订阅中断");
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+    }
+
+    /// 订阅频道并将收到的事件投递给本地会话
+    async fn run_subscriber(&self) -> Result<(), crate::errors::AppError> {
+        let mut pubsub = self.redis_pool.pubsub().await?;
+        pubsub.subscribe(&self.channel).await?;
+
+        tracing::info!(
+            channel = %self.channel,
+            instance_id = %self.instance_id,
+            "WebSocket 推送总线已订阅"
+        );
+
+        let mut stream = pubsub.on_message();
+        while let Some(msg) = stream.next().await {
+            let payload: String = match msg.get_payload() {
+                Ok(p) => p,
+                Err(e) => {
+                    tracing::warn!(error = %e, "推送总线消息读取失败");
+                    continue;
+                }
+            };
+
+            if let Some(event) = remote_event(&payload, self.instance_id) {
+                self.dispatch_local(&event);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 投递给本实例的会话
+    fn dispatch_local(&self, event: &BackplaneEvent) {
+        match event {
+            BackplaneEvent::Battery { device_id, data } => {
+                self.hub.dispatch_battery(*device_id, data);
+            }
+            BackplaneEvent::Alert { user_ids, message } => {
+                self.hub.dispatch_alert(user_ids, message);
+            }
+        }
+    }
+
+    /// 本地投递并发布到其他实例
+    async fn publish(&self, event: BackplaneEvent) {
+        self.dispatch_local(&event);
+
+        let envelope = BackplaneEnvelope {
+            origin: self.instance_id,
+            event,
+        };
+
+        // 发布失败只影响其他实例，不影响上报
+        if let Err(e) = self.redis_pool.publish(&self.channel, &envelope).await {
+            tracing::warn!(error = %e, "推送总线发布失败");
+        }
+    }
+}
+
+/// 解析频道消息，返回需要投递给本实例会话的事件
+///
+/// 本实例发布的事件已在发布前投递过，格式错误的消息记录日志后忽略
+fn remote_event(payload: &str, instance_id: Uuid) -> Option<BackplaneEvent> {
+    let envelope: BackplaneEnvelope = match serde_json::from_str(payload) {
+        Ok(e) => e,
+        Err(e) => {
+            tracing::warn!(error = %e, "推送总线消息格式错误");
+            return None;
+        }
+    };
+
+    (envelope.origin != instance_id).then_some(envelope.event)
+}
+
+#[async_trait::async_trait]
+impl BatteryPushSender for RedisBackplane {
+    async fn push_battery(&self, device_id: Uuid, data: &LatestBatteryResponse) {
+        self.publish(BackplaneEvent::Battery {
+            device_id,
+            data: Box::new(data.clone()),
+        })
+        .await;
+    }
+}
+
+#[async_trait::async_trait]
+impl AlertPushSender for RedisBackplane {
+    async fn push_alert(&self, event: &AlertEvent, user_ids: &[Uuid]) {
+        self.publish(BackplaneEvent::Alert {
+            user_ids: user_ids.to_vec(),
+            message: AlertPushMessage::from(event),
+        })
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AlertLevel, AlertStatus, AlertType, PowerSavingMode};
+    use chrono::Utc;
+
+    fn battery_event(device_id: Uuid) -> BackplaneEvent {
+        BackplaneEvent::Battery {
+            device_id,
+            data: Box::new(LatestBatteryResponse {
+                device_id,
+                component: Some("main".to_string()),
+                battery_level: 42,
+                is_charging: true,
+                power_saving_mode: PowerSavingMode::Low,
+                recorded_at: Utc::now(),
+                is_low_battery: false,
+                is_critical: false,
+                estimate: None,
+                metrics: None,
+                components: None,
+            }),
+        }
+    }
+
+    fn alert_event(user_ids: Vec<Uuid>) -> BackplaneEvent {
+        BackplaneEvent::Alert {
+            user_ids,
+            message: AlertPushMessage {
+                alert_id: Uuid::new_v4(),
+                device_id: Uuid::new_v4(),
+                alert_type: AlertType::HighTemperature,
+                message: "温度过高".to_string(),
+                severity: AlertLevel::Critical,
+                status: AlertStatus::Active,
+                timestamp: Utc::now(),
+            },
+        }
+    }
+
+    fn encode(origin: Uuid, event: BackplaneEvent) -> String {
+        serde_json::to_string(&BackplaneEnvelope { origin, event }).unwrap()
+    }
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let origin = Uuid::new_v4();
+        let device_id = Uuid::new_v4();
+
+        let payload = encode(origin, battery_event(device_id));
+        let json: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(json["event"]["kind"], "battery");
+
+        let envelope: BackplaneEnvelope = serde_json::from_str(&payload).unwrap();
+        assert_eq!(envelope.origin, origin);
+        match envelope.event {
+            BackplaneEvent::Battery {
+                device_id: id,
+                data,
+            } => {
+                assert_eq!(id, device_id);
+                assert_eq!(data.battery_level, 42);
+                assert_eq!(data.component.as_deref(), Some("main"));
+                assert_eq!(data.power_saving_mode, PowerSavingMode::Low);
+            }
+            other => panic!("事件类型错误: {:?}", other),
+        }
+
+        let user_id = Uuid::new_v4();
+        let payload = encode(origin, alert_event(vec![user_id]));
+        let envelope: BackplaneEnvelope = serde_json::from_str(&payload).unwrap();
+        match envelope.event {
+            BackplaneEvent::Alert { user_ids, message } => {
+                assert_eq!(user_ids, vec![user_id]);
+                assert_eq!(message.alert_type, AlertType::HighTemperature);
+                assert_eq!(message.severity, AlertLevel::Critical);
+            }
+            other => panic!("事件类型错误: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_own_events_not_redispatched() {
+        let instance_id = Uuid::new_v4();
+        let payload = encode(instance_id, battery_event(Uuid::new_v4()));
+
+        assert!(remote_event(&payload, instance_id).is_none());
+    }
+
+    #[test]
+    fn test_remote_events_dispatched() {
+        let instance_id = Uuid::new_v4();
+        let payload = encode(Uuid::new_v4(), alert_event(vec![Uuid::new_v4()]));
+
+        assert!(matches!(
+            remote_event(&payload, instance_id),
+            Some(BackplaneEvent::Alert { .. })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_message_ignored() {
+        assert!(remote_event("not json", Uuid::new_v4()).is_none());
+        assert!(remote_event(r#"{"origin":"x"}"#, Uuid::new_v4()).is_none());
+    }
+}
@@ -3,6 +3,7 @@
 use crate::repositories::DeviceRepository;
 use crate::security::JwtManager;
 use crate::services::{BatteryService, DeviceAccessTokenService};
+use crate::websocket::hub::WsHub;
 use crate::websocket::session::WsSession;
 
 use actix_web::{web, Error, HttpRequest, HttpResponse};
@@ -49,6 +50,7 @@ pub async fn ws_handler(
     device_token_service: web::Data<Arc<DeviceAccessTokenService>>,
     jwt_manager: web::Data<Arc<JwtManager>>,
     device_repo: web::Data<Arc<DeviceRepository>>,
+    hub: web::Data<Arc<WsHub>>,
 ) -> Result<HttpResponse, Error> {
     let client_ip = get_client_ip(&req);
 
@@ -61,6 +63,7 @@ pub async fn ws_handler(
         device_token_service.get_ref().clone(),
         jwt_manager.get_ref().clone(),
         device_repo.get_ref().clone(),
+        hub.get_ref().clone(),
     );
 
     // 升级到 WebSocket 连接
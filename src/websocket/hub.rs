@@ -0,0 +1,374 @@
+//! WebSocket 会话注册中心
+//!
+//! 维护「设备 → 订阅会话」与「用户 → 预警订阅会话」的索引，
+//! 将电量上报和预警事件路由到对应的会话。
+//! 同一进程内的多个 worker 共享同一个 Hub 实例。
+
+use crate::models::{AlertEvent, LatestBatteryResponse};
+use crate::services::{AlertPushSender, BatteryPushSender};
+use crate::websocket::messages::AlertPushMessage;
+use crate::websocket::session::{PushAlert, PushBatteryData};
+
+use actix::Recipient;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use uuid::Uuid;
+
+/// WebSocket 会话注册中心
+#[derive(Default)]
+pub struct WsHub {
+    /// 设备 ID → (会话 ID → 会话推送地址)
+    device_subscribers: RwLock<HashMap<Uuid, HashMap<Uuid, Recipient<PushBatteryData>>>>,
+
+    /// 用户 ID → (会话 ID → 会话推送地址)，仅包含订阅了预警推送的会话
+    alert_subscribers: RwLock<HashMap<Uuid, HashMap<Uuid, Recipient<PushAlert>>>>,
+}
+
+impl WsHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记会话对设备的订阅
+    pub fn subscribe(
+        &self,
+        session_id: Uuid,
+        device_ids: &[Uuid],
+        recipient: Recipient<PushBatteryData>,
+    ) {
+        let mut subscribers = self
+            .device_subscribers
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+
+        for device_id in device_ids {
+            subscribers
+                .entry(*device_id)
+                .or_default()
+                .insert(session_id, recipient.clone());
+        }
+    }
+
+    /// 取消会话对指定设备的订阅
+    pub fn unsubscribe(&self, session_id: Uuid, device_ids: &[Uuid]) {
+        let mut subscribers = self
+            .device_subscribers
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+
+        for device_id in device_ids {
+            if let Some(sessions) = subscribers.get_mut(device_id) {
+                sessions.remove(&session_id);
+                if sessions.is_empty() {
+                    subscribers.remove(device_id);
+                }
+            }
+        }
+    }
+
+    /// 登记用户会话的预警订阅
+    pub fn subscribe_alerts(
+        &self,
+        session_id: Uuid,
+        user_id: Uuid,
+        recipient: Recipient<PushAlert>,
+    ) {
+        self.alert_subscribers
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(user_id)
+            .or_default()
+            .insert(session_id, recipient);
+    }
+
+    /// 取消用户会话的预警订阅
+    pub fn unsubscribe_alerts(&self, session_id: Uuid, user_id: Uuid) {
+        let mut subscribers = self
+            .alert_subscribers
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+
+        if let Some(sessions) = subscribers.get_mut(&user_id) {
+            sessions.remove(&session_id);
+            if sessions.is_empty() {
+                subscribers.remove(&user_id);
+            }
+        }
+    }
+
+    /// 移除会话的全部订阅（连接关闭时调用）
+    pub fn remove_session(&self, session_id: Uuid) {
+        self.device_subscribers
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|_, sessions| {
+                sessions.remove(&session_id);
+                !sessions.is_empty()
+            });
+
+        self.alert_subscribers
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|_, sessions| {
+                sessions.remove(&session_id);
+                !sessions.is_empty()
+            });
+    }
+
+    /// 将电量数据分发给订阅了该设备的本地会话
+    ///
+    /// 返回成功投递的会话数
+    pub fn dispatch_battery(&self, device_id: Uuid, data: &LatestBatteryResponse) -> usize {
+        let subscribers = self
+            .device_subscribers
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+
+        let sessions = match subscribers.get(&device_id) {
+            Some(s) => s,
+            None => return 0,
+        };
+
+        let mut delivered = 0;
+        for (session_id, recipient) in sessions {
+            // 会话邮箱已满或已关闭时丢弃，不阻塞上报链路
+            match recipient.try_send(PushBatteryData {
+                device_id,
+                data: data.clone(),
+            }) {
+                Ok(()) => delivered += 1,
+                Err(e) => tracing::debug!(
+                    session_id = %session_id,
+                    device_id = %device_id,
+                    error = %e,
+                    "电量推送投递失败"
+                ),
+            }
+        }
+
+        delivered
+    }
+
+    /// 将预警推送分发给指定用户订阅了预警的本地会话
+    ///
+    /// 返回成功投递的会话数
+    pub fn dispatch_alert(&self, user_ids: &[Uuid], message: &AlertPushMessage) -> usize {
+        let subscribers = self
+            .alert_subscribers
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+
+        let mut delivered = 0;
+        for user_id in user_ids {
+            let sessions = match subscribers.get(user_id) {
+                Some(s) => s,
+                None => continue,
+            };
+
+            for (session_id, recipient) in sessions {
+                match recipient.try_send(PushAlert {
+                    message: message.clone(),
+                }) {
+                    Ok(()) => delivered += 1,
+                    Err(e) => tracing::debug!(
+                        session_id = %session_id,
+                        alert_id = %message.alert_id,
+                        error = %e,
+                        "预警推送投递失败"
+                    ),
+                }
+            }
+        }
+
+        delivered
+    }
+}
+
+#[async_trait::async_trait]
+impl BatteryPushSender for WsHub {
+    async fn push_battery(&self, device_id: Uuid, data: &LatestBatteryResponse) {
+        let delivered = self.dispatch_battery(device_id, data);
+        if delivered > 0 {
+            tracing::debug!(device_id = %device_id, sessions = delivered, "电量数据已推送");
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AlertPushSender for WsHub {
+    async fn push_alert(&self, event: &AlertEvent, user_ids: &[Uuid]) {
+        let delivered = self.dispatch_alert(user_ids, &AlertPushMessage::from(event));
+        if delivered > 0 {
+            tracing::debug!(alert_id = %event.id, sessions = delivered, "预警已推送");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AlertLevel, AlertStatus, AlertType, PowerSavingMode};
+    use actix::{Actor, Addr, Context, Handler, Message};
+    use chrono::Utc;
+
+    /// 记录收到的推送（电量推送记录设备 ID，预警推送记录预警 ID）
+    #[derive(Default)]
+    struct Collector {
+        received: Vec<Uuid>,
+    }
+
+    impl Actor for Collector {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<PushBatteryData> for Collector {
+        type Result = ();
+
+        fn handle(&mut self, msg: PushBatteryData, _: &mut Self::Context) {
+            self.received.push(msg.device_id);
+        }
+    }
+
+    impl Handler<PushAlert> for Collector {
+        type Result = ();
+
+        fn handle(&mut self, msg: PushAlert, _: &mut Self::Context) {
+            self.received.push(msg.message.alert_id);
+        }
+    }
+
+    #[derive(Message)]
+    #[rtype(result = "Vec<Uuid>")]
+    struct TakeReceived;
+
+    impl Handler<TakeReceived> for Collector {
+        type Result = Vec<Uuid>;
+
+        fn handle(&mut self, _: TakeReceived, _: &mut Self::Context) -> Vec<Uuid> {
+            std::mem::take(&mut self.received)
+        }
+    }
+
+    /// 取出已收到的推送（邮箱按顺序处理，此前投递的推送都已处理完）
+    async fn received(addr: &Addr<Collector>) -> Vec<Uuid> {
+        addr.send(TakeReceived).await.unwrap()
+    }
+
+    fn battery(device_id: Uuid) -> LatestBatteryResponse {
+        LatestBatteryResponse {
+            device_id,
+            component: None,
+            battery_level: 80,
+            is_charging: false,
+            power_saving_mode: PowerSavingMode::Off,
+            recorded_at: Utc::now(),
+            is_low_battery: false,
+            is_critical: false,
+            estimate: None,
+            metrics: None,
+            components: None,
+        }
+    }
+
+    fn alert(device_id: Uuid) -> AlertPushMessage {
+        AlertPushMessage {
+            alert_id: Uuid::new_v4(),
+            device_id,
+            alert_type: AlertType::LowBattery,
+            message: "电量低".to_string(),
+            severity: AlertLevel::Warning,
+            status: AlertStatus::Active,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_dispatch_battery_to_subscribed_sessions() {
+        let hub = WsHub::new();
+        let (device_a, device_b) = (Uuid::new_v4(), Uuid::new_v4());
+        let (session_1, session_2) = (Uuid::new_v4(), Uuid::new_v4());
+        let collector_1 = Collector::default().start();
+        let collector_2 = Collector::default().start();
+
+        hub.subscribe(
+            session_1,
+            &[device_a, device_b],
+            collector_1.clone().recipient(),
+        );
+        hub.subscribe(session_2, &[device_a], collector_2.clone().recipient());
+
+        assert_eq!(hub.dispatch_battery(device_a, &battery(device_a)), 2);
+        assert_eq!(hub.dispatch_battery(device_b, &battery(device_b)), 1);
+        assert_eq!(hub.dispatch_battery(Uuid::new_v4(), &battery(device_a)), 0);
+
+        assert_eq!(received(&collector_1).await, vec![device_a, device_b]);
+        assert_eq!(received(&collector_2).await, vec![device_a]);
+    }
+
+    #[actix_rt::test]
+    async fn test_unsubscribe_only_removes_given_devices() {
+        let hub = WsHub::new();
+        let (device_a, device_b) = (Uuid::new_v4(), Uuid::new_v4());
+        let session = Uuid::new_v4();
+        let collector = Collector::default().start();
+
+        hub.subscribe(
+            session,
+            &[device_a, device_b],
+            collector.clone().recipient(),
+        );
+        hub.unsubscribe(session, &[device_a]);
+
+        assert_eq!(hub.dispatch_battery(device_a, &battery(device_a)), 0);
+        assert_eq!(hub.dispatch_battery(device_b, &battery(device_b)), 1);
+        assert!(!hub
+            .device_subscribers
+            .read()
+            .unwrap()
+            .contains_key(&device_a));
+        assert_eq!(received(&collector).await, vec![device_b]);
+    }
+
+    #[actix_rt::test]
+    async fn test_dispatch_alert_to_subscribed_users() {
+        let hub = WsHub::new();
+        let (user_a, user_b) = (Uuid::new_v4(), Uuid::new_v4());
+        let (session_1, session_2) = (Uuid::new_v4(), Uuid::new_v4());
+        let collector_1 = Collector::default().start();
+        let collector_2 = Collector::default().start();
+
+        hub.subscribe_alerts(session_1, user_a, collector_1.clone().recipient());
+        hub.subscribe_alerts(session_2, user_b, collector_2.clone().recipient());
+
+        let message = alert(Uuid::new_v4());
+        assert_eq!(hub.dispatch_alert(&[user_a], &message), 1);
+        assert_eq!(received(&collector_1).await, vec![message.alert_id]);
+        assert!(received(&collector_2).await.is_empty());
+
+        hub.unsubscribe_alerts(session_1, user_a);
+        assert_eq!(hub.dispatch_alert(&[user_a, user_b], &message), 1);
+        assert!(received(&collector_1).await.is_empty());
+        assert_eq!(received(&collector_2).await, vec![message.alert_id]);
+    }
+
+    #[actix_rt::test]
+    async fn test_remove_session_clears_all_subscriptions() {
+        let hub = WsHub::new();
+        let (device_id, user_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let (session, other_session) = (Uuid::new_v4(), Uuid::new_v4());
+        let collector = Collector::default().start();
+        let other = Collector::default().start();
+
+        hub.subscribe(session, &[device_id], collector.clone().recipient());
+        hub.subscribe_alerts(session, user_id, collector.clone().recipient());
+        hub.subscribe(other_session, &[device_id], other.clone().recipient());
+
+        hub.remove_session(session);
+
+        assert_eq!(hub.dispatch_battery(device_id, &battery(device_id)), 1);
+        assert_eq!(hub.dispatch_alert(&[user_id], &alert(device_id)), 0);
+        assert!(hub.alert_subscribers.read().unwrap().is_empty());
+        assert!(received(&collector).await.is_empty());
+        assert_eq!(received(&other).await, vec![device_id]);
+    }
+}
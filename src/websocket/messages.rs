@@ -2,7 +2,10 @@
 //!
 //! 定义客户端和服务器之间的消息协议
 
-use crate::models::{BatteryData, LatestBatteryResponse, PowerSavingMode};
+use crate::models::{
+    AlertEvent, AlertLevel, AlertStatus, AlertType, BatteryData, LatestBatteryResponse,
+    PowerSavingMode, RejectedBatteryRecord,
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -122,6 +125,18 @@ pub struct BatteryReportMessage {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub recorded_at: Option<DateTime<Utc>>,
 
+    /// 客户端样本 ID（可选，重试时保持不变，用于去重）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_id: Option<String>,
+
+    /// 自定义指标（可选，键须为设备类型已注册的指标）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<serde_json::Map<String, serde_json::Value>>,
+
+    /// 电池组件（多电池设备必填）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
+
     /// 消息 ID（可选，用于追踪请求响应）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub msg_id: Option<String>,
@@ -133,6 +148,14 @@ pub struct BatchBatteryReportMessage {
     /// 批量数据
     pub data: Vec<BatteryReportMessage>,
 
+    /// 记录时间早于该秒数的样本不发送预警通知（可选）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_max_age_seconds: Option<i64>,
+
+    /// 设备发送时间（可选，用于测量设备时钟偏差）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sent_at: Option<DateTime<Utc>>,
+
     /// 消息 ID（可选）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub msg_id: Option<String>,
@@ -156,6 +179,12 @@ pub struct BatchReportResultMessage {
     pub success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub inserted_count: Option<usize>,
+    /// 重复样本数（已确认接收，未重复写入）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_count: Option<usize>,
+    /// 被拒绝的记录（下标与原因）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<Vec<RejectedBatteryRecord>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -166,15 +195,24 @@ pub struct BatchReportResultMessage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubscribeMessage {
     /// 要订阅的设备 ID 列表
+    #[serde(default)]
     pub device_ids: Vec<Uuid>,
+
+    /// 是否订阅预警推送（用户拥有或被共享的所有设备）
+    #[serde(default)]
+    pub alerts: bool,
 }
 
 /// 取消订阅消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnsubscribeMessage {
-    /// 要取消订阅的设备 ID 列表（为空则取消所有订阅）
+    /// 要取消订阅的设备 ID 列表（为空且不取消预警推送时取消所有订阅）
     #[serde(default)]
     pub device_ids: Vec<Uuid>,
+
+    /// 是否取消预警推送（设备列表为空时只取消预警推送）
+    #[serde(default)]
+    pub alerts: bool,
 }
 
 /// 订阅结果
@@ -182,6 +220,9 @@ pub struct UnsubscribeMessage {
 pub struct SubscribeResultMessage {
     pub success: bool,
     pub subscribed_devices: Vec<Uuid>,
+    /// 是否已订阅预警推送
+    #[serde(default)]
+    pub alerts: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
 }
@@ -193,16 +234,39 @@ pub struct BatteryPushMessage {
     pub data: LatestBatteryResponse,
 }
 
-/// 预警推送
+/// 预警推送（新预警及状态变更）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AlertPushMessage {
+    pub alert_id: Uuid,
     pub device_id: Uuid,
-    pub alert_type: String,
+    pub alert_type: AlertType,
     pub message: String,
-    pub severity: String,
+    pub severity: AlertLevel,
+    pub status: AlertStatus,
+    /// 事件发生时间（触发、确认或解决时间）
     pub timestamp: DateTime<Utc>,
 }
 
+impl From<&AlertEvent> for AlertPushMessage {
+    fn from(event: &AlertEvent) -> Self {
+        let timestamp = match event.status {
+            AlertStatus::Active => event.triggered_at,
+            AlertStatus::Acknowledged => event.acknowledged_at.unwrap_or(event.triggered_at),
+            AlertStatus::Resolved => event.resolved_at.unwrap_or(event.triggered_at),
+        };
+
+        Self {
+            alert_id: event.id,
+            device_id: event.device_id,
+            alert_type: event.alert_type.clone(),
+            message: event.message.clone(),
+            severity: event.level.clone(),
+            status: event.status.clone(),
+            timestamp,
+        }
+    }
+}
+
 /// 错误消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorMessage {
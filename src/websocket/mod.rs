@@ -3,12 +3,18 @@
 //! 提供 WebSocket 支持，用于：
 //! - 设备实时电量上报
 //! - 用户订阅设备数据推送
+//! - 预警事件实时推送
 //! - 低延迟双向通信
+//! - 多实例部署下通过 Redis Pub/Sub 跨实例推送
 
+mod backplane;
 mod handler;
+mod hub;
 mod messages;
 mod session;
 
+pub use backplane::{BackplaneEvent, RedisBackplane};
 pub use handler::{configure as configure_ws_routes, ws_handler};
+pub use hub::WsHub;
 pub use messages::*;
-pub use session::WsSession;
+pub use session::{PushAlert, PushBatteryData, WsSession};
@@ -2,14 +2,16 @@
 //!
 //! 每个 WebSocket 连接对应一个 Actor 实例，负责处理消息收发和状态管理
 
-use crate::models::BatteryReportRequest;
+use crate::models::{BatchReportResponse, BatteryReportRequest};
 use crate::repositories::DeviceRepository;
 use crate::security::JwtManager;
 use crate::services::{BatteryService, DeviceAccessTokenService};
+use crate::websocket::hub::WsHub;
 use crate::websocket::messages::*;
 
 use actix::{
-    Actor, ActorContext, ActorFutureExt, AsyncContext, Handler, Message, Running, StreamHandler,
+    Actor, ActorContext, ActorFutureExt, AsyncContext, Handler, Message, Recipient, Running,
+    StreamHandler,
 };
 use actix_web_actors::ws;
 use chrono::Utc;
@@ -39,6 +41,71 @@ pub enum ConnectionState {
     Closed,
 }
 
+/// 会话的订阅状态，变更时同步更新 Hub 中的索引
+#[derive(Debug, Default)]
+pub struct Subscriptions {
+    /// 订阅的设备列表
+    pub devices: HashSet<Uuid>,
+
+    /// 是否订阅了预警推送
+    pub alerts: bool,
+}
+
+impl Subscriptions {
+    /// 登记设备订阅，`alerts` 为 true 时同时订阅该用户的预警推送
+    #[allow(clippy::too_many_arguments)]
+    pub fn subscribe(
+        &mut self,
+        hub: &WsHub,
+        session_id: Uuid,
+        user_id: Uuid,
+        device_ids: &[Uuid],
+        alerts: bool,
+        battery_recipient: Recipient<PushBatteryData>,
+        alert_recipient: Recipient<PushAlert>,
+    ) {
+        self.devices.extend(device_ids);
+        hub.subscribe(session_id, device_ids, battery_recipient);
+
+        if alerts {
+            self.alerts = true;
+            hub.subscribe_alerts(session_id, user_id, alert_recipient);
+        }
+    }
+
+    /// 取消订阅
+    ///
+    /// 设备列表为空且不取消预警推送时取消全部订阅；设备列表为空且取消预警推送时只取消预警推送
+    pub fn unsubscribe(
+        &mut self,
+        hub: &WsHub,
+        session_id: Uuid,
+        user_id: Option<Uuid>,
+        unsub: &UnsubscribeMessage,
+    ) {
+        if unsub.device_ids.is_empty() && !unsub.alerts {
+            // 取消所有订阅（包括预警推送）
+            self.devices.clear();
+            self.alerts = false;
+            hub.remove_session(session_id);
+            return;
+        }
+
+        // 取消指定订阅（设备列表为空时只取消预警推送）
+        for device_id in &unsub.device_ids {
+            self.devices.remove(device_id);
+        }
+        hub.unsubscribe(session_id, &unsub.device_ids);
+
+        if unsub.alerts {
+            self.alerts = false;
+            if let Some(user_id) = user_id {
+                hub.unsubscribe_alerts(session_id, user_id);
+            }
+        }
+    }
+}
+
 /// WebSocket 连接 Session
 pub struct WsSession {
     /// 连接唯一 ID
@@ -59,8 +126,8 @@ pub struct WsSession {
     /// 用户 ID（用户认证后设置）
     pub user_id: Option<Uuid>,
 
-    /// 用户订阅的设备列表
-    pub subscribed_devices: HashSet<Uuid>,
+    /// 用户的设备和预警订阅
+    pub subscriptions: Subscriptions,
 
     /// 客户端 IP
     pub client_ip: Option<String>,
@@ -70,6 +137,7 @@ pub struct WsSession {
     pub device_token_service: Arc<DeviceAccessTokenService>,
     pub jwt_manager: Arc<JwtManager>,
     pub device_repo: Arc<DeviceRepository>,
+    pub hub: Arc<WsHub>,
 }
 
 impl WsSession {
@@ -79,6 +147,7 @@ impl WsSession {
         device_token_service: Arc<DeviceAccessTokenService>,
         jwt_manager: Arc<JwtManager>,
         device_repo: Arc<DeviceRepository>,
+        hub: Arc<WsHub>,
     ) -> Self {
         Self {
             id: Uuid::new_v4(),
@@ -87,12 +156,13 @@ impl WsSession {
             state: ConnectionState::WaitingAuth,
             device_id: None,
             user_id: None,
-            subscribed_devices: HashSet::new(),
+            subscriptions: Subscriptions::default(),
             client_ip,
             battery_service,
             device_token_service,
             jwt_manager,
             device_repo,
+            hub,
         }
     }
 
@@ -271,6 +341,10 @@ impl WsSession {
             temperature: report.temperature,
             voltage: report.voltage,
             recorded_at: report.recorded_at,
+            sample_id: report.sample_id,
+            metrics: report.metrics,
+            component: report.component,
+            clock: None,
         };
 
         let fut = async move { battery_service.report(device_id, request).await };
@@ -316,6 +390,8 @@ impl WsSession {
                     ServerMessage::BatchBatteryReportResult(BatchReportResultMessage {
                         success: false,
                         inserted_count: None,
+                        duplicate_count: None,
+                        rejected: None,
                         error: Some("只有设备可以上报电量数据".to_string()),
                         msg_id: batch.msg_id.clone(),
                     }),
@@ -331,6 +407,8 @@ impl WsSession {
                 ServerMessage::BatchBatteryReportResult(BatchReportResultMessage {
                     success: false,
                     inserted_count: None,
+                    duplicate_count: None,
+                    rejected: None,
                     error: Some("批量数据不能为空".to_string()),
                     msg_id: batch.msg_id.clone(),
                 }),
@@ -338,21 +416,10 @@ impl WsSession {
             return;
         }
 
-        if batch.data.len() > 1000 {
-            self.send_message(
-                ctx,
-                ServerMessage::BatchBatteryReportResult(BatchReportResultMessage {
-                    success: false,
-                    inserted_count: None,
-                    error: Some("批量数据条数不能超过 1000".to_string()),
-                    msg_id: batch.msg_id.clone(),
-                }),
-            );
-            return;
-        }
-
         let battery_service = self.battery_service.clone();
         let msg_id = batch.msg_id.clone();
+        let notify_max_age_seconds = batch.notify_max_age_seconds;
+        let sent_at = batch.sent_at;
 
         // 转换为上报请求列表
         let requests: Vec<BatteryReportRequest> = batch
@@ -365,24 +432,38 @@ impl WsSession {
                 temperature: r.temperature,
                 voltage: r.voltage,
                 recorded_at: r.recorded_at,
+                sample_id: r.sample_id,
+                metrics: r.metrics,
+                component: r.component,
+                clock: None,
             })
             .collect();
 
-        let fut = async move { battery_service.batch_report(device_id, requests).await };
+        let fut = async move {
+            battery_service
+                .batch_report(device_id, requests, notify_max_age_seconds, sent_at)
+                .await
+        };
 
         ctx.spawn(actix::fut::wrap_future(fut).map(
-            move |result: Result<usize, crate::errors::AppError>, act: &mut Self, ctx| match result
-            {
-                Ok(count) => {
+            move |result: Result<BatchReportResponse, crate::errors::AppError>,
+                  act: &mut Self,
+                  ctx| match result {
+                Ok(result) => {
                     debug!(
-                        "WebSocket 批量上报成功: device={}, count={}",
-                        device_id, count
+                        "WebSocket 批量上报成功: device={}, inserted={}, duplicates={}, rejected={}",
+                        device_id,
+                        result.inserted_count,
+                        result.duplicate_count,
+                        result.rejected_count
                     );
                     act.send_message(
                         ctx,
                         ServerMessage::BatchBatteryReportResult(BatchReportResultMessage {
                             success: true,
-                            inserted_count: Some(count),
+                            inserted_count: Some(result.inserted_count),
+                            duplicate_count: Some(result.duplicate_count),
+                            rejected: Some(result.rejected),
                             error: None,
                             msg_id,
                         }),
@@ -395,6 +476,8 @@ impl WsSession {
                         ServerMessage::BatchBatteryReportResult(BatchReportResultMessage {
                             success: false,
                             inserted_count: None,
+                            duplicate_count: None,
+                            rejected: None,
                             error: Some(e.to_string()),
                             msg_id,
                         }),
@@ -421,6 +504,7 @@ impl WsSession {
                     ServerMessage::SubscribeResult(SubscribeResultMessage {
                         success: false,
                         subscribed_devices: vec![],
+                        alerts: false,
                         error: Some("只有用户可以订阅设备数据".to_string()),
                     }),
                 );
@@ -430,6 +514,7 @@ impl WsSession {
 
         let device_repo = self.device_repo.clone();
         let device_ids = sub.device_ids.clone();
+        let subscribe_alerts = sub.alerts;
 
         // 验证用户是否有权访问这些设备
         let fut = async move {
@@ -447,10 +532,10 @@ impl WsSession {
         };
 
         ctx.spawn(actix::fut::wrap_future(fut).map(
-            |accessible_devices: Vec<Uuid>, act: &mut Self, ctx| {
+            move |accessible_devices: Vec<Uuid>, act: &mut Self, ctx| {
                 // 检查订阅数量限制
                 let new_subscriptions = accessible_devices.len();
-                let current_subscriptions = act.subscribed_devices.len();
+                let current_subscriptions = act.subscriptions.devices.len();
 
                 if current_subscriptions + new_subscriptions > MAX_SUBSCRIBED_DEVICES {
                     act.send_message(
@@ -458,6 +543,7 @@ impl WsSession {
                         ServerMessage::SubscribeResult(SubscribeResultMessage {
                             success: false,
                             subscribed_devices: vec![],
+                            alerts: act.subscriptions.alerts,
                             error: Some(format!(
                                 "订阅设备数量超过限制 (最大 {})",
                                 MAX_SUBSCRIBED_DEVICES
@@ -467,10 +553,16 @@ impl WsSession {
                     return;
                 }
 
-                // 添加到订阅列表
-                for device_id in &accessible_devices {
-                    act.subscribed_devices.insert(*device_id);
-                }
+                // 添加到订阅列表并登记到 Hub（预警推送范围为用户拥有或被共享的设备）
+                act.subscriptions.subscribe(
+                    &act.hub,
+                    act.id,
+                    user_id,
+                    &accessible_devices,
+                    subscribe_alerts,
+                    ctx.address().recipient(),
+                    ctx.address().recipient(),
+                );
 
                 info!(
                     "用户 {} 订阅了 {} 个设备",
@@ -483,6 +575,7 @@ impl WsSession {
                     ServerMessage::SubscribeResult(SubscribeResultMessage {
                         success: true,
                         subscribed_devices: accessible_devices,
+                        alerts: act.subscriptions.alerts,
                         error: None,
                     }),
                 );
@@ -496,21 +589,15 @@ impl WsSession {
         ctx: &mut ws::WebsocketContext<Self>,
         unsub: UnsubscribeMessage,
     ) {
-        if unsub.device_ids.is_empty() {
-            // 取消所有订阅
-            self.subscribed_devices.clear();
-        } else {
-            // 取消指定订阅
-            for device_id in unsub.device_ids {
-                self.subscribed_devices.remove(&device_id);
-            }
-        }
+        self.subscriptions
+            .unsubscribe(&self.hub, self.id, self.user_id, &unsub);
 
         self.send_message(
             ctx,
             ServerMessage::SubscribeResult(SubscribeResultMessage {
                 success: true,
-                subscribed_devices: self.subscribed_devices.iter().cloned().collect(),
+                subscribed_devices: self.subscriptions.devices.iter().cloned().collect(),
+                alerts: self.subscriptions.alerts,
                 error: None,
             }),
         );
@@ -586,6 +673,7 @@ impl Actor for WsSession {
     fn stopping(&mut self, _: &mut Self::Context) -> Running {
         info!("WebSocket 连接关闭: session={}", self.id);
         self.state = ConnectionState::Closed;
+        self.hub.remove_session(self.id);
         Running::Stop
     }
 }
@@ -655,7 +743,7 @@ impl Handler<PushBatteryData> for WsSession {
 
     fn handle(&mut self, msg: PushBatteryData, ctx: &mut Self::Context) {
         // 检查是否订阅了该设备
-        if self.subscribed_devices.contains(&msg.device_id) {
+        if self.subscriptions.devices.contains(&msg.device_id) {
             self.send_message(
                 ctx,
                 ServerMessage::BatteryPush(BatteryPushMessage {
@@ -666,3 +754,181 @@ impl Handler<PushBatteryData> for WsSession {
         }
     }
 }
+
+/// 用于向 Session 推送预警的消息
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct PushAlert {
+    pub message: AlertPushMessage,
+}
+
+impl Handler<PushAlert> for WsSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: PushAlert, ctx: &mut Self::Context) {
+        if self.subscriptions.alerts {
+            self.send_message(ctx, ServerMessage::AlertPush(msg.message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AlertLevel, AlertStatus, AlertType, LatestBatteryResponse};
+
+    /// 只接收推送的空 Actor，投递结果通过 Hub 的分发计数判断
+    struct Sink;
+
+    impl Actor for Sink {
+        type Context = actix::Context<Self>;
+    }
+
+    impl Handler<PushBatteryData> for Sink {
+        type Result = ();
+
+        fn handle(&mut self, _: PushBatteryData, _: &mut Self::Context) {}
+    }
+
+    impl Handler<PushAlert> for Sink {
+        type Result = ();
+
+        fn handle(&mut self, _: PushAlert, _: &mut Self::Context) {}
+    }
+
+    fn battery(device_id: Uuid) -> LatestBatteryResponse {
+        LatestBatteryResponse {
+            device_id,
+            component: None,
+            battery_level: 80,
+            is_charging: false,
+            power_saving_mode: Default::default(),
+            recorded_at: Utc::now(),
+            is_low_battery: false,
+            is_critical: false,
+            estimate: None,
+            metrics: None,
+            components: None,
+        }
+    }
+
+    fn alert() -> AlertPushMessage {
+        AlertPushMessage {
+            alert_id: Uuid::new_v4(),
+            device_id: Uuid::new_v4(),
+            alert_type: AlertType::LowBattery,
+            message: "电量低".to_string(),
+            severity: AlertLevel::Warning,
+            status: AlertStatus::Active,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn unsubscribe(device_ids: Vec<Uuid>, alerts: bool) -> UnsubscribeMessage {
+        UnsubscribeMessage { device_ids, alerts }
+    }
+
+    /// 订阅指定设备和预警推送
+    fn subscribed(
+        hub: &WsHub,
+        session_id: Uuid,
+        user_id: Uuid,
+        device_ids: &[Uuid],
+    ) -> Subscriptions {
+        let sink = Sink.start();
+        let mut subscriptions = Subscriptions::default();
+        subscriptions.subscribe(
+            hub,
+            session_id,
+            user_id,
+            device_ids,
+            true,
+            sink.clone().recipient(),
+            sink.recipient(),
+        );
+        subscriptions
+    }
+
+    #[actix_rt::test]
+    async fn test_alert_only_subscribe_and_unsubscribe() {
+        let hub = WsHub::new();
+        let (session_id, user_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let mut subscriptions = subscribed(&hub, session_id, user_id, &[]);
+        assert!(subscriptions.alerts);
+        assert!(subscriptions.devices.is_empty());
+        assert_eq!(hub.dispatch_alert(&[user_id], &alert()), 1);
+
+        subscriptions.unsubscribe(&hub, session_id, Some(user_id), &unsubscribe(vec![], true));
+        assert!(!subscriptions.alerts);
+        assert_eq!(hub.dispatch_alert(&[user_id], &alert()), 0);
+    }
+
+    #[actix_rt::test]
+    async fn test_alert_only_unsubscribe_keeps_devices() {
+        let hub = WsHub::new();
+        let (session_id, user_id, device_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut subscriptions = subscribed(&hub, session_id, user_id, &[device_id]);
+
+        subscriptions.unsubscribe(&hub, session_id, Some(user_id), &unsubscribe(vec![], true));
+
+        assert!(!subscriptions.alerts);
+        assert!(subscriptions.devices.contains(&device_id));
+        assert_eq!(hub.dispatch_alert(&[user_id], &alert()), 0);
+        assert_eq!(hub.dispatch_battery(device_id, &battery(device_id)), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_unsubscribe_devices_and_alerts_together() {
+        let hub = WsHub::new();
+        let (session_id, user_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let (device_a, device_b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut subscriptions = subscribed(&hub, session_id, user_id, &[device_a, device_b]);
+
+        subscriptions.unsubscribe(
+            &hub,
+            session_id,
+            Some(user_id),
+            &unsubscribe(vec![device_a], true),
+        );
+
+        assert!(!subscriptions.alerts);
+        assert_eq!(subscriptions.devices, HashSet::from([device_b]));
+        assert_eq!(hub.dispatch_alert(&[user_id], &alert()), 0);
+        assert_eq!(hub.dispatch_battery(device_a, &battery(device_a)), 0);
+        assert_eq!(hub.dispatch_battery(device_b, &battery(device_b)), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_unsubscribe_devices_keeps_alerts() {
+        let hub = WsHub::new();
+        let (session_id, user_id, device_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut subscriptions = subscribed(&hub, session_id, user_id, &[device_id]);
+
+        subscriptions.unsubscribe(
+            &hub,
+            session_id,
+            Some(user_id),
+            &unsubscribe(vec![device_id], false),
+        );
+
+        assert!(subscriptions.alerts);
+        assert!(subscriptions.devices.is_empty());
+        assert_eq!(hub.dispatch_alert(&[user_id], &alert()), 1);
+        assert_eq!(hub.dispatch_battery(device_id, &battery(device_id)), 0);
+    }
+
+    #[actix_rt::test]
+    async fn test_empty_unsubscribe_removes_everything() {
+        let hub = WsHub::new();
+        let (session_id, user_id, device_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut subscriptions = subscribed(&hub, session_id, user_id, &[device_id]);
+
+        subscriptions.unsubscribe(&hub, session_id, Some(user_id), &unsubscribe(vec![], false));
+
+        assert!(!subscriptions.alerts);
+        assert!(subscriptions.devices.is_empty());
+        assert_eq!(hub.dispatch_alert(&[user_id], &alert()), 0);
+        assert_eq!(hub.dispatch_battery(device_id, &battery(device_id)), 0);
+    }
+}
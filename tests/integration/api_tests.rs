@@ -90,3 +90,56 @@ mod device_creation {
         // TODO: 实现用户绑定测试
     }
 }
+
+mod alert_auto_resolve {
+
+    #[actix_web::test]
+    #[ignore = "需要数据库连接"]
+    async fn test_low_battery_resolves_after_hysteresis() {
+        // 低电量预警（阈值 20，回差 5）触发后：
+        // POST /api/v1/battery/report level=22 → 预警保持 active
+        // POST /api/v1/battery/report level=25 → 预警 resolved，resolved_at 为记录时间
+        // TODO: 实现回差解决测试
+    }
+
+    #[actix_web::test]
+    #[ignore = "需要数据库连接"]
+    async fn test_other_component_does_not_resolve() {
+        // 组件 main 的低电量预警不会被组件 aux 的高电量数据解决
+        // TODO: 实现多电池组件解决测试
+    }
+
+    #[actix_web::test]
+    #[ignore = "需要数据库连接"]
+    async fn test_resolution_notification_queued() {
+        // 预警自动解决后发件箱写入 event_type = alert_resolved 的记录，且不受频率限制
+        // TODO: 实现解决通知测试
+    }
+}
+
+mod device_offline {
+
+    #[actix_web::test]
+    #[ignore = "需要数据库连接"]
+    async fn test_stale_device_marked_offline() {
+        // 上报间隔 60 秒、宽限倍数 3 的在线设备：
+        // last_seen_at 为 2 分钟前 → DeviceMonitorService::check_offline_devices 后仍为 online
+        // last_seen_at 为 4 分钟前 → 标记为 offline，并触发 device_offline 预警
+        // TODO: 实现离线检测测试
+    }
+
+    #[actix_web::test]
+    #[ignore = "需要数据库连接"]
+    async fn test_report_restores_online_and_resolves_offline_alert() {
+        // 离线设备重新上报 POST /api/v1/battery/report：
+        // 设备状态恢复为 online，未解决的 device_offline 预警自动解决
+        // TODO: 实现离线恢复测试
+    }
+
+    #[actix_web::test]
+    #[ignore = "需要数据库连接"]
+    async fn test_concurrent_checks_mark_device_once() {
+        // 两个实例同时执行离线检测，每台设备只被标记一次、只触发一条离线预警
+        // TODO: 实现并发检测测试
+    }
+}
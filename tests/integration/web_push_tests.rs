@@ -2,8 +2,6 @@
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[test]
     fn test_parse_web_push_subscription() {
         let json = r#"{
@@ -20,15 +18,13 @@ mod tests {
 
     #[test]
     fn test_web_push_config_validation() {
-        use crate::models::WebPushNotificationConfig;
+        use zinnia::models::WebPushNotificationConfig;
 
         let config = WebPushNotificationConfig {
             enabled: true,
-            subscriptions: vec![],
         };
 
         assert!(config.enabled);
-        assert_eq!(config.subscriptions.len(), 0);
     }
 }
 
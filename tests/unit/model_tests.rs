@@ -1,8 +1,95 @@
 //! 模型单元测试
 
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use uuid::Uuid;
-use zinnia::models::{CreateAccessTokenRequest, DeviceAccessToken, TokenPermission};
+use zinnia::models::{
+    BatteryData, CreateAccessTokenRequest, DeviceAccessToken, PowerSavingMode, TokenPermission,
+};
+
+/// 测试用电量数据构造器
+///
+/// 默认为单电池设备、未充电、电量 80%，记录时间为基准时间 2026-01-12T00:00:00Z，
+/// `minute` 按分钟偏移基准时间，便于构造按时间排列的序列
+struct BatteryDataBuilder(BatteryData);
+
+fn battery_data() -> BatteryDataBuilder {
+    BatteryDataBuilder(BatteryData {
+        id: Uuid::new_v4(),
+        device_id: Uuid::nil(),
+        component: String::new(),
+        battery_level: 80,
+        is_charging: false,
+        power_saving_mode: PowerSavingMode::Off,
+        temperature: None,
+        voltage: None,
+        recorded_at: BatteryDataBuilder::base_time(),
+        created_at: Utc::now(),
+        sample_id: None,
+        metrics: None,
+        clock_flag: None,
+        device_recorded_at: None,
+    })
+}
+
+impl BatteryDataBuilder {
+    fn base_time() -> DateTime<Utc> {
+        "2026-01-12T00:00:00Z".parse().unwrap()
+    }
+
+    fn minute(mut self, minutes: i64) -> Self {
+        self.0.recorded_at = Self::base_time() + Duration::minutes(minutes);
+        self
+    }
+
+    fn recorded_at(mut self, rfc3339: &str) -> Self {
+        self.0.recorded_at = rfc3339.parse().unwrap();
+        self
+    }
+
+    fn level(mut self, level: i32) -> Self {
+        self.0.battery_level = level;
+        self
+    }
+
+    fn charging(mut self, is_charging: bool) -> Self {
+        self.0.is_charging = is_charging;
+        self
+    }
+
+    fn mode(mut self, mode: PowerSavingMode) -> Self {
+        self.0.power_saving_mode = mode;
+        self
+    }
+
+    fn temperature(mut self, temperature: Option<f64>) -> Self {
+        self.0.temperature = temperature;
+        self
+    }
+
+    fn voltage(mut self, voltage: f64) -> Self {
+        self.0.voltage = Some(voltage);
+        self
+    }
+
+    fn component(mut self, component: &str) -> Self {
+        self.0.component = component.to_string();
+        self
+    }
+
+    fn sample_id(mut self, sample_id: &str) -> Self {
+        self.0.sample_id = Some(sample_id.to_string());
+        self
+    }
+
+    fn metrics(mut self, metrics: serde_json::Value) -> Self {
+        self.0.metrics = Some(metrics);
+        self
+    }
+
+    fn build(self) -> BatteryData {
+        self.0
+    }
+}
 
 mod device_access_token {
     use super::*;
@@ -216,3 +303,1847 @@ mod create_access_token_request {
         assert!(request.validate().is_ok(), "无过期时间应验证通过");
     }
 }
+
+mod discharge_rate {
+    use super::*;
+    use zinnia::models::discharge_rate_per_hour;
+
+    fn sample(minutes: i64, level: i32, is_charging: bool) -> BatteryData {
+        battery_data()
+            .minute(minutes)
+            .level(level)
+            .charging(is_charging)
+            .build()
+    }
+
+    #[test]
+    fn test_steady_discharge() {
+        let samples = vec![
+            sample(0, 80, false),
+            sample(15, 75, false),
+            sample(30, 70, false),
+        ];
+        let rate = discharge_rate_per_hour(&samples, 10).unwrap();
+        assert!((rate - 20.0).abs() < 1e-9, "30 分钟下降 10% 应为 20%/小时");
+    }
+
+    #[test]
+    fn test_ignores_charging_period() {
+        let samples = vec![
+            sample(0, 80, false),
+            sample(10, 75, false),
+            sample(20, 90, true),
+            sample(30, 95, true),
+            sample(40, 94, false),
+            sample(50, 89, false),
+        ];
+        let rate = discharge_rate_per_hour(&samples, 10).unwrap();
+        assert!((rate - 30.0).abs() < 1e-9, "充电区间不应计入放电速率");
+    }
+
+    #[test]
+    fn test_insufficient_discharge_time() {
+        let samples = vec![sample(0, 80, false), sample(3, 78, false)];
+        assert!(
+            discharge_rate_per_hour(&samples, 10).is_none(),
+            "放电时长不足应返回 None"
+        );
+        assert!(
+            discharge_rate_per_hour(&samples[..1], 1).is_none(),
+            "单个样本应返回 None"
+        );
+    }
+}
+
+mod lttb {
+    use super::*;
+    use zinnia::models::downsample_lttb;
+
+    fn series(levels: &[i32]) -> Vec<BatteryData> {
+        levels
+            .iter()
+            .enumerate()
+            .map(|(i, level)| battery_data().minute(i as i64).level(*level).build())
+            .collect()
+    }
+
+    #[test]
+    fn test_small_series_unchanged() {
+        let data = series(&[80, 79, 78]);
+        assert_eq!(downsample_lttb(data, 10).len(), 3);
+    }
+
+    #[test]
+    fn test_bounded_and_keeps_endpoints() {
+        let levels: Vec<i32> = (0..1000).map(|i| 100 - i / 10).collect();
+        let data = series(&levels);
+        let first = data[0].id;
+        let last = data[999].id;
+
+        let sampled = downsample_lttb(data, 50);
+
+        assert_eq!(sampled.len(), 50);
+        assert_eq!(sampled[0].id, first);
+        assert_eq!(sampled[49].id, last);
+        assert!(sampled
+            .windows(2)
+            .all(|w| w[0].recorded_at < w[1].recorded_at));
+    }
+
+    #[test]
+    fn test_preserves_spike_and_drop() {
+        let mut levels = vec![50; 1000];
+        levels[300] = 95;
+        levels[700] = 5;
+
+        let sampled = downsample_lttb(series(&levels), 20);
+
+        assert!(sampled.iter().any(|d| d.battery_level == 95));
+        assert!(sampled.iter().any(|d| d.battery_level == 5));
+    }
+
+    #[test]
+    fn test_max_points_validation() {
+        use validator::Validate;
+        use zinnia::models::BatteryQueryRequest;
+
+        let query = |max_points: &str| {
+            serde_json::from_str::<BatteryQueryRequest>(&format!(
+                r#"{{"start_time": "2026-01-01T00:00:00Z", "end_time": "2026-01-02T00:00:00Z"{}}}"#,
+                max_points
+            ))
+            .unwrap()
+        };
+
+        assert!(query("").max_points.is_none());
+        assert!(query(r#", "max_points": 500"#).validate().is_ok());
+        assert!(query(r#", "max_points": 2"#).validate().is_err());
+        assert!(query(r#", "max_points": 5001"#).validate().is_err());
+    }
+}
+
+mod history_query {
+    use super::*;
+    use zinnia::models::{
+        BatteryQueryRequest, BatteryStreamRequest, HistoryCursor, HistoryPagination,
+    };
+
+    fn query(extra: &str) -> BatteryQueryRequest {
+        serde_json::from_str(&format!(
+            r#"{{"start_time": "2026-01-01T00:00:00Z", "end_time": "2026-01-02T00:00:00Z"{}}}"#,
+            extra
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_cursor_roundtrip() {
+        let cursor = HistoryCursor {
+            recorded_at: "2026-01-12T10:30:00.123456Z".parse().unwrap(),
+            id: Uuid::new_v4(),
+        };
+
+        let encoded = cursor.encode();
+        assert!(!encoded.contains(':'), "游标应为不透明字符串");
+        assert_eq!(HistoryCursor::decode(&encoded), Ok(cursor));
+    }
+
+    #[test]
+    fn test_invalid_cursor() {
+        for cursor in ["", "not-base64!", "bm90LWEtY3Vyc29y", "MTIzOmFiYw"] {
+            assert!(HistoryCursor::decode(cursor).is_err(), "{}", cursor);
+        }
+    }
+
+    #[test]
+    fn test_pagination_mode() {
+        assert!(!query("").uses_cursor());
+        assert_eq!(query("").pagination, HistoryPagination::Offset);
+        assert!(query(r#", "pagination": "cursor""#).uses_cursor());
+        assert!(query(r#", "cursor": "abc""#).uses_cursor());
+    }
+
+    #[test]
+    fn test_stream_time_range_not_limited() {
+        let end = Utc::now() - Duration::hours(1);
+        let start = end - Duration::days(180);
+
+        let stream = BatteryStreamRequest {
+            start_time: start,
+            end_time: end,
+            component: None,
+        };
+        assert!(stream.validate_time_range().is_ok());
+
+        let mut paged = query("");
+        paged.start_time = start;
+        paged.end_time = end;
+        assert!(paged.validate_time_range().is_err());
+
+        let reversed = BatteryStreamRequest {
+            start_time: end,
+            end_time: start,
+            component: None,
+        };
+        assert!(reversed.validate_time_range().is_err());
+    }
+}
+
+mod battery_export {
+    use super::*;
+    use zinnia::models::BatteryExportRequest;
+
+    fn request(extra: &str) -> BatteryExportRequest {
+        serde_json::from_str(&format!(
+            r#"{{"device_ids": "00000000-0000-0000-0000-000000000001", "start_time": "2026-01-01T00:00:00Z", "end_time": "2026-01-02T00:00:00Z"{}}}"#,
+            extra
+        ))
+        .unwrap()
+    }
+
+    fn data() -> BatteryData {
+        battery_data()
+            .recorded_at("2026-01-12T10:30:00Z")
+            .charging(true)
+            .mode(PowerSavingMode::Low)
+            .temperature(Some(27.5))
+            .sample_id("a,\"b\"")
+            .build()
+    }
+
+    #[test]
+    fn test_parse_device_ids() {
+        let mut req = request("");
+        req.device_ids = format!(" {}, {} ,,{}", Uuid::nil(), Uuid::max(), Uuid::nil());
+        assert_eq!(req.parse_device_ids(), Ok(vec![Uuid::nil(), Uuid::max()]));
+
+        req.device_ids = "not-a-uuid".to_string();
+        assert!(req.parse_device_ids().is_err());
+
+        req.device_ids = " , ".to_string();
+        assert!(req.parse_device_ids().is_err());
+
+        req.device_ids = (0..51)
+            .map(|_| Uuid::new_v4().to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        assert!(req.parse_device_ids().is_err());
+    }
+
+    #[test]
+    fn test_csv_with_columns_and_timezone() {
+        let exporter = request(
+            r#", "columns": "recorded_at,battery_level,voltage,sample_id,power_saving_mode", "timezone": "Asia/Shanghai""#,
+        )
+        .exporter()
+        .unwrap();
+
+        let mut buf = exporter.header();
+        exporter.write_row(&data(), &mut buf);
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "recorded_at,battery_level,voltage,sample_id,power_saving_mode\r\n\
+             2026-01-12T18:30:00+08:00,80,,\"a,\"\"b\"\"\",low\r\n"
+        );
+    }
+
+    #[test]
+    fn test_ndjson_default_columns() {
+        let exporter = request(r#", "format": "ndjson""#).exporter().unwrap();
+        assert!(exporter.header().is_empty());
+
+        let mut buf = Vec::new();
+        exporter.write_row(&data(), &mut buf);
+        assert_eq!(buf.last(), Some(&b'\n'));
+
+        let row: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(row["recorded_at"], "2026-01-12T10:30:00Z");
+        assert_eq!(row["battery_level"], 80);
+        assert_eq!(row["is_charging"], true);
+        assert_eq!(row["power_saving_mode"], "low");
+        assert!(row["voltage"].is_null());
+        assert!(row.get("sample_id").is_none(), "默认不导出 sample_id");
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        assert!(request(r#", "columns": "battery_level,secret""#)
+            .exporter()
+            .is_err());
+        assert!(request(r#", "columns": ",""#).exporter().is_err());
+        assert!(request(r#", "timezone": "Mars/Olympus""#)
+            .exporter()
+            .is_err());
+        assert!(serde_json::from_str::<BatteryExportRequest>(
+            r#"{"device_ids": "x", "start_time": "2026-01-01T00:00:00Z", "end_time": "2026-01-02T00:00:00Z", "format": "parquet"}"#
+        )
+        .is_err());
+    }
+}
+
+mod battery_import {
+    use super::*;
+    use zinnia::models::{parse_import, BatteryExportRequest, ImportFormat};
+
+    #[test]
+    fn test_csv_rows_and_line_errors() {
+        let content = "\u{feff}device_id,recorded_at,battery_level,is_charging,power_saving_mode,temperature,sample_id\r\n\
+             x,2026-01-12T18:30:00+08:00,80,1,low,27.5,\"a,\"\"b\"\"\"\r\n\
+             \r\n\
+             x,2026-01-12T10:35:00Z,79,,,,\r\n\
+             x,,78,false,,,\r\n\
+             x,2026-01-12T10:45:00Z,abc,false,,,\r\n\
+             x,2026-01-12T10:50:00Z,77,maybe,,,\r\n\
+             x,2026-01-12T10:55:00Z,76\r\n";
+
+        let parsed = parse_import(ImportFormat::Csv, content).unwrap();
+
+        assert_eq!(parsed.records.len(), 2);
+        let first = &parsed.records[0];
+        assert_eq!(first.line, 2);
+        assert_eq!(
+            first.request.recorded_at,
+            Some("2026-01-12T10:30:00Z".parse().unwrap())
+        );
+        assert_eq!(first.request.battery_level, 80);
+        assert!(first.request.is_charging);
+        assert_eq!(first.request.power_saving_mode, PowerSavingMode::Low);
+        assert_eq!(first.request.temperature, Some(27.5));
+        assert_eq!(first.request.voltage, None);
+        assert_eq!(first.request.sample_id.as_deref(), Some("a,\"b\""));
+
+        let second = &parsed.records[1];
+        assert_eq!(second.line, 4);
+        assert!(!second.request.is_charging);
+        assert_eq!(second.request.power_saving_mode, PowerSavingMode::Off);
+        assert_eq!(second.request.sample_id, None);
+
+        let lines: Vec<usize> = parsed.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_csv_header_errors() {
+        assert!(parse_import(ImportFormat::Csv, "recorded_at,is_charging\n").is_err());
+        assert!(parse_import(
+            ImportFormat::Csv,
+            "recorded_at,battery_level,battery_level\n"
+        )
+        .is_err());
+        assert!(parse_import(ImportFormat::Csv, "\n\n").is_err());
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let content = r#"{"recorded_at":"2026-01-12T10:30:00Z","battery_level":80,"device_id":"ignored"}
+
+{"battery_level":79}
+not json
+"#;
+
+        let parsed = parse_import(ImportFormat::Ndjson, content).unwrap();
+
+        assert_eq!(parsed.records.len(), 1);
+        assert_eq!(parsed.records[0].line, 1);
+        assert_eq!(parsed.records[0].request.battery_level, 80);
+        let lines: Vec<usize> = parsed.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let data = battery_data()
+            .recorded_at("2026-01-12T10:30:00.123Z")
+            .level(42)
+            .mode(PowerSavingMode::High)
+            .voltage(3.85)
+            .sample_id("s-1")
+            .build();
+
+        for (format, import_format) in
+            [("csv", ImportFormat::Csv), ("ndjson", ImportFormat::Ndjson)]
+        {
+            let exporter = serde_json::from_str::<BatteryExportRequest>(&format!(
+                r#"{{"device_ids": "{}", "start_time": "2026-01-01T00:00:00Z", "end_time": "2026-01-02T00:00:00Z", "format": "{}", "columns": "device_id,recorded_at,battery_level,is_charging,power_saving_mode,temperature,voltage,sample_id", "timezone": "Asia/Shanghai"}}"#,
+                Uuid::nil(),
+                format
+            ))
+            .unwrap()
+            .exporter()
+            .unwrap();
+            let mut buf = exporter.header();
+            exporter.write_row(&data, &mut buf);
+
+            let parsed = parse_import(import_format, std::str::from_utf8(&buf).unwrap()).unwrap();
+            assert!(parsed.errors.is_empty(), "{}: {:?}", format, parsed.errors);
+            let request = &parsed.records[0].request;
+            assert_eq!(request.recorded_at, Some(data.recorded_at));
+            assert_eq!(request.battery_level, 42);
+            assert_eq!(request.power_saving_mode, PowerSavingMode::High);
+            assert_eq!(request.voltage, Some(3.85));
+            assert_eq!(request.sample_id.as_deref(), Some("s-1"));
+        }
+    }
+
+    #[test]
+    fn test_format_from_str() {
+        assert_eq!("CSV".parse::<ImportFormat>(), Ok(ImportFormat::Csv));
+        assert_eq!("jsonl".parse::<ImportFormat>(), Ok(ImportFormat::Ndjson));
+        assert!("parquet".parse::<ImportFormat>().is_err());
+    }
+}
+
+mod battery_estimate {
+    use super::*;
+    use zinnia::models::{
+        estimate_battery, estimate_battery_rate, estimate_rate_per_hour, BatteryEstimate,
+    };
+
+    fn sample(minutes: i64, level: i32, is_charging: bool, mode: PowerSavingMode) -> BatteryData {
+        battery_data()
+            .minute(minutes)
+            .level(level)
+            .charging(is_charging)
+            .mode(mode)
+            .build()
+    }
+
+    fn discharging(minutes: i64, level: i32) -> BatteryData {
+        sample(minutes, level, false, PowerSavingMode::Off)
+    }
+
+    #[test]
+    fn test_discharge_estimate() {
+        let samples = vec![discharging(0, 80), discharging(15, 75), discharging(30, 70)];
+        let latest = samples.last().unwrap();
+        let estimate = estimate_battery(&samples, latest, 10, 10).unwrap();
+
+        assert!((estimate.rate.rate_per_hour - 20.0).abs() < 1e-9);
+        assert!(estimate.rate.lower < 20.0 && estimate.rate.upper > 20.0);
+        assert!(estimate.mode_matched);
+        assert!(estimate.time_to_full.is_none());
+
+        let empty = estimate.time_to_empty.unwrap();
+        assert_eq!(empty.minutes, 210, "70% 以 20%/小时耗尽需 210 分钟");
+        assert!(empty.earliest_at < empty.at);
+        assert!(empty.latest_at.unwrap() > empty.at);
+
+        let critical = estimate.time_to_critical.unwrap();
+        assert_eq!(critical.minutes, 180);
+    }
+
+    #[test]
+    fn test_charge_estimate() {
+        let samples = vec![
+            sample(0, 50, true, PowerSavingMode::Off),
+            sample(15, 60, true, PowerSavingMode::Off),
+            sample(30, 70, true, PowerSavingMode::Off),
+        ];
+        let estimate = estimate_battery(&samples, &samples[2], 10, 10).unwrap();
+
+        assert!((estimate.rate.rate_per_hour - 40.0).abs() < 1e-9);
+        assert!(estimate.time_to_empty.is_none());
+        assert!(estimate.time_to_critical.is_none());
+        assert_eq!(estimate.time_to_full.unwrap().minutes, 45);
+    }
+
+    #[test]
+    fn test_separates_power_saving_mode() {
+        let samples = vec![
+            discharging(0, 90),
+            discharging(30, 70),
+            sample(40, 70, false, PowerSavingMode::High),
+            sample(70, 65, false, PowerSavingMode::High),
+            sample(100, 60, false, PowerSavingMode::High),
+        ];
+        let rate =
+            estimate_rate_per_hour(&samples, false, Some(&PowerSavingMode::High), 10).unwrap();
+
+        assert!(
+            (rate.rate_per_hour - 10.0).abs() < 1e-9,
+            "只使用省电模式下的数据"
+        );
+        assert_eq!(rate.sample_count, 3);
+    }
+
+    #[test]
+    fn test_falls_back_to_charging_state() {
+        let samples = vec![
+            discharging(0, 90),
+            discharging(30, 80),
+            sample(60, 70, false, PowerSavingMode::Low),
+        ];
+        let estimate = estimate_battery(&samples, &samples[2], 10, 10).unwrap();
+
+        assert!(
+            !estimate.mode_matched,
+            "当前模式数据不足时退化为只区分充放电"
+        );
+        assert_eq!(estimate.rate.sample_count, 3);
+    }
+
+    #[test]
+    fn test_charging_segments_excluded() {
+        let samples = vec![
+            discharging(0, 80),
+            discharging(30, 70),
+            sample(40, 90, true, PowerSavingMode::Off),
+            discharging(50, 90),
+            discharging(80, 80),
+        ];
+        let rate = estimate_rate_per_hour(&samples, false, None, 10).unwrap();
+
+        assert!(
+            (rate.rate_per_hour - 20.0).abs() < 1e-9,
+            "各放电区间独立拟合截距"
+        );
+    }
+
+    #[test]
+    fn test_insufficient_data() {
+        let samples = vec![discharging(0, 80), discharging(5, 79)];
+        assert!(estimate_battery(&samples, &samples[1], 10, 10).is_none());
+        assert!(estimate_battery(&samples[..1], &samples[0], 10, 1).is_none());
+        assert!(estimate_battery_rate(&samples, &samples[1], 10).is_none());
+    }
+
+    #[test]
+    fn test_cached_rate_applied_to_newer_sample() {
+        let samples = vec![discharging(0, 80), discharging(15, 75), discharging(30, 70)];
+        let (rate, mode_matched) = estimate_battery_rate(&samples, &samples[2], 10).unwrap();
+        assert_eq!(
+            BatteryEstimate::from_rate(rate.clone(), mode_matched, &samples[2], 10),
+            estimate_battery(&samples, &samples[2], 10, 10).unwrap()
+        );
+
+        // 缓存的速率按新的电量和时间重新推算
+        let newer = discharging(45, 60);
+        let estimate = BatteryEstimate::from_rate(rate, mode_matched, &newer, 10);
+        let empty = estimate.time_to_empty.unwrap();
+        assert_eq!(empty.minutes, 180, "60% 以 20%/小时耗尽需 180 分钟");
+        assert_eq!(empty.at, newer.recorded_at + Duration::minutes(180));
+        assert_eq!(estimate.time_to_critical.unwrap().minutes, 150);
+    }
+}
+
+mod charging_session {
+    use super::*;
+    use zinnia::models::track_charging_sessions;
+
+    fn sample(
+        minutes: i64,
+        level: i32,
+        is_charging: bool,
+        temperature: Option<f64>,
+    ) -> BatteryData {
+        battery_data()
+            .minute(minutes)
+            .level(level)
+            .charging(is_charging)
+            .temperature(temperature)
+            .build()
+    }
+
+    #[test]
+    fn test_session_lifecycle() {
+        let samples = vec![
+            sample(0, 40, false, None),
+            sample(10, 40, true, Some(30.0)),
+            sample(40, 70, true, Some(36.5)),
+            sample(50, 80, true, Some(34.0)),
+            sample(60, 79, false, None),
+        ];
+        let sessions = track_charging_sessions(None, &samples);
+
+        assert_eq!(sessions.len(), 1);
+        let session = &sessions[0];
+        assert_eq!(session.started_at, samples[1].recorded_at);
+        assert_eq!(session.ended_at, Some(samples[4].recorded_at));
+        assert_eq!((session.start_level, session.end_level), (40, 80));
+        assert_eq!(session.peak_temperature, Some(36.5));
+        assert_eq!(session.sample_count, 3);
+        assert_eq!(session.duration_minutes, 50);
+        assert!((session.avg_charge_rate.unwrap() - 60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_open_session_continues_across_reports() {
+        let first = track_charging_sessions(None, &[sample(0, 20, true, None)]);
+        assert_eq!(first.len(), 1);
+        assert!(first[0].is_open());
+        assert_eq!(first[0].avg_charge_rate, None);
+
+        let second =
+            track_charging_sessions(first.into_iter().next(), &[sample(30, 35, true, None)]);
+        assert_eq!(second.len(), 1);
+        assert!(second[0].is_open());
+        assert_eq!(second[0].sample_count, 2);
+        assert_eq!(second[0].duration_minutes, 30);
+    }
+
+    #[test]
+    fn test_close_and_restart_in_one_batch() {
+        let open = track_charging_sessions(None, &[sample(0, 20, true, None)]).pop();
+        let samples = vec![
+            sample(10, 30, false, None),
+            sample(20, 29, false, None),
+            sample(30, 29, true, None),
+        ];
+        let sessions = track_charging_sessions(open, &samples);
+
+        assert_eq!(sessions.len(), 2, "结束的会话和新会话都需要保存");
+        assert!(!sessions[0].is_open());
+        assert!(sessions[1].is_open());
+        assert_eq!(sessions[1].start_level, 29);
+    }
+
+    #[test]
+    fn test_ignores_samples_before_last_session() {
+        let samples = vec![sample(0, 20, true, None), sample(30, 50, false, None)];
+        let closed = track_charging_sessions(None, &samples).pop();
+
+        let late = vec![sample(10, 30, true, None), sample(20, 40, true, None)];
+        assert!(
+            track_charging_sessions(closed, &late).is_empty(),
+            "补传的旧数据不应开启新会话"
+        );
+    }
+
+    #[test]
+    fn test_no_session_without_charging() {
+        let samples = vec![sample(0, 80, false, None), sample(10, 75, false, None)];
+        assert!(track_charging_sessions(None, &samples).is_empty());
+    }
+}
+
+mod aggregate_interval {
+    use zinnia::models::{AggregateInterval, BatteryAggregateRequest, GapFill};
+
+    /// 与默认配置 `battery.max_aggregate_buckets` 一致
+    const MAX_BUCKETS: i64 = 50000;
+
+    fn at(s: &str) -> chrono::DateTime<chrono::Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_named_and_shorthand() {
+        assert_eq!("hour".parse(), Ok(AggregateInterval::HOUR));
+        assert_eq!("Week".parse(), Ok(AggregateInterval::WEEK));
+        assert_eq!("month".parse(), Ok(AggregateInterval::Months(1)));
+        assert_eq!("5m".parse(), Ok(AggregateInterval::Fixed(300)));
+        assert_eq!("15min".parse(), Ok(AggregateInterval::Fixed(900)));
+        assert_eq!("6h".parse(), Ok(AggregateInterval::Fixed(21600)));
+        assert_eq!("2d".parse(), Ok(AggregateInterval::Fixed(172800)));
+        assert_eq!("3mo".parse(), Ok(AggregateInterval::Months(3)));
+        assert_eq!("1y".parse(), Ok(AggregateInterval::Months(12)));
+    }
+
+    #[test]
+    fn test_parse_iso8601() {
+        assert_eq!("PT15M".parse(), Ok(AggregateInterval::Fixed(900)));
+        assert_eq!("PT1H30M".parse(), Ok(AggregateInterval::Fixed(5400)));
+        assert_eq!("P1D".parse(), Ok(AggregateInterval::DAY));
+        assert_eq!("P1W".parse(), Ok(AggregateInterval::WEEK));
+        assert_eq!("P1DT12H".parse(), Ok(AggregateInterval::Fixed(129600)));
+        assert_eq!("P1M".parse(), Ok(AggregateInterval::Months(1)));
+        assert_eq!("P1Y6M".parse(), Ok(AggregateInterval::Months(18)));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for s in [
+            "",
+            "fortnight",
+            "5",
+            "5x",
+            "30s",
+            "0m",
+            "PT",
+            "P",
+            "PT0.5H",
+            "P1M1D",
+            "PT1D",
+            "P1H",
+        ] {
+            assert!(s.parse::<AggregateInterval>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_request_deserialize() {
+        let request: BatteryAggregateRequest = serde_json::from_str(
+            r#"{"start_time": "2026-01-01T00:00:00Z", "end_time": "2026-01-02T00:00:00Z", "interval": "15m", "fill": "locf"}"#,
+        )
+        .unwrap();
+        assert_eq!(request.interval, AggregateInterval::Fixed(900));
+        assert_eq!(request.fill, GapFill::Locf);
+
+        let request: BatteryAggregateRequest = serde_json::from_str(
+            r#"{"start_time": "2026-01-01T00:00:00Z", "end_time": "2026-01-02T00:00:00Z"}"#,
+        )
+        .unwrap();
+        assert_eq!(request.interval, AggregateInterval::HOUR);
+        assert_eq!(request.fill, GapFill::None);
+
+        assert!(serde_json::from_str::<BatteryAggregateRequest>(
+            r#"{"start_time": "2026-01-01T00:00:00Z", "end_time": "2026-01-02T00:00:00Z", "interval": "10s"}"#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_continuous_aggregate_selection() {
+        assert!(AggregateInterval::MINUTE.continuous_aggregate().is_none());
+        assert!(AggregateInterval::Fixed(5400)
+            .continuous_aggregate()
+            .is_none());
+        assert_eq!(
+            AggregateInterval::Fixed(21600).continuous_aggregate(),
+            Some(("battery_hourly_stats", AggregateInterval::HOUR))
+        );
+        assert_eq!(
+            AggregateInterval::WEEK.continuous_aggregate(),
+            Some(("battery_daily_stats", AggregateInterval::DAY))
+        );
+        assert_eq!(
+            AggregateInterval::MONTH.continuous_aggregate(),
+            Some(("battery_daily_stats", AggregateInterval::DAY))
+        );
+    }
+
+    #[test]
+    fn test_full_bucket_range_excludes_partial_edges() {
+        let range = AggregateInterval::HOUR
+            .full_bucket_range(at("2026-01-01T00:20:00Z"), at("2026-01-01T09:30:00Z"));
+
+        assert_eq!(
+            range,
+            Some((at("2026-01-01T01:00:00Z"), at("2026-01-01T09:00:00Z")))
+        );
+    }
+
+    #[test]
+    fn test_full_bucket_range_aligned_start() {
+        let range = AggregateInterval::DAY
+            .full_bucket_range(at("2026-01-01T00:00:00Z"), at("2026-01-03T12:00:00Z"));
+
+        assert_eq!(
+            range,
+            Some((at("2026-01-01T00:00:00Z"), at("2026-01-03T00:00:00Z")))
+        );
+    }
+
+    #[test]
+    fn test_full_bucket_range_within_single_bucket() {
+        let range = AggregateInterval::DAY
+            .full_bucket_range(at("2026-01-01T01:00:00Z"), at("2026-01-01T23:00:00Z"));
+
+        assert!(range.is_none());
+    }
+
+    #[test]
+    fn test_validate_time_range() {
+        let start = at("2025-01-01T00:00:00Z");
+        let end = at("2026-01-01T00:00:00Z");
+
+        assert!(AggregateInterval::DAY
+            .validate_time_range(start, end, MAX_BUCKETS)
+            .is_ok());
+        assert!(AggregateInterval::MONTH
+            .validate_time_range(start, end, MAX_BUCKETS)
+            .is_ok());
+        assert!(AggregateInterval::Fixed(21600)
+            .validate_time_range(start, end, MAX_BUCKETS)
+            .is_ok());
+        assert!(AggregateInterval::Fixed(900)
+            .validate_time_range(start, end, MAX_BUCKETS)
+            .is_err());
+        assert!(AggregateInterval::MINUTE
+            .validate_time_range(at("2025-12-25T00:00:00Z"), end, MAX_BUCKETS)
+            .is_ok());
+        assert!(AggregateInterval::HOUR
+            .validate_time_range(end, start, MAX_BUCKETS)
+            .is_err());
+    }
+
+    #[test]
+    fn test_bucket_count() {
+        let start = at("2025-01-01T00:00:00Z");
+
+        assert_eq!(
+            AggregateInterval::HOUR.bucket_count(start, at("2025-01-01T23:30:00Z")),
+            24
+        );
+        assert_eq!(
+            AggregateInterval::DAY.bucket_count(start, at("2025-01-01T00:00:00Z")),
+            1
+        );
+        assert_eq!(
+            AggregateInterval::MONTH.bucket_count(start, at("2025-12-31T00:00:00Z")),
+            12
+        );
+        assert_eq!(
+            AggregateInterval::Months(3).bucket_count(start, at("2025-12-31T00:00:00Z")),
+            4
+        );
+    }
+
+    #[test]
+    fn test_validate_time_range_limits_buckets() {
+        let end = at("2026-01-01T00:00:00Z");
+
+        // 视图支持的间隔不受 30 天限制，但补齐时间桶时不能从公元 1 年开始逐小时生成
+        assert!(AggregateInterval::HOUR
+            .validate_time_range(at("0001-01-01T00:00:00Z"), end, MAX_BUCKETS)
+            .is_err());
+        assert!(AggregateInterval::HOUR
+            .validate_time_range(at("2025-01-01T00:00:00Z"), end, MAX_BUCKETS)
+            .is_ok());
+        assert!(AggregateInterval::DAY
+            .validate_time_range(at("1800-01-01T00:00:00Z"), end, MAX_BUCKETS)
+            .is_err());
+        assert!(AggregateInterval::HOUR
+            .validate_time_range(at("2025-12-31T00:00:00Z"), end, 24)
+            .is_err());
+        assert!(AggregateInterval::HOUR
+            .validate_time_range(at("2025-12-31T01:00:00Z"), end, 24)
+            .is_ok());
+    }
+}
+
+mod battery_health {
+    use super::*;
+    use zinnia::models::{degradation_score, HealthStatus, HealthTrend, HealthTrendPoint};
+
+    fn weekly(values: &[f64]) -> HealthTrend {
+        let start = Utc::now() - Duration::weeks(values.len() as i64);
+        HealthTrend::fit(
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| HealthTrendPoint {
+                    bucket: start + Duration::weeks(i as i64),
+                    value: *v,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_fit_linear_trend() {
+        let trend = weekly(&[100.0, 99.0, 98.0, 97.0, 96.0]);
+
+        assert!((trend.change.unwrap() + 4.0).abs() < 1e-9);
+        assert!((trend.baseline.unwrap() - 100.0).abs() < 1e-9);
+        assert!((trend.slope_per_30_days.unwrap() + 30.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fit_requires_two_points() {
+        let trend = weekly(&[100.0]);
+
+        assert_eq!(trend.points.len(), 1);
+        assert!(trend.slope_per_30_days.is_none());
+        assert!(trend.change.is_none());
+    }
+
+    #[test]
+    fn test_degradation_score_weights_available_metrics() {
+        // 满充电量下降 4 个百分点（20 分），放电速率上升 10%（20 分），电压无数据
+        let score = degradation_score(
+            &weekly(&[100.0, 98.0, 96.0]),
+            &weekly(&[5.0, 5.25, 5.5]),
+            &weekly(&[]),
+        )
+        .unwrap();
+
+        assert!((score - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_degradation_score_clamped() {
+        // 满充电量上升不计为负分，电压下降超过上限计为 100 分
+        let score = degradation_score(
+            &weekly(&[90.0, 95.0, 100.0]),
+            &weekly(&[]),
+            &weekly(&[3.9, 3.7, 3.5]),
+        )
+        .unwrap();
+
+        assert!((score - 100.0 * 0.25 / 0.65).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_degradation_score_without_data() {
+        assert!(degradation_score(&weekly(&[]), &weekly(&[96.0]), &weekly(&[])).is_none());
+    }
+
+    #[test]
+    fn test_status_from_score() {
+        assert_eq!(HealthStatus::from_score(0.0), HealthStatus::Good);
+        assert_eq!(HealthStatus::from_score(30.0), HealthStatus::Fair);
+        assert_eq!(HealthStatus::from_score(59.9), HealthStatus::Fair);
+        assert_eq!(HealthStatus::from_score(60.0), HealthStatus::Replace);
+    }
+}
+
+mod alert_rule {
+    use super::*;
+    use zinnia::models::{
+        AlertComparison, AlertLevel, AlertRule, AlertRuleScope, AlertType, UpdateAlertRuleRequest,
+    };
+
+    fn rule(
+        alert_type: AlertType,
+        threshold: Option<f64>,
+        comparison: Option<AlertComparison>,
+    ) -> AlertRule {
+        AlertRule {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: "test".to_string(),
+            alert_type,
+            level: AlertLevel::Warning,
+            cooldown_minutes: 30,
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            scope: AlertRuleScope::All,
+            device_ids: Vec::new(),
+            device_type: None,
+            threshold,
+            comparison,
+            metric_key: None,
+        }
+    }
+
+    #[test]
+    fn test_default_threshold_and_comparison() {
+        let low = rule(AlertType::LowBattery, None, None);
+        assert_eq!(low.breached(15.0, 20.0), Some(20.0));
+        assert_eq!(low.breached(20.0, 20.0), None);
+
+        let temp = rule(AlertType::HighTemperature, None, None);
+        assert_eq!(temp.breached(46.0, 45.0), Some(45.0));
+        assert_eq!(temp.breached(44.0, 45.0), None);
+    }
+
+    #[test]
+    fn test_rule_threshold_overrides_device_config() {
+        let low = rule(AlertType::LowBattery, Some(30.0), None);
+        assert_eq!(low.breached(25.0, 20.0), Some(30.0), "应使用规则阈值");
+
+        let inclusive = rule(
+            AlertType::LowBattery,
+            Some(30.0),
+            Some(AlertComparison::Lte),
+        );
+        assert_eq!(inclusive.breached(30.0, 20.0), Some(30.0));
+    }
+
+    #[test]
+    fn test_update_request_distinguishes_null() {
+        let omitted: UpdateAlertRuleRequest = serde_json::from_str(r#"{}"#).unwrap();
+        assert_eq!(omitted.threshold, None);
+
+        let cleared: UpdateAlertRuleRequest =
+            serde_json::from_str(r#"{"threshold": null}"#).unwrap();
+        assert_eq!(cleared.threshold, Some(None));
+
+        let set: UpdateAlertRuleRequest = serde_json::from_str(
+            r#"{"threshold": 15, "scope": "device_type", "device_type": "sensor"}"#,
+        )
+        .unwrap();
+        assert_eq!(set.threshold, Some(Some(15.0)));
+        assert_eq!(set.scope, Some(AlertRuleScope::DeviceType));
+    }
+}
+
+mod offline_detection {
+    use super::*;
+    use zinnia::models::{offline_cutoff, DeviceConfig};
+
+    fn now() -> DateTime<Utc> {
+        BatteryDataBuilder::base_time()
+    }
+
+    #[test]
+    fn test_cutoff_is_interval_times_grace_multiplier() {
+        assert_eq!(offline_cutoff(now(), 60, 3.0), now() - Duration::minutes(3));
+        assert_eq!(
+            offline_cutoff(now(), 60, 1.5),
+            now() - Duration::seconds(90)
+        );
+        assert_eq!(
+            offline_cutoff(now(), DeviceConfig::default().report_interval_seconds, 3.0),
+            now() - Duration::minutes(3),
+            "未配置的设备按默认上报间隔计算"
+        );
+    }
+
+    #[test]
+    fn test_grace_multiplier_at_least_one() {
+        assert_eq!(offline_cutoff(now(), 60, 0.5), now() - Duration::minutes(1));
+        assert_eq!(offline_cutoff(now(), 60, 0.0), now() - Duration::minutes(1));
+    }
+
+    #[test]
+    fn test_invalid_interval_clamped() {
+        assert_eq!(offline_cutoff(now(), 0, 3.0), now() - Duration::seconds(3));
+    }
+
+    #[test]
+    fn test_device_offline_only_after_cutoff() {
+        let cutoff = offline_cutoff(now(), 60, 3.0);
+        let just_seen = now() - Duration::minutes(2);
+        let long_ago = now() - Duration::minutes(4);
+
+        assert!(just_seen >= cutoff, "宽限期内仍视为在线");
+        assert!(long_ago < cutoff, "超过宽限期视为离线");
+    }
+}
+
+mod alert_recovery {
+    use super::*;
+    use zinnia::models::{AlertEvent, AlertLevel, AlertStatus, AlertType, DeviceConfig};
+
+    fn event(alert_type: AlertType, threshold: f64, component: Option<&str>) -> AlertEvent {
+        AlertEvent {
+            id: Uuid::new_v4(),
+            device_id: Uuid::nil(),
+            rule_id: Uuid::new_v4(),
+            alert_type,
+            level: AlertLevel::Warning,
+            status: AlertStatus::Active,
+            message: "test".to_string(),
+            value: threshold,
+            threshold,
+            triggered_at: BatteryDataBuilder::base_time(),
+            acknowledged_at: None,
+            resolved_at: None,
+            metric_key: None,
+            component: component.map(String::from),
+        }
+    }
+
+    /// 回差为默认值：电量 5 个百分点，温度 2 摄氏度
+    fn config() -> DeviceConfig {
+        DeviceConfig::default()
+    }
+
+    fn after_trigger() -> DateTime<Utc> {
+        BatteryDataBuilder::base_time() + Duration::minutes(5)
+    }
+
+    #[test]
+    fn test_battery_requires_threshold_plus_band() {
+        let low = event(AlertType::LowBattery, 20.0, None);
+        let at = after_trigger();
+
+        assert!(
+            !low.is_recovered(None, 21.0, None, &config(), at),
+            "刚越过阈值不解决"
+        );
+        assert!(!low.is_recovered(None, 24.0, None, &config(), at));
+        assert!(
+            low.is_recovered(None, 25.0, None, &config(), at),
+            "阈值 + 回差即解决"
+        );
+        assert!(low.is_recovered(None, 60.0, None, &config(), at));
+
+        let critical = event(AlertType::CriticalBattery, 10.0, None);
+        assert!(!critical.is_recovered(None, 14.0, None, &config(), at));
+        assert!(critical.is_recovered(None, 15.0, None, &config(), at));
+    }
+
+    #[test]
+    fn test_temperature_requires_threshold_minus_band() {
+        let hot = event(AlertType::HighTemperature, 45.0, None);
+        let at = after_trigger();
+
+        assert!(!hot.is_recovered(None, 80.0, Some(44.0), &config(), at));
+        assert!(
+            hot.is_recovered(None, 80.0, Some(43.0), &config(), at),
+            "阈值 - 回差即解决"
+        );
+        assert!(hot.is_recovered(None, 80.0, Some(30.0), &config(), at));
+        assert!(
+            !hot.is_recovered(None, 80.0, None, &config(), at),
+            "未上报温度不解决"
+        );
+    }
+
+    #[test]
+    fn test_band_follows_device_config() {
+        let low = event(AlertType::LowBattery, 20.0, None);
+        let config = DeviceConfig {
+            battery_hysteresis: 0,
+            temperature_hysteresis: 0.0,
+            ..DeviceConfig::default()
+        };
+
+        assert!(low.is_recovered(None, 20.0, None, &config, after_trigger()));
+    }
+
+    #[test]
+    fn test_only_same_component_resolves() {
+        let main = event(AlertType::LowBattery, 20.0, Some("main"));
+        let at = after_trigger();
+
+        assert!(main.is_recovered(Some("main"), 50.0, None, &config(), at));
+        assert!(!main.is_recovered(Some("aux"), 50.0, None, &config(), at));
+        assert!(!main.is_recovered(None, 50.0, None, &config(), at));
+
+        let device_level = event(AlertType::LowBattery, 20.0, None);
+        assert!(!device_level.is_recovered(Some("main"), 50.0, None, &config(), at));
+    }
+
+    #[test]
+    fn test_data_before_trigger_does_not_resolve() {
+        let low = event(AlertType::LowBattery, 20.0, None);
+        let before = BatteryDataBuilder::base_time() - Duration::minutes(1);
+
+        assert!(!low.is_recovered(None, 80.0, None, &config(), before));
+    }
+
+    #[test]
+    fn test_offline_resolves_on_any_report() {
+        let offline = event(AlertType::DeviceOffline, 0.0, None);
+
+        assert!(offline.is_recovered(Some("main"), 0.0, None, &config(), after_trigger()));
+    }
+
+    #[test]
+    fn test_resolved_and_other_types_are_skipped() {
+        let mut low = event(AlertType::LowBattery, 20.0, None);
+        low.status = AlertStatus::Resolved;
+        assert!(!low.is_recovered(None, 80.0, None, &config(), after_trigger()));
+
+        let drain = event(AlertType::RapidDrain, 20.0, None);
+        assert!(!drain.is_recovered(None, 80.0, None, &config(), after_trigger()));
+    }
+}
+
+mod alert_evaluation {
+    use super::*;
+    use zinnia::models::{
+        alert_evaluation_order, latest_by_component, notify_cutoff, window_ending_at, AlertLevel,
+        AlertRule, AlertRuleScope, AlertType, DeviceAlertRules,
+    };
+
+    fn sample(minutes: i64, level: i32) -> BatteryData {
+        battery_data().minute(minutes).level(level).build()
+    }
+
+    fn rule(alert_type: AlertType, scope: AlertRuleScope, threshold: f64) -> AlertRule {
+        AlertRule {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: "test".to_string(),
+            alert_type,
+            level: AlertLevel::Warning,
+            cooldown_minutes: 30,
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            scope,
+            device_ids: Vec::new(),
+            device_type: None,
+            threshold: Some(threshold),
+            comparison: None,
+            metric_key: None,
+        }
+    }
+
+    #[test]
+    fn test_evaluated_in_recorded_order() {
+        let samples = vec![sample(20, 70), sample(0, 80), sample(10, 75)];
+
+        let order: Vec<i32> = alert_evaluation_order(&samples, None)
+            .into_iter()
+            .map(|(d, notify)| {
+                assert!(notify, "未设置 notify_max_age_seconds 时全部通知");
+                d.battery_level
+            })
+            .collect();
+
+        assert_eq!(order, vec![80, 75, 70]);
+    }
+
+    #[test]
+    fn test_notify_cutoff() {
+        let now = BatteryDataBuilder::base_time() + Duration::hours(1);
+        assert_eq!(notify_cutoff(None, now), Ok(None));
+        assert!(notify_cutoff(Some(-1), now).is_err());
+
+        // 只通知 30 分钟内的数据，更早的补传数据仍参与检查
+        let cutoff = notify_cutoff(Some(1800), now).unwrap();
+        assert_eq!(cutoff, Some(now - Duration::minutes(30)));
+
+        let samples = vec![sample(50, 70), sample(10, 80), sample(30, 75)];
+        let notify: Vec<(i32, bool)> = alert_evaluation_order(&samples, cutoff)
+            .into_iter()
+            .map(|(d, notify)| (d.battery_level, notify))
+            .collect();
+
+        assert_eq!(notify, vec![(80, false), (75, true), (70, true)]);
+    }
+
+    #[test]
+    fn test_latest_by_component() {
+        let samples = vec![
+            battery_data().minute(0).component("left").level(90).build(),
+            battery_data()
+                .minute(5)
+                .component("right")
+                .level(85)
+                .build(),
+            battery_data()
+                .minute(10)
+                .component("left")
+                .level(88)
+                .build(),
+        ];
+
+        let latest: Vec<(&str, i32)> = latest_by_component(&samples)
+            .into_iter()
+            .map(|d| (d.component.as_str(), d.battery_level))
+            .collect();
+
+        assert_eq!(latest, vec![("right", 85), ("left", 88)]);
+    }
+
+    #[test]
+    fn test_window_ending_at() {
+        let history: Vec<BatteryData> = (0..6).map(|i| sample(i * 10, 80 - i as i32)).collect();
+        let end = history[4].recorded_at;
+
+        let window = window_ending_at(&history, end, Duration::minutes(20));
+        let levels: Vec<i32> = window.iter().map(|d| d.battery_level).collect();
+        assert_eq!(
+            levels,
+            vec![78, 77, 76],
+            "窗口两端都包含在内，不含之后的数据"
+        );
+
+        let before = BatteryDataBuilder::base_time() - Duration::minutes(1);
+        assert!(window_ending_at(&history, before, Duration::minutes(5)).is_empty());
+    }
+
+    #[test]
+    fn test_device_rules_matching() {
+        let rules = DeviceAlertRules::new(vec![
+            rule(AlertType::LowBattery, AlertRuleScope::All, 20.0),
+            rule(AlertType::LowBattery, AlertRuleScope::Devices, 30.0),
+            rule(AlertType::LowBattery, AlertRuleScope::DeviceType, 25.0),
+        ]);
+
+        let matched = rules.matching(&AlertType::LowBattery, None).unwrap();
+        assert_eq!(matched.threshold, Some(30.0), "指定设备的规则优先");
+        assert!(
+            rules.matching(&AlertType::RapidDrain, None).is_none(),
+            "没有快速耗电规则时不检查快速耗电"
+        );
+    }
+}
+
+mod batch_report_request {
+    use validator::Validate;
+    use zinnia::models::BatchBatteryReportRequest;
+
+    #[test]
+    fn test_invalid_rows_do_not_fail_whole_batch() {
+        let request: BatchBatteryReportRequest =
+            serde_json::from_str(r#"{"data": [{"battery_level": 80}, {"battery_level": 150}]}"#)
+                .unwrap();
+
+        assert!(request.validate().is_ok(), "单条记录由服务端逐条校验");
+        assert!(request.data[1].validate().is_err());
+    }
+
+    #[test]
+    fn test_empty_batch_rejected() {
+        let request: BatchBatteryReportRequest = serde_json::from_str(r#"{"data": []}"#).unwrap();
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn test_sample_id_length() {
+        let request: BatchBatteryReportRequest = serde_json::from_str(&format!(
+            r#"{{"data": [{{"battery_level": 80, "sample_id": "s-1"}}, {{"battery_level": 80, "sample_id": ""}}, {{"battery_level": 80, "sample_id": "{}"}}]}}"#,
+            "x".repeat(65)
+        ))
+        .unwrap();
+
+        assert_eq!(request.data[0].sample_id.as_deref(), Some("s-1"));
+        assert!(request.data[0].validate().is_ok());
+        assert!(request.data[1].validate().is_err());
+        assert!(request.data[2].validate().is_err());
+    }
+
+    #[test]
+    fn test_compat_query_keeps_sample_id() {
+        use zinnia::models::CompatBatteryReportQuery;
+
+        let query: CompatBatteryReportQuery = serde_json::from_str(
+            r#"{"token": "t", "level": 50, "ts": 1736677800, "sample_id": "s-1"}"#,
+        )
+        .unwrap();
+        let report = query.to_battery_report();
+
+        assert_eq!(report.sample_id.as_deref(), Some("s-1"));
+        assert_eq!(report.recorded_at.map(|t| t.timestamp()), Some(1736677800));
+    }
+
+    #[test]
+    fn test_batch_recorded_at_distinct_without_timestamps() {
+        use chrono::{TimeZone, Utc};
+        use std::collections::HashSet;
+        use zinnia::models::BatteryReportRequest;
+
+        let explicit = Utc.with_ymd_and_hms(2025, 1, 12, 8, 30, 0).unwrap();
+        let request: BatchBatteryReportRequest = serde_json::from_value(serde_json::json!({
+            "data": [
+                {"battery_level": 80},
+                {"battery_level": 79, "recorded_at": explicit},
+                {"battery_level": 78},
+                {"battery_level": 77},
+            ]
+        }))
+        .unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 1, 12, 9, 0, 0).unwrap();
+
+        let recorded = BatteryReportRequest::batch_recorded_at(&request.data, now);
+
+        assert_eq!(recorded.len(), 4);
+        assert_eq!(recorded[1], explicit);
+        let unique: HashSet<_> = recorded.iter().collect();
+        assert_eq!(unique.len(), 4, "未提供记录时间的记录不能落在同一时间");
+        // 按批次顺序递增，最后一条为当前时间
+        assert!(recorded[0] < recorded[2] && recorded[2] < recorded[3]);
+        assert_eq!(recorded[3], now);
+    }
+
+    #[test]
+    fn test_storage_violation_matches_table_constraints() {
+        let request: BatchBatteryReportRequest = serde_json::from_value(serde_json::json!({
+            "data": [
+                {"battery_level": 0, "component": "左耳", "sample_id": "s".repeat(64)},
+                {"battery_level": 100, "component": "耳".repeat(50)},
+                {"battery_level": -1},
+                {"battery_level": 101},
+                {"battery_level": 80, "component": "x".repeat(51)},
+                {"battery_level": 80, "sample_id": "s".repeat(65)},
+            ]
+        }))
+        .unwrap();
+        let violations: Vec<_> = request
+            .data
+            .iter()
+            .map(|r| r.storage_violation())
+            .collect();
+
+        assert!(violations[0].is_none());
+        assert!(violations[1].is_none(), "列长度按字符计算");
+        assert!(violations[2]
+            .as_deref()
+            .unwrap()
+            .contains("battery_data_battery_level_check"));
+        assert!(violations[3].is_some());
+        assert!(violations[4].is_some());
+        assert!(violations[5].is_some());
+    }
+}
+
+mod custom_metrics {
+    use super::*;
+    use serde_json::json;
+    use validator::Validate;
+    use zinnia::models::{
+        metric_value, parse_import, validate_metrics, BatteryAggregateRequest,
+        BatteryExportRequest, BatteryReportRequest, CreateMetricDefinitionRequest, ImportFormat,
+        MetricDefinition, MetricValueType,
+    };
+
+    fn definition(
+        key: &str,
+        value_type: MetricValueType,
+        min_value: Option<f64>,
+        max_value: Option<f64>,
+    ) -> MetricDefinition {
+        MetricDefinition {
+            id: Uuid::new_v4(),
+            device_type: "phone".to_string(),
+            key: key.to_string(),
+            value_type,
+            unit: None,
+            description: None,
+            min_value,
+            max_value,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn metrics(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_validate_metrics() {
+        let definitions = vec![
+            definition(
+                "current_ma",
+                MetricValueType::Integer,
+                Some(-5000.0),
+                Some(5000.0),
+            ),
+            definition("signal", MetricValueType::Float, None, Some(0.0)),
+            definition("screen_on", MetricValueType::Boolean, None, None),
+        ];
+
+        assert!(validate_metrics(
+            &definitions,
+            &metrics(json!({"current_ma": -320, "signal": -71.5, "screen_on": true}))
+        )
+        .is_ok());
+        assert!(
+            validate_metrics(&definitions, &metrics(json!({"current_ma": null}))).is_ok(),
+            "null 视为未上报"
+        );
+
+        for invalid in [
+            json!({"current_ma": 1.5}),
+            json!({"current_ma": "100"}),
+            json!({"current_ma": 6000}),
+            json!({"signal": 3}),
+            json!({"screen_on": 1}),
+            json!({"unknown": 1}),
+        ] {
+            assert!(
+                validate_metrics(&definitions, &metrics(invalid.clone())).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_metric_value() {
+        let values = json!({"current_ma": -320, "screen_on": true, "note": "x", "empty": null});
+        assert_eq!(metric_value(&values, "current_ma"), Some(-320.0));
+        assert_eq!(metric_value(&values, "screen_on"), Some(1.0));
+        assert_eq!(metric_value(&values, "note"), None);
+        assert_eq!(metric_value(&values, "empty"), None);
+        assert_eq!(metric_value(&values, "missing"), None);
+    }
+
+    #[test]
+    fn test_stored_metrics_drops_nulls() {
+        let request: BatteryReportRequest = serde_json::from_str(
+            r#"{"battery_level": 80, "metrics": {"current_ma": -320, "signal": null}}"#,
+        )
+        .unwrap();
+        assert!(request.has_metrics());
+        assert_eq!(request.stored_metrics(), Some(json!({"current_ma": -320})));
+
+        let request: BatteryReportRequest =
+            serde_json::from_str(r#"{"battery_level": 80, "metrics": {"signal": null}}"#).unwrap();
+        assert_eq!(request.stored_metrics(), None);
+
+        let request: BatteryReportRequest =
+            serde_json::from_str(r#"{"battery_level": 80}"#).unwrap();
+        assert!(!request.has_metrics());
+    }
+
+    #[test]
+    fn test_definition_key_format() {
+        let request = |key: &str| -> CreateMetricDefinitionRequest {
+            serde_json::from_value(
+                json!({"device_type": "phone", "key": key, "value_type": "integer"}),
+            )
+            .unwrap()
+        };
+
+        assert!(request("current_ma").validate().is_ok());
+        assert!(request("Current").validate().is_err());
+        assert!(request("1st").validate().is_err());
+        assert!(request("current-ma").validate().is_err());
+        assert!(request(&"a".repeat(51)).validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_aggregate_metric_keys() {
+        let request = |metrics: &str| -> BatteryAggregateRequest {
+            serde_json::from_value(json!({
+                "start_time": "2026-01-01T00:00:00Z",
+                "end_time": "2026-01-02T00:00:00Z",
+                "metrics": metrics
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(
+            request(" current_ma,screen_on,,current_ma ").parse_metric_keys(),
+            Ok(vec!["current_ma".to_string(), "screen_on".to_string()])
+        );
+        let too_many = (0..=BatteryAggregateRequest::MAX_METRICS)
+            .map(|i| format!("m{}", i))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert!(request(&too_many).parse_metric_keys().is_err());
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let data = battery_data()
+            .recorded_at("2026-01-12T10:30:00Z")
+            .level(42)
+            .metrics(json!({"current_ma": -320, "screen_on": true}))
+            .build();
+
+        for (format, import_format) in
+            [("csv", ImportFormat::Csv), ("ndjson", ImportFormat::Ndjson)]
+        {
+            let exporter = serde_json::from_str::<BatteryExportRequest>(&format!(
+                r#"{{"device_ids": "{}", "start_time": "2026-01-01T00:00:00Z", "end_time": "2026-01-02T00:00:00Z", "format": "{}", "columns": "recorded_at,battery_level,metrics"}}"#,
+                Uuid::nil(),
+                format
+            ))
+            .unwrap()
+            .exporter()
+            .unwrap();
+            let mut buf = exporter.header();
+            exporter.write_row(&data, &mut buf);
+
+            let parsed = parse_import(import_format, std::str::from_utf8(&buf).unwrap()).unwrap();
+            assert!(parsed.errors.is_empty(), "{}: {:?}", format, parsed.errors);
+            assert_eq!(
+                parsed.records[0].request.stored_metrics(),
+                data.metrics,
+                "{}",
+                format
+            );
+        }
+
+        let parsed = parse_import(
+            ImportFormat::Csv,
+            "recorded_at,battery_level,metrics\n2026-01-12T10:30:00Z,42,[1]\n",
+        )
+        .unwrap();
+        assert_eq!(parsed.errors.len(), 1, "CSV 中的指标应为 JSON 对象");
+    }
+}
+
+mod battery_components {
+    use super::*;
+    use serde_json::json;
+    use validator::Validate;
+    use zinnia::models::{
+        parse_import, BatteryExportRequest, BatteryReportRequest, CreateDeviceRequest, Device,
+        DeviceStatus, ImportFormat,
+    };
+
+    fn device(components: &[&str]) -> Device {
+        Device {
+            id: Uuid::nil(),
+            owner_id: None,
+            name: "earbuds".to_string(),
+            device_type: "earbuds".to_string(),
+            components: components.iter().map(|c| c.to_string()).collect(),
+            gateway_id: None,
+            status: DeviceStatus::Online,
+            api_key_hash: String::new(),
+            api_key_prefix: String::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            last_seen_at: None,
+            metadata: None,
+            clock_offset_seconds: None,
+            clock_offset_measured_at: None,
+        }
+    }
+
+    #[test]
+    fn test_check_component() {
+        let single = device(&[]);
+        assert!(single.check_component(None).is_ok());
+        assert!(single.check_component(Some("left")).is_err());
+
+        let earbuds = device(&["left", "right", "case"]);
+        assert!(earbuds.check_component(Some("right")).is_ok());
+        assert!(earbuds.check_component(Some("Left")).is_err());
+        let error = earbuds.check_component(None).unwrap_err();
+        assert!(error.contains("left / right / case"), "{}", error);
+    }
+
+    #[test]
+    fn test_validate_declared_components() {
+        let request = |components: serde_json::Value| -> CreateDeviceRequest {
+            serde_json::from_value(
+                json!({"name": "Buds", "device_type": "earbuds", "components": components}),
+            )
+            .unwrap()
+        };
+
+        assert!(request(json!(["left", "right", "case"])).validate().is_ok());
+        assert!(request(json!(["pack-1", "pack_2"])).validate().is_ok());
+        assert!(request(json!([])).validate().is_ok());
+        assert!(request(json!(["Left"])).validate().is_err());
+        assert!(request(json!(["-a"])).validate().is_err());
+        assert!(request(json!(["left", "left"])).validate().is_err());
+        let too_many: Vec<String> = (0..=Device::MAX_COMPONENTS)
+            .map(|i| format!("c{}", i))
+            .collect();
+        assert!(request(json!(too_many)).validate().is_err());
+
+        let request: CreateDeviceRequest =
+            serde_json::from_str(r#"{"name": "Phone", "device_type": "phone"}"#).unwrap();
+        assert!(request.components.is_empty());
+    }
+
+    #[test]
+    fn test_stored_component() {
+        let request: BatteryReportRequest =
+            serde_json::from_str(r#"{"battery_level": 80, "component": "left"}"#).unwrap();
+        assert_eq!(request.stored_component(), "left");
+
+        let request: BatteryReportRequest =
+            serde_json::from_str(r#"{"battery_level": 80}"#).unwrap();
+        assert_eq!(request.stored_component(), "");
+
+        let request: BatteryReportRequest =
+            serde_json::from_str(r#"{"battery_level": 80, "component": ""}"#).unwrap();
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn test_export_import_component() {
+        let mut data = battery_data()
+            .recorded_at("2026-01-12T10:30:00Z")
+            .component("case")
+            .level(64)
+            .charging(true)
+            .build();
+        assert_eq!(data.component_key(), Some("case"));
+
+        for (format, import_format) in
+            [("csv", ImportFormat::Csv), ("ndjson", ImportFormat::Ndjson)]
+        {
+            let exporter = serde_json::from_str::<BatteryExportRequest>(&format!(
+                r#"{{"device_ids": "{}", "start_time": "2026-01-01T00:00:00Z", "end_time": "2026-01-02T00:00:00Z", "format": "{}"}}"#,
+                Uuid::nil(),
+                format
+            ))
+            .unwrap()
+            .exporter()
+            .unwrap();
+            let mut buf = exporter.header();
+            exporter.write_row(&data, &mut buf);
+
+            let parsed = parse_import(import_format, std::str::from_utf8(&buf).unwrap()).unwrap();
+            assert!(parsed.errors.is_empty(), "{}: {:?}", format, parsed.errors);
+            assert_eq!(
+                parsed.records[0].request.component.as_deref(),
+                Some("case"),
+                "{}",
+                format
+            );
+        }
+
+        // 单电池设备的数据不输出组件
+        data.component = String::new();
+        assert_eq!(data.component_key(), None);
+        let row = serde_json::to_value(&data).unwrap();
+        assert!(row.get("component").is_none());
+
+        let parsed = parse_import(
+            ImportFormat::Csv,
+            "recorded_at,battery_level,component\n2026-01-12T10:30:00Z,42,\n",
+        )
+        .unwrap();
+        assert_eq!(parsed.records[0].request.component, None);
+    }
+}
+
+mod gateway_report {
+    use super::*;
+    use validator::Validate;
+    use zinnia::models::{
+        BatchReportResponse, GatewayDeviceResult, GatewayReportRequest, UpdateDeviceRequest,
+    };
+
+    #[test]
+    fn test_gateway_report_request() {
+        let request: GatewayReportRequest = serde_json::from_value(serde_json::json!({
+            "devices": [
+                { "device_id": Uuid::nil(), "data": [{ "battery_level": 80 }] }
+            ],
+            "notify_max_age_seconds": 3600
+        }))
+        .unwrap();
+        assert!(request.validate().is_ok());
+        assert_eq!(request.devices[0].data[0].battery_level, 80);
+
+        let empty: GatewayReportRequest =
+            serde_json::from_value(serde_json::json!({ "devices": [] })).unwrap();
+        assert!(empty.validate().is_err());
+    }
+
+    #[test]
+    fn test_gateway_device_result_serialization() {
+        let ok = GatewayDeviceResult {
+            device_id: Uuid::nil(),
+            result: Some(BatchReportResponse {
+                inserted_count: 2,
+                duplicate_count: 0,
+                rejected_count: 0,
+                rejected: vec![],
+            }),
+            error: None,
+        };
+        let json = serde_json::to_value(&ok).unwrap();
+        assert_eq!(json["inserted_count"], 2);
+        assert!(json.get("error").is_none());
+
+        let failed = GatewayDeviceResult {
+            device_id: Uuid::nil(),
+            result: None,
+            error: Some("设备不属于此网关".to_string()),
+        };
+        let json = serde_json::to_value(&failed).unwrap();
+        assert_eq!(json["error"], "设备不属于此网关");
+        assert!(json.get("inserted_count").is_none());
+    }
+
+    #[test]
+    fn test_update_gateway_nullable() {
+        let unchanged: UpdateDeviceRequest = serde_json::from_str(r#"{"name": "hub"}"#).unwrap();
+        assert_eq!(unchanged.gateway_id, None);
+
+        let cleared: UpdateDeviceRequest = serde_json::from_str(r#"{"gateway_id": null}"#).unwrap();
+        assert_eq!(cleared.gateway_id, Some(None));
+
+        let set: UpdateDeviceRequest =
+            serde_json::from_value(serde_json::json!({ "gateway_id": Uuid::nil() })).unwrap();
+        assert_eq!(set.gateway_id, Some(Some(Uuid::nil())));
+    }
+}
+
+mod clock_skew {
+    use super::*;
+    use chrono::TimeZone;
+    use zinnia::models::{clock_offset_seconds, BatteryReportRequest, ClockFlag, DeviceClock};
+
+    fn report_at(recorded_at: Option<chrono::DateTime<Utc>>) -> BatteryReportRequest {
+        serde_json::from_value(serde_json::json!({
+            "battery_level": 80,
+            "recorded_at": recorded_at,
+        }))
+        .unwrap()
+    }
+
+    fn clock(offset_seconds: Option<i64>, correction: bool) -> DeviceClock {
+        DeviceClock {
+            offset_seconds,
+            correction,
+            tolerance_seconds: 30,
+        }
+    }
+
+    #[test]
+    fn test_clock_offset_seconds() {
+        let now = Utc::now();
+        assert_eq!(clock_offset_seconds(now - Duration::seconds(120), now), 120);
+        assert_eq!(
+            clock_offset_seconds(now + Duration::milliseconds(1600), now),
+            -2
+        );
+        assert_eq!(clock_offset_seconds(now, now), 0);
+    }
+
+    #[test]
+    fn test_apply_within_tolerance() {
+        let now = Utc::now();
+        let device_time = now - Duration::seconds(10);
+        let mut request = report_at(Some(device_time));
+        clock(Some(10), true)
+            .apply(&mut request, now, true)
+            .unwrap();
+        assert_eq!(request.recorded_at, Some(device_time));
+        assert!(request.clock.is_none());
+
+        let mut request = report_at(None);
+        clock(Some(600), true)
+            .apply(&mut request, now, true)
+            .unwrap();
+        assert!(request.recorded_at.is_none());
+        assert!(request.clock.is_none());
+    }
+
+    #[test]
+    fn test_apply_skewed() {
+        let now = Utc::now();
+        let device_time = now - Duration::minutes(5);
+
+        let mut request = report_at(Some(device_time));
+        clock(Some(300), true)
+            .apply(&mut request, now, true)
+            .unwrap();
+        assert_eq!(request.recorded_at, Some(now));
+        let adjustment = request.clock.unwrap();
+        assert_eq!(adjustment.flag, ClockFlag::Corrected);
+        assert_eq!(adjustment.device_recorded_at, device_time);
+
+        let mut request = report_at(Some(device_time));
+        clock(Some(300), false)
+            .apply(&mut request, now, true)
+            .unwrap();
+        assert_eq!(request.recorded_at, Some(device_time));
+        assert_eq!(request.clock.unwrap().flag, ClockFlag::Skewed);
+    }
+
+    #[test]
+    fn test_apply_bogus_time() {
+        let now = Utc::now();
+        let reset = Utc.with_ymd_and_hms(1970, 1, 1, 0, 5, 0).unwrap();
+
+        // 实时上报以接收时间代替，保留原始时间
+        let mut request = report_at(Some(reset));
+        clock(None, false).apply(&mut request, now, true).unwrap();
+        assert_eq!(request.recorded_at, Some(now));
+        let adjustment = request.clock.unwrap();
+        assert_eq!(adjustment.flag, ClockFlag::Replaced);
+        assert_eq!(adjustment.device_recorded_at, reset);
+
+        // 补传的数据无法确定真实时间
+        let mut request = report_at(Some(reset));
+        assert!(clock(None, false).apply(&mut request, now, false).is_err());
+
+        let mut request = report_at(Some(now + Duration::hours(1)));
+        assert!(clock(None, false).apply(&mut request, now, false).is_err());
+
+        // 容差内的未来时间按接收时间记录
+        let mut request = report_at(Some(now + Duration::seconds(5)));
+        clock(None, false).apply(&mut request, now, false).unwrap();
+        assert_eq!(request.recorded_at, Some(now));
+        assert!(request.clock.is_none());
+    }
+
+    #[test]
+    fn test_clock_flag_serialization() {
+        assert_eq!(
+            serde_json::to_value(ClockFlag::Replaced).unwrap(),
+            "replaced"
+        );
+        let request: zinnia::models::BatchBatteryReportRequest =
+            serde_json::from_value(serde_json::json!({
+                "data": [{ "battery_level": 80 }],
+                "sent_at": "2026-01-01T00:00:00Z"
+            }))
+            .unwrap();
+        assert!(request.sent_at.is_some());
+    }
+}
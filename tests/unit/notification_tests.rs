@@ -7,7 +7,7 @@ mod notification_service_tests {
     #[test]
     fn test_should_notify_for_level() {
         // 测试预警级别过滤逻辑
-        use crate::models::{AlertLevel, UserNotificationPreference};
+        use zinnia::models::{AlertLevel, UserNotificationPreference};
         use uuid::Uuid;
 
         let mut pref = UserNotificationPreference {
@@ -18,6 +18,7 @@ mod notification_service_tests {
             webhook_config: None,
             sms_config: None,
             push_config: None,
+            web_push_config: None,
             notify_info: false,
             notify_warning: true,
             notify_critical: true,
@@ -44,10 +45,10 @@ mod notification_service_tests {
     }
 
     fn should_notify_for_level(
-        preference: &crate::models::UserNotificationPreference,
-        level: &crate::models::AlertLevel,
+        preference: &zinnia::models::UserNotificationPreference,
+        level: &zinnia::models::AlertLevel,
     ) -> bool {
-        use crate::models::AlertLevel;
+        use zinnia::models::AlertLevel;
         match level {
             AlertLevel::Info => preference.notify_info,
             AlertLevel::Warning => preference.notify_warning,
@@ -87,16 +88,12 @@ mod notification_service_tests {
 
     #[test]
     fn test_web_push_subscription_parsing() {
-        use crate::models::SubscribeWebPushRequest;
+        use zinnia::models::SubscribeWebPushRequest;
 
         let json = r#"{
-            "subscription": {
-                "endpoint": "https://fcm.googleapis.com/fcm/send/test",
-                "keys": {
-                    "p256dh": "BNcRdreALRFXTkOOUHK1EtK2wtaz5Ry4YfYCA_0QTpQtUbVlUls0VJXg7A8u-Ts1XbjhazAkj7I99e8QcYP7DkM=",
-                    "auth": "tBHItJI5svbpez7KI4CCXg=="
-                }
-            }
+            "endpoint": "https://fcm.googleapis.com/fcm/send/test",
+            "p256dh_key": "BNcRdreALRFXTkOOUHK1EtK2wtaz5Ry4YfYCA_0QTpQtUbVlUls0VJXg7A8u-Ts1XbjhazAkj7I99e8QcYP7DkM=",
+            "auth_secret": "tBHItJI5svbpez7KI4CCXg=="
         }"#;
 
         let result: Result<SubscribeWebPushRequest, _> = serde_json::from_str(json);
@@ -104,7 +101,7 @@ mod notification_service_tests {
 
         let req = result.unwrap();
         assert_eq!(
-            req.subscription.endpoint,
+            req.endpoint,
             "https://fcm.googleapis.com/fcm/send/test"
         );
     }
@@ -158,3 +155,52 @@ mod web_push_payload_tests {
         assert_eq!(notification["actions"].as_array().unwrap().len(), 2);
     }
 }
+
+mod resolution_notification_tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+    use zinnia::models::{
+        is_rate_limited_event, AlertEvent, AlertLevel, AlertStatus, AlertType, NotificationPayload,
+        EVENT_ALERT_RESOLVED, EVENT_ALERT_TRIGGERED,
+    };
+
+    fn payload(status: AlertStatus) -> NotificationPayload {
+        NotificationPayload {
+            alert_event: AlertEvent {
+                id: Uuid::new_v4(),
+                device_id: Uuid::new_v4(),
+                rule_id: Uuid::new_v4(),
+                alert_type: AlertType::LowBattery,
+                level: AlertLevel::Warning,
+                status,
+                message: "电量低于 20%".to_string(),
+                value: 18.0,
+                threshold: 20.0,
+                triggered_at: Utc::now(),
+                acknowledged_at: None,
+                resolved_at: None,
+                metric_key: None,
+                component: None,
+            },
+            device_name: "iPhone".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_resolved_notifications_bypass_rate_limit() {
+        assert!(is_rate_limited_event(EVENT_ALERT_TRIGGERED));
+        assert!(!is_rate_limited_event(EVENT_ALERT_RESOLVED));
+    }
+
+    #[test]
+    fn test_resolved_title() {
+        assert_eq!(
+            payload(AlertStatus::Resolved).title(EVENT_ALERT_RESOLVED),
+            "已恢复 - LowBattery"
+        );
+        assert_eq!(
+            payload(AlertStatus::Active).title(EVENT_ALERT_TRIGGERED),
+            "Warning - LowBattery"
+        );
+    }
+}